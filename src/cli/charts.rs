@@ -0,0 +1,445 @@
+//! Terminal charts for reports: horizontal bars, stacked daily bars and a
+//! calendar heatmap. Rendering is pure so it can be tested without a TTY.
+
+use chrono::{Datelike, Duration, NaiveDate};
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+
+use crate::models::{format_hours_minutes, DailyCommits, ProjectSummary, TimeReport};
+
+/// Unicode eighth blocks, from 1/8 to a full block
+const EIGHTHS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+const UNICODE_SEGMENTS: [char; 4] = ['█', '▓', '▒', '░'];
+const ASCII_SEGMENTS: [char; 5] = ['#', '=', '*', '+', '-'];
+const UNICODE_HEAT: [char; 5] = ['·', '░', '▒', '▓', '█'];
+const ASCII_HEAT: [char; 5] = ['.', '-', '+', '*', '#'];
+const PALETTE: [u8; 6] = [36, 32, 33, 35, 34, 31];
+const HEAT_COLORS: [u8; 5] = [238, 22, 28, 34, 46];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChartStyle {
+    pub unicode: bool,
+    pub color: bool,
+}
+
+impl ChartStyle {
+    /// Pick a style for the current terminal. Color is disabled when
+    /// `NO_COLOR` is set or stdout is not a terminal; Unicode falls back to
+    /// ASCII on dumb terminals, non-UTF-8 locales, or when forced.
+    pub fn detect(force_ascii: bool) -> Self {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        let dumb_terminal = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()));
+        let utf8_locale = match locale {
+            Some(l) => {
+                let l = l.to_lowercase();
+                l.contains("utf-8") || l.contains("utf8")
+            }
+            None => true,
+        };
+
+        Self {
+            unicode: !force_ascii && !dumb_terminal && utf8_locale,
+            color: !no_color && !dumb_terminal && std::io::stdout().is_terminal(),
+        }
+    }
+
+    pub(super) fn paint(&self, text: &str, color: u8) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", color, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn paint_256(&self, text: &str, color: u8) -> String {
+        if self.color {
+            format!("\x1b[38;5;{}m{}\x1b[0m", color, text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Horizontal bar per project, scaled to the largest project
+pub fn render_project_bars(
+    summaries: &[ProjectSummary],
+    width: usize,
+    style: ChartStyle,
+) -> String {
+    let mut summaries: Vec<&ProjectSummary> = summaries.iter().collect();
+    summaries.sort_by(|a, b| {
+        b.total_duration
+            .cmp(&a.total_duration)
+            .then_with(|| a.project_name.cmp(&b.project_name))
+    });
+
+    let max = summaries
+        .first()
+        .map(|s| s.total_duration.num_seconds())
+        .unwrap_or(0);
+    let label_width = summaries
+        .iter()
+        .map(|s| s.project_name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut out = String::new();
+    for (index, summary) in summaries.iter().enumerate() {
+        // Pad before painting so escape codes don't count towards the width
+        let bar = format!(
+            "{:<width$}",
+            bar(summary.total_duration.num_seconds(), max, width, style),
+            width = width
+        );
+        out.push_str(&format!(
+            "{:<label_width$}  {} {}\n",
+            summary.project_name,
+            style.paint(&bar, PALETTE[index % PALETTE.len()]),
+            format_hours_minutes(summary.total_duration),
+            label_width = label_width
+        ));
+    }
+
+    out
+}
+
+/// Horizontal bar per day, scaled to the day with the most commits
+pub fn render_daily_commits(daily: &[DailyCommits], width: usize, style: ChartStyle) -> String {
+    let max = daily.iter().map(|day| day.commits).max().unwrap_or(0) as i64;
+
+    let mut out = String::new();
+    for day in daily {
+        // Pad before painting so escape codes don't count towards the width
+        let bar = format!(
+            "{:<width$}",
+            bar(day.commits as i64, max, width, style),
+            width = width
+        );
+        out.push_str(&format!(
+            "{}  {} {:>3}  {}\n",
+            day.date.format("%a %m-%d"),
+            style.paint(&bar, PALETTE[0]),
+            day.commits,
+            format_hours_minutes(Duration::seconds(day.estimated_seconds))
+        ));
+    }
+
+    out
+}
+
+/// One stacked bar per weekday (Mon–Sun) with a segment per project
+pub fn render_weekly_stacked(report: &TimeReport, width: usize, style: ChartStyle) -> String {
+    let week_start = report.date_range.start.date_naive();
+
+    let mut projects: Vec<String> = report
+        .project_summaries
+        .iter()
+        .map(|s| s.project_name.clone())
+        .collect();
+    projects.sort();
+
+    let mut per_day = vec![vec![0i64; projects.len()]; 7];
+    for entry in &report.entries {
+        let day = (entry.start_time.date_naive() - week_start).num_days();
+        if !(0..7).contains(&day) {
+            continue;
+        }
+        if let Some(index) = projects.iter().position(|p| *p == entry.project_name) {
+            let seconds = entry.duration.map(|d| d.num_seconds()).unwrap_or(0);
+            per_day[day as usize][index] += seconds;
+        }
+    }
+
+    let max_day = per_day
+        .iter()
+        .map(|day| day.iter().sum::<i64>())
+        .max()
+        .unwrap_or(0);
+
+    let mut out = String::new();
+    for (offset, seconds) in per_day.iter().enumerate() {
+        let date = week_start + Duration::days(offset as i64);
+        let mut line = String::new();
+
+        for (index, project_seconds) in seconds.iter().enumerate() {
+            let cells = scaled_cells(*project_seconds, max_day, width);
+            if cells == 0 {
+                continue;
+            }
+            let glyph = segment_glyph(index, style);
+            line.push_str(&style.paint(
+                &glyph.to_string().repeat(cells),
+                PALETTE[index % PALETTE.len()],
+            ));
+        }
+
+        out.push_str(&format!(
+            "{} {}  {} {}\n",
+            date.format("%a"),
+            date.format("%m-%d"),
+            line,
+            format_hours_minutes(Duration::seconds(seconds.iter().sum()))
+        ));
+    }
+
+    if !projects.is_empty() {
+        out.push('\n');
+        for (index, project) in projects.iter().enumerate() {
+            let glyph = segment_glyph(index, style).to_string();
+            out.push_str(&format!(
+                "  {} {}\n",
+                style.paint(&glyph, PALETTE[index % PALETTE.len()]),
+                project
+            ));
+        }
+    }
+
+    out
+}
+
+/// GitHub-style calendar heatmap: weekdays as rows, weeks as columns,
+/// covering the `weeks` weeks that end with the week containing `today`
+pub fn render_heatmap(
+    daily_totals: &BTreeMap<NaiveDate, Duration>,
+    weeks: u32,
+    today: NaiveDate,
+    style: ChartStyle,
+) -> String {
+    let weeks = weeks.max(1) as i64;
+    let current_monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let first_monday = current_monday - Duration::weeks(weeks - 1);
+
+    let max = daily_totals
+        .iter()
+        .filter(|(date, _)| **date >= first_monday && **date <= today)
+        .map(|(_, d)| d.num_seconds())
+        .max()
+        .unwrap_or(0);
+
+    let glyphs = if style.unicode {
+        UNICODE_HEAT
+    } else {
+        ASCII_HEAT
+    };
+
+    let mut out = String::new();
+
+    // Month labels above the first week of each month
+    let mut header = String::from("    ");
+    let mut last_month = None;
+    for week in 0..weeks {
+        let monday = first_monday + Duration::weeks(week);
+        if last_month != Some(monday.month()) {
+            header.push_str(&monday.format("%b").to_string()[..1]);
+            last_month = Some(monday.month());
+        } else {
+            header.push(' ');
+        }
+    }
+    out.push_str(header.trim_end());
+    out.push('\n');
+
+    for weekday in 0..7 {
+        let label = (first_monday + Duration::days(weekday)).format("%a");
+        let mut line = format!("{} ", label);
+        for week in 0..weeks {
+            let date = first_monday + Duration::weeks(week) + Duration::days(weekday);
+            if date > today {
+                line.push(' ');
+                continue;
+            }
+
+            let seconds = daily_totals
+                .get(&date)
+                .map(|d| d.num_seconds())
+                .unwrap_or(0);
+            let level = heat_level(seconds, max);
+            line.push_str(&style.paint_256(&glyphs[level].to_string(), HEAT_COLORS[level]));
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+
+    out.push_str(&format!(
+        "\n    less {} more (max {} per day)\n",
+        glyphs.iter().collect::<String>(),
+        format_hours_minutes(Duration::seconds(max))
+    ));
+
+    out
+}
+
+fn bar(value: i64, max: i64, width: usize, style: ChartStyle) -> String {
+    if max <= 0 || value <= 0 {
+        return String::new();
+    }
+
+    if !style.unicode {
+        return "#".repeat(scaled_cells(value, max, width).max(1));
+    }
+
+    let eighths = ((value as f64 / max as f64) * (width * 8) as f64).round() as usize;
+    let eighths = eighths.max(1);
+    let mut out = EIGHTHS[7].to_string().repeat(eighths / 8);
+    match eighths % 8 {
+        0 => {}
+        rest => out.push(EIGHTHS[rest - 1]),
+    }
+    out
+}
+
+fn scaled_cells(value: i64, max: i64, width: usize) -> usize {
+    if max <= 0 || value <= 0 {
+        return 0;
+    }
+    (((value as f64 / max as f64) * width as f64).round() as usize).max(1)
+}
+
+fn segment_glyph(index: usize, style: ChartStyle) -> char {
+    if style.unicode {
+        UNICODE_SEGMENTS[index % UNICODE_SEGMENTS.len()]
+    } else {
+        ASCII_SEGMENTS[index % ASCII_SEGMENTS.len()]
+    }
+}
+
+/// Map a day's total onto five intensity levels, 0 meaning no time
+fn heat_level(seconds: i64, max: i64) -> usize {
+    if seconds <= 0 || max <= 0 {
+        return 0;
+    }
+    let ratio = seconds as f64 / max as f64;
+    ((ratio * 4.0).ceil() as usize).clamp(1, 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TimeEntry;
+    use chrono::{TimeZone, Utc};
+    use uuid::Uuid;
+
+    const PLAIN_UNICODE: ChartStyle = ChartStyle {
+        unicode: true,
+        color: false,
+    };
+    const PLAIN_ASCII: ChartStyle = ChartStyle {
+        unicode: false,
+        color: false,
+    };
+
+    fn entry(project: &str, day: u32, minutes: i64) -> TimeEntry {
+        let start = Utc.with_ymd_and_hms(2024, 1, day, 9, 0, 0).unwrap();
+        let mut entry = TimeEntry::new(Uuid::new_v4(), project.to_string(), None, start);
+        entry.stop(start + Duration::minutes(minutes)).unwrap();
+        entry
+    }
+
+    #[test]
+    fn test_project_bars_scale_to_largest() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let report = TimeReport::new(
+            vec![entry("Alpha", 1, 120), entry("Beta", 1, 60)],
+            start,
+            start,
+        );
+
+        let chart = render_project_bars(&report.project_summaries, 10, PLAIN_ASCII);
+        let lines: Vec<&str> = chart.lines().collect();
+
+        assert!(lines[0].starts_with("Alpha  ##########"));
+        assert!(lines[1].starts_with("Beta   ##### "));
+        // Durations line up whatever the bar length
+        assert_eq!(
+            lines[0][17..],
+            format!(" {}", format_hours_minutes(Duration::minutes(120)))
+        );
+        assert_eq!(
+            lines[1][17..],
+            format!(" {}", format_hours_minutes(Duration::minutes(60)))
+        );
+
+        let unicode = render_project_bars(&report.project_summaries, 10, PLAIN_UNICODE);
+        assert!(unicode.contains("█████"));
+        // Durations of equal length end both lines in the same column
+        let widths: Vec<usize> = unicode.lines().map(|line| line.chars().count()).collect();
+        assert_eq!(widths[0], widths[1]);
+        assert!(!unicode.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_weekly_stacked_has_row_per_day() {
+        // 2024-01-01 is a Monday
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2024, 1, 7, 23, 59, 59).unwrap();
+        let report = TimeReport::new(
+            vec![
+                entry("Alpha", 1, 60),
+                entry("Beta", 1, 60),
+                entry("Alpha", 3, 30),
+            ],
+            start,
+            end,
+        );
+
+        let chart = render_weekly_stacked(&report, 8, PLAIN_ASCII);
+
+        assert!(chart.starts_with("Mon 01-01  ####==== 2h 0m"));
+        assert!(chart.contains("Wed 01-03  ## 0h 30m"));
+        assert!(chart.contains("Sun 01-07   0h 0m"));
+        assert!(chart.contains("  # Alpha"));
+        assert!(chart.contains("  = Beta"));
+    }
+
+    #[test]
+    fn test_heatmap_levels_and_fallback() {
+        let today = NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(); // Wednesday
+        let mut totals = BTreeMap::new();
+        totals.insert(today, Duration::hours(4));
+        totals.insert(today - Duration::days(1), Duration::hours(1));
+
+        let ascii = render_heatmap(&totals, 2, today, PLAIN_ASCII);
+        let rows: Vec<&str> = ascii.lines().collect();
+
+        assert_eq!(rows[2], "Tue .-");
+        assert_eq!(rows[3], "Wed .#");
+        // Days after today are left blank
+        assert_eq!(rows[4], "Thu .");
+
+        let unicode = render_heatmap(&totals, 2, today, PLAIN_UNICODE);
+        assert!(unicode.contains('█'));
+    }
+
+    #[test]
+    fn test_daily_commits_scale_to_busiest_day() {
+        let day = |d, commits| DailyCommits {
+            date: NaiveDate::from_ymd_opt(2024, 1, d).unwrap(),
+            commits,
+            estimated_seconds: commits as i64 * 1800,
+        };
+        let daily = vec![day(1, 4), day(2, 0), day(3, 2)];
+
+        let out = render_daily_commits(&daily, 8, PLAIN_UNICODE);
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("Mon 01-01  ████████"));
+        assert!(lines[0].ends_with("4  2h 0m"));
+        assert!(!lines[1].contains('█'));
+        assert!(lines[2].contains("████ "));
+    }
+
+    #[test]
+    fn test_color_only_when_enabled() {
+        let colored = ChartStyle {
+            unicode: true,
+            color: true,
+        };
+        assert!(colored.paint("x", 32).contains("\x1b[32m"));
+        assert_eq!(PLAIN_UNICODE.paint("x", 32), "x");
+    }
+}
@@ -1,11 +1,23 @@
-use clap::{Args, Parser, Subcommand};
-use std::path::PathBuf;
+mod charts;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use crate::config::{GitConfig, UserConfig};
+use crate::manifest::{ProjectManifest, MANIFEST_FILE};
+use crate::models::{
+    format_hours_minutes, ActivityGap, BranchTimeSummary, CommitActivityReport, CommitType,
+    EntryDetails, EstimationStrategy, InvoiceGrouping, MergePolicy, PeriodComparison,
+    ProjectChange, ProjectMatch, ReportPeriod, RepositoryActivity, RoundingMode, RoundingRule,
+    RoundingScope, TicketSummary,
+};
 use crate::repository::SqliteRepository;
 use crate::services::{
-    ClientDiscoveryService, DiscoveryOptions, GitService, ProjectService, ReportingService,
-    TimeTrackingService,
+    AuthorFilter, AuthorMatcher, ClientDiscoveryService, CommitQuery, DiscoveryOptions,
+    GitHookService, GitImportOptions, GitService, HookAction, HookKind, HookTarget, InvoiceOptions,
+    InvoiceService, ProjectService, ReportingService, SessionSettings, StoppedTimer,
+    TimeTrackingService, IGNORE_FILE, NOTES_REF,
 };
 use crate::Result;
 
@@ -24,8 +36,17 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Commands {
     Start(StartArgs),
-    Stop,
+    Stop {
+        /// Attach commits made in the project's repository while the timer ran
+        #[arg(long)]
+        link_commits: bool,
+    },
     Status,
+    /// Inspect tracked time entries
+    Entry {
+        #[command(subcommand)]
+        command: EntryCommands,
+    },
     Project {
         #[command(subcommand)]
         command: ProjectCommands,
@@ -38,15 +59,107 @@ pub enum Commands {
         #[command(subcommand)]
         command: GitCommands,
     },
+    /// Create an invoice from a client's billable time
+    Invoice(InvoiceArgs),
 }
 
 #[derive(Args)]
 pub struct StartArgs {
-    pub project: String,
+    /// Project to track; defaults to the one declared by the nearest .timespan.toml
+    pub project: Option<String>,
     #[arg(short, long)]
     pub task: Option<String>,
 }
 
+#[derive(Args)]
+pub struct InvoiceArgs {
+    /// Client to invoice (matches project names, ignoring a [CLIENT] prefix)
+    #[arg(long)]
+    pub client: String,
+    /// First day of the billing period (YYYY-MM-DD)
+    #[arg(long)]
+    pub from: chrono::NaiveDate,
+    /// Last day of the billing period (YYYY-MM-DD)
+    #[arg(long)]
+    pub to: chrono::NaiveDate,
+    /// Group line items by project or by task
+    #[arg(long, default_value = "project")]
+    pub group_by: InvoiceGrouping,
+    /// Round each line item up to this many minutes
+    #[arg(long, default_value = "15")]
+    pub round: i64,
+    /// Hourly rate for projects without their own rate (e.g. 120.50)
+    #[arg(long)]
+    pub rate: Option<String>,
+    /// Currency code shown on the invoice
+    #[arg(long, default_value = "USD")]
+    pub currency: String,
+    /// Output format
+    #[arg(long, value_enum, default_value = "markdown")]
+    pub format: InvoiceFormat,
+    /// Write the rendered invoice to a file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// Preview the invoice without saving it or locking entries
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct RoundingArgs {
+    /// Round durations to this many minutes (e.g. 6 or 15)
+    #[arg(long)]
+    pub round: Option<i64>,
+    /// Rounding direction: up, down or nearest
+    #[arg(long, default_value = RoundingMode::default().as_str(), requires = "round")]
+    pub round_mode: RoundingMode,
+    /// Apply rounding per entry or per daily total
+    #[arg(long, default_value = "entry", requires = "round")]
+    pub round_scope: RoundingScope,
+}
+
+impl RoundingArgs {
+    fn rule(&self) -> Result<Option<RoundingRule>> {
+        match self.round {
+            Some(increment) if increment <= 0 => Err(crate::TimeSpanError::InvalidInput(
+                "--round must be a positive number of minutes".to_string(),
+            )),
+            Some(increment) => Ok(Some(RoundingRule::new(
+                self.round_mode,
+                self.round_scope,
+                increment,
+            ))),
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum InvoiceFormat {
+    Markdown,
+    Html,
+    Json,
+}
+
+/// Output format for reports that can be exported
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    Text,
+    Csv,
+    Json,
+}
+
+#[derive(Subcommand)]
+pub enum EntryCommands {
+    /// Show an entry and the commits linked to it
+    Show {
+        /// Entry id, or a unique prefix of at least 4 characters
+        id: String,
+        #[arg(long)]
+        json: bool,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum ProjectCommands {
     Create {
@@ -55,17 +168,59 @@ pub enum ProjectCommands {
         description: Option<String>,
     },
     List,
+    /// Set or clear a project's hourly billing rate
+    Rate {
+        name: String,
+        /// Hourly rate (e.g. 120 or 120.50)
+        rate: Option<String>,
+        /// Remove the project's rate
+        #[arg(long, conflicts_with = "rate")]
+        clear: bool,
+    },
+    /// Set or clear a project's rounding rule, overriding report defaults
+    Rounding {
+        name: String,
+        /// Increment in minutes (e.g. 6 or 15)
+        #[arg(long, required_unless_present = "clear")]
+        increment: Option<i64>,
+        /// Rounding direction: up, down or nearest
+        #[arg(long, default_value = RoundingMode::default().as_str())]
+        mode: RoundingMode,
+        /// Apply rounding per entry or per daily total
+        #[arg(long, default_value = "entry")]
+        scope: RoundingScope,
+        /// Remove the project's override
+        #[arg(long, conflicts_with = "increment")]
+        clear: bool,
+    },
     /// Discover projects from client directories
     Discover {
-        /// Base path to scan for client directories
-        #[arg(long, default_value = "/Users/user/workspace/Clients")]
-        path: String,
-        /// Prefix to add to discovered project names
-        #[arg(long, default_value = "[CLIENT]")]
-        prefix: String,
+        /// Base path to scan for client directories (repeatable; defaults to
+        /// the roots under [discovery] in config.toml)
+        #[arg(long)]
+        path: Vec<PathBuf>,
+        /// Prefix to add to discovered project names ("" for none; defaults
+        /// to the configured prefix)
+        #[arg(long)]
+        prefix: Option<String>,
+        /// Skip directories matching this glob, by name or path relative to
+        /// the base (repeatable; added to the configured list and .timespanignore)
+        #[arg(long, value_name = "GLOB")]
+        exclude: Vec<String>,
+        /// Only keep project directories matching this glob (repeatable)
+        #[arg(long, value_name = "GLOB")]
+        include: Vec<String>,
+        /// Levels below the base where projects sit, e.g. 2 for
+        /// <base>/<client>/<project>
+        #[arg(long, default_value = "1", value_parser = clap::value_parser!(u32).range(1..))]
+        depth: u32,
         /// Preview mode - show what would be created without actually creating
         #[arg(long)]
         dry_run: bool,
+        /// Archive client projects under the base whose directory is gone
+        /// and wasn't found elsewhere
+        #[arg(long)]
+        prune: bool,
     },
     /// List only client projects
     Clients,
@@ -76,9 +231,116 @@ pub enum ReportCommands {
     Daily {
         #[arg(long)]
         json: bool,
+        #[command(flatten)]
+        rounding: RoundingArgs,
+        #[command(flatten)]
+        chart: ChartArgs,
+    },
+    /// Report for the current week (Monday to Sunday)
+    Weekly {
+        #[arg(long)]
+        json: bool,
+        #[command(flatten)]
+        rounding: RoundingArgs,
+        #[command(flatten)]
+        chart: ChartArgs,
+    },
+    /// Calendar heatmap of tracked time
+    Heatmap {
+        /// Number of weeks to show, ending with the current week
+        #[arg(short, long, default_value = "12")]
+        weeks: u32,
+        /// Use ASCII characters instead of Unicode blocks
+        #[arg(long)]
+        ascii: bool,
+    },
+    /// Weekly timesheet grid with projects as rows and Mon–Sun as columns
+    Timesheet {
+        /// Any date in the week to show (defaults to the current week)
+        #[arg(long)]
+        week_of: Option<chrono::NaiveDate>,
+        #[arg(long, value_enum, default_value = "text")]
+        format: ExportFormat,
+        /// Show decimal hours (7.25) instead of h:mm (7:15)
+        #[arg(long)]
+        decimal: bool,
+    },
+    /// Compare per-project totals with an earlier period
+    Compare {
+        /// Period to compare: day, week or month
+        #[arg(long = "this", default_value = "week")]
+        period: ReportPeriod,
+        /// How many periods back to compare against
+        #[arg(long, num_args = 0..=1, default_value = "1", default_missing_value = "1")]
+        previous: u32,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Commits per day, estimated time and commit types from imported git history
+    Commits {
+        /// Period to report on: day, week or month
+        #[arg(long, default_value = "week")]
+        period: ReportPeriod,
+        #[arg(long)]
+        json: bool,
+        /// Use ASCII characters instead of Unicode blocks
+        #[arg(long)]
+        ascii: bool,
+    },
+    /// Time per ticket or issue referenced in task descriptions and commit
+    /// messages (e.g. #123, ACME-45)
+    Tickets {
+        /// Period to report on: day, week or month
+        #[arg(long, default_value = "week")]
+        period: ReportPeriod,
+        #[arg(long, value_enum, default_value = "text")]
+        format: ExportFormat,
+        /// Index the references of all existing entries again first
+        #[arg(long)]
+        reindex: bool,
+    },
+    /// Time per ticket, taken from the branch each entry was tracked on
+    Branches {
+        /// Period to report on: day, week or month
+        #[arg(long, default_value = "week")]
+        period: ReportPeriod,
+        #[arg(long)]
+        json: bool,
+    },
+    /// Working-tree activity seen by `git watch` that no entry covers
+    Gaps {
+        /// Period to report on: day, week or month
+        #[arg(long, default_value = "day")]
+        period: ReportPeriod,
+        #[arg(long, conflicts_with = "add")]
+        json: bool,
+        /// Save every gap as a time entry tagged git-activity
+        #[arg(long)]
+        add: bool,
+    },
+    /// Repositories ranked by estimated time from imported git history
+    Repos {
+        /// Number of repositories to show
+        #[arg(long, default_value = "10")]
+        top: usize,
+        /// Only count commits from the current day, week or month
+        #[arg(long)]
+        period: Option<ReportPeriod>,
+        #[arg(long)]
+        json: bool,
     },
 }
 
+#[derive(Args)]
+pub struct ChartArgs {
+    /// Draw bar charts in addition to the totals
+    #[arg(long)]
+    pub chart: bool,
+    /// Use ASCII characters instead of Unicode blocks
+    #[arg(long, requires = "chart")]
+    pub ascii: bool,
+}
+
 #[derive(Subcommand)]
 pub enum GitCommands {
     /// Analyze recent commits in current directory
@@ -89,20 +351,170 @@ pub enum GitCommands {
         /// Specific repository path to analyze
         #[arg(short, long)]
         repo: Option<PathBuf>,
+        /// Most commits to analyze
+        #[arg(long, default_value = "20")]
+        limit: usize,
+        /// Only commits by this email (plus its aliases) or author name;
+        /// defaults to the repository's user.email
+        #[arg(long, conflicts_with = "all_authors")]
+        author: Option<String>,
+        /// Include commits by every author
+        #[arg(long)]
+        all_authors: bool,
+        /// Ref, revision or glob to read commits from (repeatable); defaults
+        /// to all local branches
+        #[arg(long = "ref", value_name = "REF")]
+        refs: Vec<String>,
+        /// What to do with merge commits: skip or zero (keep, with no time)
+        #[arg(long)]
+        merges: Option<MergePolicy>,
     },
     /// Show git integration status
     Status,
     /// Import commits from a repository and create time entries
     Import {
         /// Repository path to import from
-        #[arg(short, long)]
+        #[arg(short, long, conflicts_with = "scan")]
         repo: Option<PathBuf>,
+        /// Import every git repository found under this directory
+        #[arg(long, value_name = "DIR", conflicts_with = "project")]
+        scan: Option<PathBuf>,
+        /// How many directory levels --scan descends
+        #[arg(long, default_value = "5", requires = "scan")]
+        depth: usize,
         /// Number of days to import
         #[arg(short, long, default_value = "30")]
         days: u32,
+        /// Most commits to read per repository (defaults to [history]
+        /// max_commits in git-config.toml, 50 unless set)
+        #[arg(long)]
+        limit: Option<usize>,
         /// Project name to associate commits with
         #[arg(short, long)]
         project: Option<String>,
+        /// Show what would be imported without saving anything
+        #[arg(long)]
+        dry_run: bool,
+        /// How to estimate time per commit: heuristic or session
+        #[arg(long, default_value = "heuristic")]
+        estimator: EstimationStrategy,
+        /// Minutes between commits that start a new session (session estimator)
+        #[arg(long, default_value = "120")]
+        session_gap: i64,
+        /// Minutes credited to the first commit of a session (session estimator)
+        #[arg(long, default_value = "30")]
+        lead_in: i64,
+        /// Only commits by this email (plus its aliases) or author name;
+        /// defaults to the repository's user.email
+        #[arg(long, conflicts_with = "all_authors")]
+        author: Option<String>,
+        /// Include commits by every author
+        #[arg(long)]
+        all_authors: bool,
+        /// Ref, revision or glob to read commits from (repeatable); defaults
+        /// to all local branches
+        #[arg(long = "ref", value_name = "REF")]
+        refs: Vec<String>,
+        /// What to do with merge commits: skip or zero (keep, with no time)
+        #[arg(long)]
+        merges: Option<MergePolicy>,
+    },
+    /// Write tracked or estimated time per commit to git notes
+    /// (refs/notes/timespan); view them with `git log --notes=timespan`
+    Annotate {
+        /// Repository to annotate (defaults to the current directory)
+        #[arg(short, long)]
+        repo: Option<PathBuf>,
+        /// Number of days to look back
+        #[arg(short, long, default_value = "30")]
+        days: u32,
+        /// Most commits to annotate (defaults to [history] max_commits in
+        /// git-config.toml, 50 unless set)
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Only commits by this email (plus its aliases) or author name;
+        /// defaults to the repository's user.email
+        #[arg(long, conflicts_with = "all_authors")]
+        author: Option<String>,
+        /// Include commits by every author
+        #[arg(long)]
+        all_authors: bool,
+        /// Ref, revision or glob to read commits from (repeatable); defaults
+        /// to all local branches
+        #[arg(long = "ref", value_name = "REF")]
+        refs: Vec<String>,
+        /// Delete every TimeSpan note instead of writing them
+        #[arg(long, conflicts_with_all = ["days", "limit", "author", "all_authors", "refs"])]
+        remove: bool,
+    },
+    /// Fit estimate multipliers from commits made while a timer was running
+    Calibrate {
+        /// Repository to calibrate (defaults to the current directory)
+        #[arg(short, long)]
+        repo: Option<PathBuf>,
+        /// Show the fitted multipliers without saving them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Install (or remove) the post-commit hook that records each commit
+    Setup {
+        /// Repository to install into (defaults to the current directory)
+        #[arg(short, long, conflicts_with = "global")]
+        repo: Option<PathBuf>,
+        /// Install into the global git hooks directory instead
+        #[arg(long)]
+        global: bool,
+        /// Remove the hook instead of installing it
+        #[arg(long)]
+        uninstall: bool,
+        /// Set up the post-checkout hook that moves the running timer to the
+        /// new branch instead
+        #[arg(long)]
+        post_checkout: bool,
+    },
+    /// Record a single commit (called by the post-commit hook)
+    RecordCommit {
+        /// Commit hash or revision to record
+        #[arg(short, long, default_value = "HEAD")]
+        commit: String,
+        /// Repository containing the commit (defaults to the current directory)
+        #[arg(short, long)]
+        repo: Option<PathBuf>,
+        /// Project name to associate the commit with
+        #[arg(short, long)]
+        project: Option<String>,
+    },
+    /// Move the running timer to the checked-out branch (called by the
+    /// post-checkout hook; needs `[branches] enabled = true`)
+    SyncBranch {
+        /// Repository whose branch to follow (defaults to the current directory)
+        #[arg(short, long)]
+        repo: Option<PathBuf>,
+    },
+    /// Sample the working trees of project directories and record activity
+    /// pulses, so untracked work shows up in status and `report gaps`
+    Watch {
+        /// Seconds between samples
+        #[arg(long, default_value = "60")]
+        interval: u64,
+        /// Take one sample and exit (e.g. from cron)
+        #[arg(long)]
+        once: bool,
+    },
+    /// Inspect estimation settings from git-config.toml
+    Config {
+        #[command(subcommand)]
+        command: GitConfigCommands,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum GitConfigCommands {
+    /// Print the effective estimation settings and where each value comes from
+    Show {
+        /// Repository whose overrides to apply (defaults to the current directory)
+        #[arg(short, long)]
+        repo: Option<PathBuf>,
     },
 }
 
@@ -161,6 +573,33 @@ mod input_validation {
         Ok(sanitized)
     }
 
+    /// Parses a money amount such as `120` or `120.50` into minor units
+    pub fn parse_amount_cents(amount: &str) -> Result<i64> {
+        let invalid = || TimeSpanError::InvalidInput(format!("Invalid amount: {}", amount));
+        let amount = amount.trim();
+
+        let (whole, fraction) = match amount.split_once('.') {
+            Some((whole, fraction)) => (whole, fraction),
+            None => (amount, ""),
+        };
+
+        if whole.is_empty()
+            || fraction.len() > 2
+            || !whole.chars().all(|c| c.is_ascii_digit())
+            || !fraction.chars().all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let whole: i64 = whole.parse().map_err(|_| invalid())?;
+        let fraction: i64 = format!("{:0<2}", fraction).parse().map_err(|_| invalid())?;
+
+        whole
+            .checked_mul(100)
+            .and_then(|cents| cents.checked_add(fraction))
+            .ok_or_else(invalid)
+    }
+
     /// Basic input sanitization
     fn sanitize_input(input: &str) -> Result<String> {
         // Remove null bytes and other control characters
@@ -252,6 +691,428 @@ pub fn sanitize_error_message(error: &crate::TimeSpanError) -> String {
         crate::TimeSpanError::InvalidDuration(msg) => {
             format!("Invalid duration format: {}", msg)
         }
+        crate::TimeSpanError::Config(msg) => {
+            // Points at the user's own config file and key
+            format!("Invalid configuration: {}", msg)
+        }
+        crate::TimeSpanError::TimeEntryLocked(id) => {
+            format!(
+                "Time entry {} is already invoiced and cannot be changed",
+                id
+            )
+        }
+    }
+}
+
+/// `--ref`/`--merges` overrides shared by git analyze, import and annotate
+struct HistoryOptions {
+    refs: Vec<String>,
+    merges: Option<MergePolicy>,
+    limit: Option<usize>,
+}
+
+impl HistoryOptions {
+    fn query(
+        &self,
+        days: u32,
+        default_limit: usize,
+        authors: Option<AuthorMatcher>,
+    ) -> CommitQuery {
+        CommitQuery {
+            since: Some(chrono::Utc::now() - chrono::Duration::days(days as i64)),
+            limit: Some(self.limit.unwrap_or(default_limit)),
+            authors,
+            refs: (!self.refs.is_empty()).then(|| self.refs.clone()),
+            merges: self.merges,
+        }
+    }
+}
+
+fn author_filter(author: Option<String>, all_authors: bool) -> AuthorFilter {
+    match author {
+        _ if all_authors => AuthorFilter::All,
+        Some(author) => AuthorFilter::Author(author),
+        None => AuthorFilter::CurrentUser,
+    }
+}
+
+fn print_author_scope(filter: &AuthorFilter, authors: Option<&AuthorMatcher>) {
+    match (authors, filter) {
+        (Some(authors), _) => println!("👤 Author: {}", authors.describe()),
+        (None, AuthorFilter::CurrentUser) => {
+            println!("👤 Author: all (no user.email configured; use --author to filter)")
+        }
+        (None, _) => println!("👤 Author: all"),
+    }
+}
+
+fn print_timesheet(timesheet: &crate::models::Timesheet, decimal: bool) {
+    use crate::models::Timesheet;
+
+    let name_width = timesheet
+        .rows
+        .iter()
+        .map(|row| row.project_name.chars().count())
+        .chain(std::iter::once("Total".len()))
+        .max()
+        .unwrap_or(0);
+    let cell_width = 7;
+
+    let last_day = timesheet.days.last().unwrap_or(&timesheet.week_start);
+    println!(
+        "🗓️  Timesheet {} – {}",
+        timesheet.week_start.format("%Y-%m-%d"),
+        last_day.format("%Y-%m-%d")
+    );
+    println!();
+
+    let mut header = format!("{:<width$}", "Project", width = name_width);
+    for day in &timesheet.days {
+        header.push_str(&format!(
+            " {:>width$}",
+            day.format("%a %d").to_string(),
+            width = cell_width
+        ));
+    }
+    header.push_str(&format!(" {:>width$}", "Total", width = cell_width));
+    println!("{}", header);
+    println!("{}", "-".repeat(header.chars().count()));
+
+    let print_row = |name: &str, cells: &[i64], total: i64| {
+        let mut line = format!("{:<width$}", name, width = name_width);
+        for seconds in cells {
+            let cell = if *seconds == 0 {
+                "-".to_string()
+            } else {
+                Timesheet::format_seconds(*seconds, decimal)
+            };
+            line.push_str(&format!(" {:>width$}", cell, width = cell_width));
+        }
+        line.push_str(&format!(
+            " {:>width$}",
+            Timesheet::format_seconds(total, decimal),
+            width = cell_width
+        ));
+        println!("{}", line);
+    };
+
+    for row in &timesheet.rows {
+        print_row(&row.project_name, &row.daily_seconds, row.total_seconds);
+    }
+    println!("{}", "-".repeat(header.chars().count()));
+    print_row("Total", &timesheet.daily_totals, timesheet.total_seconds);
+}
+
+/// Signed "+Xh Ym" / "-Xh Ym" rendering of a difference in seconds
+fn format_signed_seconds(seconds: i64) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    format!(
+        "{}{}",
+        sign,
+        format_hours_minutes(chrono::Duration::seconds(seconds.abs()))
+    )
+}
+
+fn format_percent_change(percent: Option<f64>) -> String {
+    match percent {
+        Some(p) => format!("{:+.1}%", p),
+        None => "n/a".to_string(),
+    }
+}
+
+fn print_comparison(comparison: &PeriodComparison, style: charts::ChartStyle) {
+    let range = |r: &crate::models::DateRange| {
+        if r.start.date_naive() == r.end.date_naive() {
+            r.start.format("%Y-%m-%d").to_string()
+        } else {
+            format!(
+                "{} – {}",
+                r.start.format("%Y-%m-%d"),
+                r.end.format("%Y-%m-%d")
+            )
+        }
+    };
+
+    println!(
+        "📊 {} comparison: {} vs {}",
+        comparison.period.as_str(),
+        range(&comparison.current_range),
+        range(&comparison.previous_range)
+    );
+    println!(
+        "Total: {} vs {} ({}, {})",
+        format_hours_minutes(chrono::Duration::seconds(comparison.current_seconds)),
+        format_hours_minutes(chrono::Duration::seconds(comparison.previous_seconds)),
+        format_signed_seconds(comparison.delta_seconds),
+        format_percent_change(comparison.percent_change)
+    );
+
+    if comparison.projects.is_empty() {
+        println!("No time tracked in either period");
+        return;
+    }
+
+    println!();
+    for delta in &comparison.projects {
+        let line = format!(
+            "  {}: {} vs {} ({}, {})",
+            delta.project_name,
+            format_hours_minutes(chrono::Duration::seconds(delta.current_seconds)),
+            format_hours_minutes(chrono::Duration::seconds(delta.previous_seconds)),
+            format_signed_seconds(delta.delta_seconds),
+            format_percent_change(delta.percent_change)
+        );
+        match delta.change {
+            ProjectChange::Appeared => println!("{} 🆕 new", style.paint(&line, 32)),
+            ProjectChange::Disappeared => println!("{} 🚫 gone", style.paint(&line, 31)),
+            _ => println!("{}", line),
+        }
+    }
+}
+
+fn print_commit_activity(
+    report: &CommitActivityReport,
+    period: ReportPeriod,
+    style: charts::ChartStyle,
+) {
+    println!(
+        "📈 Commit activity this {}: {} – {}",
+        period.as_str(),
+        report.date_range.start.format("%Y-%m-%d"),
+        report.date_range.end.format("%Y-%m-%d")
+    );
+
+    if report.total_commits == 0 {
+        println!("No imported commits in this period; run 'timespan git import' first");
+        return;
+    }
+
+    println!(
+        "Commits: {}  Estimated: {}  Average per commit: {}",
+        report.total_commits,
+        format_hours_minutes(chrono::Duration::seconds(report.estimated_seconds)),
+        format_hours_minutes(chrono::Duration::seconds(report.average_seconds))
+    );
+    if let Some(confidence) = report.average_confidence {
+        println!("Average confidence: {:.0}%", confidence * 100.0);
+    }
+    if let Some(day) = &report.most_active_day {
+        println!(
+            "Most active day: {} {} ({} commits)",
+            day.date.format("%A"),
+            day.date.format("%Y-%m-%d"),
+            day.commits
+        );
+    }
+
+    println!();
+    print!("{}", charts::render_daily_commits(&report.daily, 20, style));
+
+    println!();
+    println!("By repository:");
+    for repo in &report.repositories {
+        println!(
+            "  {}: {} commits, {}",
+            repo.name,
+            repo.commits,
+            format_hours_minutes(chrono::Duration::seconds(repo.estimated_seconds))
+        );
+    }
+
+    println!();
+    println!("By type:");
+    for activity in &report.commit_types {
+        println!(
+            "  {}: {} commits, {}",
+            activity.commit_type.as_str(),
+            activity.commits,
+            format_hours_minutes(chrono::Duration::seconds(activity.estimated_seconds))
+        );
+    }
+}
+
+fn print_entry_details(details: &EntryDetails) {
+    let entry = &details.entry;
+    println!("🧾 Entry {}", entry.id);
+    println!("   Project: {}", entry.project_name);
+    if let Some(task) = &entry.task_description {
+        println!("   Task: {}", task);
+    }
+    match entry.end_time {
+        Some(end) => println!(
+            "   Time: {} – {} ({})",
+            entry.start_time.format("%Y-%m-%d %H:%M"),
+            end.format("%H:%M"),
+            format_hours_minutes(entry.duration.unwrap_or_else(chrono::Duration::zero))
+        ),
+        None => println!(
+            "   Time: {} – running",
+            entry.start_time.format("%Y-%m-%d %H:%M")
+        ),
+    }
+    if let Some(branch) = &entry.branch {
+        println!("   Branch: {}", branch);
+    }
+    if !entry.tags.is_empty() {
+        println!("   Tags: {}", entry.tags.join(", "));
+    }
+    if entry.invoice_id.is_some() {
+        println!("   🔒 Invoiced");
+    }
+
+    if entry.commit_hashes.is_empty() {
+        println!("   No linked commits");
+        return;
+    }
+
+    println!();
+    println!("Commits ({}):", entry.commit_hashes.len());
+    for commit in &details.commits {
+        println!(
+            "  {} {} {}",
+            &commit.hash[..commit.hash.len().min(8)],
+            commit.timestamp.format("%H:%M"),
+            commit.message.lines().next().unwrap_or_default()
+        );
+    }
+    for hash in &details.missing_commits {
+        println!(
+            "  {} (no longer in the repository)",
+            &hash[..hash.len().min(8)]
+        );
+    }
+}
+
+fn print_ticket_report(summaries: &[TicketSummary], period: ReportPeriod) {
+    println!("🎫 Time per reference this {}", period.as_str());
+
+    if summaries.is_empty() {
+        println!("No tickets or issues referenced in this period");
+        return;
+    }
+
+    for summary in summaries {
+        let commits = if summary.commit_count > 0 {
+            format!(", {} from commits", summary.commit_count)
+        } else {
+            String::new()
+        };
+        println!(
+            "  {}: {} ({:.2}h, {} entries{})",
+            summary.reference,
+            format_hours_minutes(chrono::Duration::seconds(summary.total_seconds)),
+            summary.total_seconds as f64 / 3600.0,
+            summary.entry_count,
+            commits
+        );
+        println!("      Projects: {}", summary.projects.join(", "));
+    }
+    println!();
+    println!("Entries that mention several references count towards each of them.");
+}
+
+fn print_branch_report(summaries: &[BranchTimeSummary], period: ReportPeriod) {
+    println!("🎫 Time per ticket this {}", period.as_str());
+
+    if summaries.is_empty() {
+        println!("No time tracked in this period");
+        return;
+    }
+
+    for summary in summaries {
+        println!(
+            "  {}: {} ({} entries)",
+            summary.key,
+            format_hours_minutes(chrono::Duration::seconds(summary.total_seconds)),
+            summary.entry_count
+        );
+        if summary.ticket.is_some() && !summary.branches.is_empty() {
+            println!("      Branches: {}", summary.branches.join(", "));
+        }
+        println!("      Projects: {}", summary.projects.join(", "));
+    }
+}
+
+fn print_activity_gaps(gaps: &[ActivityGap], period: ReportPeriod) {
+    println!("🕳️  Untracked activity this {}", period.as_str());
+
+    if gaps.is_empty() {
+        println!("No untracked activity (pulses come from 'timespan git watch')");
+        return;
+    }
+
+    for gap in gaps {
+        println!(
+            "  {} – {}  {:<24} {} ({} samples)",
+            gap.start.format("%Y-%m-%d %H:%M"),
+            gap.end.format("%H:%M"),
+            gap.project_name,
+            format_hours_minutes(gap.duration()),
+            gap.pulse_count
+        );
+    }
+    let total = gaps.iter().fold(chrono::Duration::zero(), |total, gap| {
+        total + gap.duration()
+    });
+    println!();
+    println!("Total: {}", format_hours_minutes(total));
+}
+
+fn print_repository_activity(repositories: &[RepositoryActivity], period: Option<ReportPeriod>) {
+    match period {
+        Some(period) => println!("🗂️ Most active repositories this {}", period.as_str()),
+        None => println!("🗂️ Most active repositories"),
+    }
+
+    if repositories.is_empty() {
+        println!("No imported commits; run 'timespan git import' first");
+        return;
+    }
+
+    for (rank, repo) in repositories.iter().enumerate() {
+        println!();
+        println!(
+            "{:>2}. {} ({})",
+            rank + 1,
+            repo.name,
+            repo.repository_path.display()
+        );
+        println!(
+            "    Estimated: {}  Commits: {}  Confidence: {:.0}%",
+            format_hours_minutes(chrono::Duration::seconds(repo.estimated_seconds)),
+            repo.commits,
+            repo.average_confidence * 100.0
+        );
+        println!(
+            "    Projects: {}  Last commit: {}",
+            repo.projects.join(", "),
+            repo.last_commit_at.format("%Y-%m-%d %H:%M")
+        );
+    }
+}
+
+/// Print raw and rounded per-project totals side by side
+fn print_rounded_summaries(report: &crate::models::TimeReport) {
+    if report.rounded_total_duration.is_none() {
+        return;
+    }
+
+    let mut summaries: Vec<_> = report.project_summaries.iter().collect();
+    summaries.sort_by(|a, b| a.project_name.cmp(&b.project_name));
+
+    for summary in summaries {
+        let rounded = summary.rounded_duration.unwrap_or(summary.total_duration);
+        let rule = summary
+            .rounding_rule
+            .map(|rule| format!(" ({})", rule))
+            .unwrap_or_default();
+        println!(
+            "  {}: {} → {}{}",
+            summary.project_name,
+            format_hours_minutes(summary.total_duration),
+            format_hours_minutes(rounded),
+            rule
+        );
     }
 }
 
@@ -261,6 +1122,7 @@ pub struct TimeSpanApp {
     reporting_service: ReportingService,
     client_discovery_service: ClientDiscoveryService,
     git_service: GitService,
+    invoice_service: InvoiceService,
 }
 
 impl TimeSpanApp {
@@ -294,42 +1156,96 @@ impl TimeSpanApp {
 
         let repository = Arc::new(SqliteRepository::new(&db_path)?);
 
+        let git_config = GitConfig::load().unwrap_or_else(|e| {
+            eprintln!(
+                "⚠️  {} (using default estimation settings)",
+                sanitize_error_message(&e)
+            );
+            GitConfig::default()
+        });
+        let user_config = UserConfig::load().unwrap_or_else(|e| {
+            eprintln!(
+                "⚠️  {} (using default discovery settings)",
+                sanitize_error_message(&e)
+            );
+            UserConfig::default()
+        });
+
         Ok(Self {
             project_service: ProjectService::new(repository.clone()),
-            tracking_service: TimeTrackingService::new(repository.clone()),
+            tracking_service: TimeTrackingService::with_references(
+                repository.clone(),
+                git_config.references.clone(),
+            ),
             reporting_service: ReportingService::new(repository.clone()),
-            client_discovery_service: ClientDiscoveryService::new(repository.clone()),
-            git_service: GitService::new(repository),
+            client_discovery_service: ClientDiscoveryService::with_config(
+                repository.clone(),
+                user_config.discovery,
+            ),
+            git_service: GitService::with_config(repository.clone(), git_config),
+            invoice_service: InvoiceService::new(repository),
         })
     }
 
     pub async fn run(&self, cli: Cli) -> Result<()> {
         match cli.command {
             Commands::Start(args) => self.handle_start(args).await,
-            Commands::Stop => self.handle_stop().await,
+            Commands::Stop { link_commits } => self.handle_stop(link_commits).await,
             Commands::Status => self.handle_status().await,
+            Commands::Entry { command } => self.handle_entry(command).await,
             Commands::Project { command } => self.handle_project(command).await,
             Commands::Report { command } => self.handle_report(command).await,
             Commands::Git { command } => self.handle_git(command).await,
+            Commands::Invoice(args) => self.handle_invoice(args).await,
         }
     }
 
     async fn handle_start(&self, args: StartArgs) -> Result<()> {
+        let current_dir = std::env::current_dir().unwrap_or_default();
+
         // Validate and sanitize inputs
-        let project = input_validation::validate_project_name(&args.project)?;
+        let (project, rule) = match &args.project {
+            Some(name) => (input_validation::validate_project_name(name)?, None),
+            None => match self.resolve_start_project(&current_dir).await {
+                Ok((project, rule)) => (project.name, Some(rule)),
+                Err(e) => {
+                    eprintln!("Error: {}", sanitize_error_message(&e));
+                    return Err(e);
+                }
+            },
+        };
         let task = if let Some(task_desc) = args.task {
             Some(input_validation::validate_task_description(&task_desc)?)
         } else {
             None
         };
 
+        let repository = self.git_service.repository_root(&current_dir);
+        let branch = self.git_service.current_branch(&current_dir);
         match self
             .tracking_service
-            .start_timer(&project, task.as_deref())
+            .start_timer_on_branch(
+                &project,
+                task.as_deref(),
+                repository.as_deref(),
+                branch.as_deref(),
+                self.git_service.branches(),
+            )
             .await
         {
             Ok(timer) => {
                 println!("Started tracking time for '{}'", timer.project_name);
+                if let Some(rule) = &rule {
+                    println!("   📍 Matched by {}", rule.describe());
+                    // Default tags from the manifest that declares this project
+                    if let Ok(Some(manifest)) = ProjectManifest::find(&current_dir) {
+                        if manifest.project_name() == timer.project_name {
+                            for tag in manifest.tags {
+                                self.tracking_service.add_tag_to_active_timer(tag).await?;
+                            }
+                        }
+                    }
+                }
                 Ok(())
             }
             Err(e) => {
@@ -339,9 +1255,45 @@ impl TimeSpanApp {
         }
     }
 
-    async fn handle_stop(&self) -> Result<()> {
-        match self.tracking_service.stop_timer().await {
-            Ok(entry) => {
+    /// Project for `timespan start` without a project argument
+    async fn resolve_start_project(
+        &self,
+        dir: &Path,
+    ) -> Result<(crate::models::Project, ProjectMatch)> {
+        // Broken manifests are reported only if the lookup gets that far
+        if let Ok(Some(manifest)) = ProjectManifest::find(dir) {
+            input_validation::validate_project_name(&manifest.project_name())?;
+        }
+        self.project_service
+            .resolve_directory_project(dir, &self.git_service)
+            .await?
+            .ok_or_else(|| {
+                crate::TimeSpanError::InvalidInput(format!(
+                    "No project for {}: no project directory contains it, no project is \
+                     named after its git repository and no {} was found",
+                    dir.display(),
+                    MANIFEST_FILE
+                ))
+            })
+    }
+
+    async fn handle_stop(&self, link_commits: bool) -> Result<()> {
+        let stopped = if link_commits {
+            self.tracking_service
+                .stop_timer_with_commits(&self.git_service)
+                .await
+        } else {
+            self.tracking_service
+                .stop_timer()
+                .await
+                .map(|entry| StoppedTimer {
+                    entry,
+                    link_error: None,
+                })
+        };
+
+        match stopped {
+            Ok(StoppedTimer { entry, link_error }) => {
                 let duration = entry.duration.unwrap();
                 let hours = duration.num_hours();
                 let minutes = duration.num_minutes() % 60;
@@ -349,6 +1301,20 @@ impl TimeSpanApp {
                     "Stopped tracking time for '{}' ({}h {}m)",
                     entry.project_name, hours, minutes
                 );
+                if let Some(e) = link_error {
+                    eprintln!("⚠️  Could not link commits: {}", sanitize_error_message(&e));
+                } else if link_commits {
+                    match entry.commit_hashes.len() {
+                        0 => println!(
+                            "   No commits found in the project's directory while the timer ran"
+                        ),
+                        count => println!(
+                            "   🔗 Linked {} commit(s); see 'timespan entry show {}'",
+                            count,
+                            &entry.id.to_string()[..8]
+                        ),
+                    }
+                }
                 Ok(())
             }
             Err(e) => {
@@ -358,9 +1324,95 @@ impl TimeSpanApp {
         }
     }
 
+    async fn handle_entry(&self, command: EntryCommands) -> Result<()> {
+        match command {
+            EntryCommands::Show { id, json } => {
+                let entry = match self.tracking_service.find_entry(&id).await {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        eprintln!("Error: {}", sanitize_error_message(&e));
+                        return Err(e);
+                    }
+                };
+                let details = self
+                    .tracking_service
+                    .entry_details(entry, &self.git_service)
+                    .await?;
+
+                if json {
+                    println!("{}", self.tracking_service.export_entry_json(&details)?);
+                } else {
+                    print_entry_details(&details);
+                }
+                Ok(())
+            }
+        }
+    }
+
     async fn handle_status(&self) -> Result<()> {
+        if self.git_service.branches().enabled {
+            if let Ok(dir) = std::env::current_dir() {
+                if let Err(e) = self.sync_timer_branch(&dir).await {
+                    eprintln!(
+                        "⚠️  Could not switch the timer to the current branch: {}",
+                        sanitize_error_message(&e)
+                    );
+                }
+            }
+        }
+
         let status = self.tracking_service.get_current_status().await?;
         println!("{}", status);
+
+        let today = ReportPeriod::Day.range_containing(chrono::Utc::now());
+        let gaps = self.reporting_service.find_activity_gaps(&today).await?;
+        if !gaps.is_empty() {
+            let total = gaps.iter().fold(chrono::Duration::zero(), |total, gap| {
+                total + gap.duration()
+            });
+            println!(
+                "🕳️  {} of untracked activity today in {} stretch(es); see 'timespan report gaps'",
+                format_hours_minutes(total),
+                gaps.len()
+            );
+        }
+        Ok(())
+    }
+
+    /// Split the running timer when the branch checked out in `path` differs
+    /// from the one it was started on in the same repository
+    async fn sync_timer_branch(&self, path: &Path) -> Result<()> {
+        let branches = self.git_service.branches();
+        if !branches.enabled {
+            return Ok(());
+        }
+
+        let repository = self.git_service.repository_root(path);
+        let branch = self.git_service.current_branch(path);
+        if let Some(switch) = self
+            .tracking_service
+            .switch_branch(repository.as_deref(), branch.as_deref(), branches)
+            .await?
+        {
+            println!(
+                "🔀 Switched to branch '{}': saved {} on '{}'",
+                switch.timer.branch.as_deref().unwrap_or_default(),
+                format_hours_minutes(
+                    switch
+                        .stopped
+                        .duration
+                        .unwrap_or_else(chrono::Duration::zero)
+                ),
+                switch.stopped.branch.as_deref().unwrap_or_default()
+            );
+            match &switch.ticket {
+                Some(ticket) => println!(
+                    "   Now tracking '{}' for ticket {}",
+                    switch.timer.project_name, ticket
+                ),
+                None => println!("   Now tracking '{}'", switch.timer.project_name),
+            }
+        }
         Ok(())
     }
 
@@ -407,64 +1459,497 @@ impl TimeSpanApp {
                             .as_deref()
                             .map(|p| format!(" ({})", p))
                             .unwrap_or_default();
-                        println!("  - {}{}{}", project.name, client_marker, path_info);
+                        let archived_marker = if project.is_archived() {
+                            " [archived]"
+                        } else {
+                            ""
+                        };
+                        println!(
+                            "  - {}{}{}{}",
+                            project.name, client_marker, path_info, archived_marker
+                        );
                     }
                 }
                 Ok(())
             }
+            ProjectCommands::Rate { name, rate, clear } => {
+                let rate_cents = match (rate, clear) {
+                    (Some(rate), false) => Some(input_validation::parse_amount_cents(&rate)?),
+                    (None, true) => None,
+                    _ => {
+                        return Err(crate::TimeSpanError::InvalidInput(
+                            "Provide a rate or --clear".to_string(),
+                        ))
+                    }
+                };
+
+                match self
+                    .project_service
+                    .set_hourly_rate(&name, rate_cents)
+                    .await
+                {
+                    Ok(_) => {
+                        match rate_cents {
+                            Some(cents) => println!(
+                                "Set hourly rate for '{}' to {}.{:02}",
+                                name,
+                                cents / 100,
+                                cents % 100
+                            ),
+                            None => println!("Cleared hourly rate for '{}'", name),
+                        }
+                        Ok(())
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", sanitize_error_message(&e));
+                        Err(e)
+                    }
+                }
+            }
+            ProjectCommands::Rounding {
+                name,
+                increment,
+                mode,
+                scope,
+                clear,
+            } => {
+                let rule = match increment {
+                    Some(increment) if increment > 0 && !clear => {
+                        Some(RoundingRule::new(mode, scope, increment))
+                    }
+                    None if clear => None,
+                    _ => {
+                        return Err(crate::TimeSpanError::InvalidInput(
+                            "Increment must be a positive number of minutes".to_string(),
+                        ))
+                    }
+                };
+
+                match self.project_service.set_rounding_rule(&name, rule).await {
+                    Ok(_) => {
+                        match rule {
+                            Some(rule) => println!("Rounding for '{}': {}", name, rule),
+                            None => println!("Cleared rounding override for '{}'", name),
+                        }
+                        Ok(())
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", sanitize_error_message(&e));
+                        Err(e)
+                    }
+                }
+            }
             ProjectCommands::Discover {
                 path,
                 prefix,
+                exclude,
+                include,
+                depth,
                 dry_run,
-            } => self.handle_project_discover(path, prefix, dry_run).await,
+                prune,
+            } => {
+                let mut options =
+                    DiscoveryOptions::from_config(self.client_discovery_service.config());
+                if !path.is_empty() {
+                    options.roots = path;
+                } else if options.roots.is_empty() {
+                    let e = crate::TimeSpanError::InvalidInput(format!(
+                        "No path given and no discovery roots configured; pass --path or \
+                         set roots under [discovery] in {}",
+                        UserConfig::default_path().display()
+                    ));
+                    eprintln!("❌ Discovery failed: {}", sanitize_error_message(&e));
+                    return Err(e);
+                }
+                if let Some(prefix) = prefix {
+                    let prefix = prefix.trim();
+                    options.project_prefix = (!prefix.is_empty()).then(|| prefix.to_string());
+                }
+                options.exclude_patterns.extend(exclude);
+                options.include_patterns = include;
+                options.depth = depth as usize;
+                options.dry_run = dry_run;
+                options.prune = prune;
+                self.handle_project_discover(options).await
+            }
             ProjectCommands::Clients => self.handle_list_client_projects().await,
         }
     }
 
     async fn handle_report(&self, command: ReportCommands) -> Result<()> {
         match command {
-            ReportCommands::Daily { json } => {
-                let report = self
+            ReportCommands::Daily {
+                json,
+                rounding,
+                chart,
+            } => {
+                let mut report = self
                     .reporting_service
                     .generate_daily_report(chrono::Utc::now())
                     .await?;
+                self.reporting_service
+                    .apply_rounding(&mut report, rounding.rule()?)
+                    .await?;
+
+                if json {
+                    let json_output = self.reporting_service.export_report_json(&report)?;
+                    println!("{}", json_output);
+                } else {
+                    match report.rounded_total_duration {
+                        Some(rounded) => println!(
+                            "Daily Report: Total time {} (rounded: {})",
+                            format_hours_minutes(report.total_duration),
+                            format_hours_minutes(rounded)
+                        ),
+                        None => println!(
+                            "Daily Report: Total time {}",
+                            format_hours_minutes(report.total_duration)
+                        ),
+                    }
+                    print_rounded_summaries(&report);
+
+                    if chart.chart && !report.project_summaries.is_empty() {
+                        let style = charts::ChartStyle::detect(chart.ascii);
+                        println!();
+                        print!(
+                            "{}",
+                            charts::render_project_bars(&report.project_summaries, 40, style)
+                        );
+                    }
+                }
+                Ok(())
+            }
+            ReportCommands::Weekly {
+                json,
+                rounding,
+                chart,
+            } => {
+                let mut report = self
+                    .reporting_service
+                    .generate_weekly_report(chrono::Utc::now())
+                    .await?;
+                self.reporting_service
+                    .apply_rounding(&mut report, rounding.rule()?)
+                    .await?;
 
                 if json {
                     let json_output = self.reporting_service.export_report_json(&report)?;
                     println!("{}", json_output);
+                    return Ok(());
+                }
+
+                println!(
+                    "Weekly Report ({} – {})",
+                    report.date_range.start.format("%Y-%m-%d"),
+                    report.date_range.end.format("%Y-%m-%d")
+                );
+                match report.rounded_total_duration {
+                    Some(rounded) => println!(
+                        "Total time {} (rounded: {})",
+                        format_hours_minutes(report.total_duration),
+                        format_hours_minutes(rounded)
+                    ),
+                    None => println!("Total time {}", format_hours_minutes(report.total_duration)),
+                }
+
+                if report.rounded_total_duration.is_some() {
+                    print_rounded_summaries(&report);
+                } else {
+                    let mut summaries: Vec<_> = report.project_summaries.iter().collect();
+                    summaries.sort_by(|a, b| a.project_name.cmp(&b.project_name));
+                    for summary in summaries {
+                        println!(
+                            "  {}: {}",
+                            summary.project_name,
+                            format_hours_minutes(summary.total_duration)
+                        );
+                    }
+                }
+
+                if chart.chart && !report.entries.is_empty() {
+                    let style = charts::ChartStyle::detect(chart.ascii);
+                    println!();
+                    print!(
+                        "{}",
+                        charts::render_project_bars(&report.project_summaries, 40, style)
+                    );
+                    println!();
+                    print!("{}", charts::render_weekly_stacked(&report, 40, style));
+                }
+                Ok(())
+            }
+            ReportCommands::Heatmap { weeks, ascii } => {
+                use chrono::Datelike;
+
+                if weeks == 0 || weeks > 104 {
+                    return Err(crate::TimeSpanError::InvalidInput(
+                        "--weeks must be between 1 and 104".to_string(),
+                    ));
+                }
+
+                let today = chrono::Utc::now().date_naive();
+                let first_day = today
+                    - chrono::Duration::days(today.weekday().num_days_from_monday() as i64)
+                    - chrono::Duration::weeks(weeks as i64 - 1);
+                let totals = self
+                    .reporting_service
+                    .generate_daily_totals(first_day, today)
+                    .await?;
+
+                let style = charts::ChartStyle::detect(ascii);
+                println!("Tracked time, last {} weeks", weeks);
+                println!();
+                print!("{}", charts::render_heatmap(&totals, weeks, today, style));
+                Ok(())
+            }
+            ReportCommands::Timesheet {
+                week_of,
+                format,
+                decimal,
+            } => {
+                let date = match week_of {
+                    Some(day) => day.and_hms_opt(12, 0, 0).unwrap().and_utc(),
+                    None => chrono::Utc::now(),
+                };
+                let timesheet = self.reporting_service.generate_timesheet(date).await?;
+
+                match format {
+                    ExportFormat::Text => print_timesheet(&timesheet, decimal),
+                    ExportFormat::Csv => print!(
+                        "{}",
+                        self.reporting_service
+                            .export_timesheet_csv(&timesheet, decimal)
+                    ),
+                    ExportFormat::Json => println!(
+                        "{}",
+                        self.reporting_service.export_timesheet_json(&timesheet)?
+                    ),
+                }
+                Ok(())
+            }
+            ReportCommands::Compare {
+                period,
+                previous,
+                json,
+            } => {
+                let comparison = self
+                    .reporting_service
+                    .compare_periods(period, chrono::Utc::now(), previous)
+                    .await?;
+
+                if json {
+                    let json_output = self.reporting_service.export_comparison_json(&comparison)?;
+                    println!("{}", json_output);
+                } else {
+                    print_comparison(&comparison, charts::ChartStyle::detect(false));
+                }
+                Ok(())
+            }
+            ReportCommands::Commits {
+                period,
+                json,
+                ascii,
+            } => {
+                let range = period.range_containing(chrono::Utc::now());
+                let report = self.git_service.commit_activity(range).await?;
+
+                if json {
+                    println!("{}", self.git_service.export_activity_json(&report)?);
+                } else {
+                    print_commit_activity(&report, period, charts::ChartStyle::detect(ascii));
+                }
+                Ok(())
+            }
+            ReportCommands::Tickets {
+                period,
+                format,
+                reindex,
+            } => {
+                if reindex {
+                    let result = self
+                        .tracking_service
+                        .reindex_references(&self.git_service)
+                        .await?;
+                    if !result.missing_commits.is_empty() {
+                        eprintln!(
+                            "⚠️  {} linked commits are no longer in their repository and weren't indexed",
+                            result.missing_commits.len()
+                        );
+                    }
+                    eprintln!("Indexed references of {} entries", result.entries);
+                }
+
+                let range = period.range_containing(chrono::Utc::now());
+                let summaries = self
+                    .reporting_service
+                    .generate_ticket_report(&range)
+                    .await?;
+
+                match format {
+                    ExportFormat::Text => print_ticket_report(&summaries, period),
+                    ExportFormat::Csv => print!(
+                        "{}",
+                        self.reporting_service.export_ticket_report_csv(&summaries)
+                    ),
+                    ExportFormat::Json => println!(
+                        "{}",
+                        self.reporting_service
+                            .export_ticket_report_json(&summaries)?
+                    ),
+                }
+                Ok(())
+            }
+            ReportCommands::Branches { period, json } => {
+                let range = period.range_containing(chrono::Utc::now());
+                let summaries = self
+                    .reporting_service
+                    .generate_branch_report(&range, self.git_service.branches())
+                    .await?;
+
+                if json {
+                    println!(
+                        "{}",
+                        self.reporting_service
+                            .export_branch_report_json(&summaries)?
+                    );
                 } else {
-                    let total_hours = report.total_duration.num_hours();
-                    let total_minutes = report.total_duration.num_minutes() % 60;
+                    print_branch_report(&summaries, period);
+                }
+                Ok(())
+            }
+            ReportCommands::Gaps { period, json, add } => {
+                let range = period.range_containing(chrono::Utc::now());
+                let gaps = self.reporting_service.find_activity_gaps(&range).await?;
+
+                if json {
+                    println!("{}", self.reporting_service.export_gap_report_json(&gaps)?);
+                    return Ok(());
+                }
+                print_activity_gaps(&gaps, period);
+                if !add && !gaps.is_empty() {
+                    println!(
+                        "   Save them as entries with 'timespan report gaps --period {} --add'",
+                        period.as_str()
+                    );
+                }
+                if add && !gaps.is_empty() {
+                    let entries = self.tracking_service.add_activity_entries(&gaps).await?;
+                    println!();
                     println!(
-                        "Daily Report: Total time {}h {}m",
-                        total_hours, total_minutes
+                        "✅ Added {} entries tagged git-activity ({})",
+                        entries.len(),
+                        format_hours_minutes(
+                            entries
+                                .iter()
+                                .filter_map(|entry| entry.duration)
+                                .fold(chrono::Duration::zero(), |total, d| total + d)
+                        )
                     );
                 }
                 Ok(())
             }
+            ReportCommands::Repos { top, period, json } => {
+                if top == 0 {
+                    return Err(crate::TimeSpanError::InvalidInput(
+                        "--top must be at least 1".to_string(),
+                    ));
+                }
+                let range = period.map(|period| period.range_containing(chrono::Utc::now()));
+                let repositories = self
+                    .git_service
+                    .repository_activity(range.as_ref(), top)
+                    .await?;
+
+                if json {
+                    println!("{}", self.git_service.export_activity_json(&repositories)?);
+                } else {
+                    print_repository_activity(&repositories, period);
+                }
+                Ok(())
+            }
         }
     }
 
-    async fn handle_project_discover(
-        &self,
-        path: String,
-        prefix: String,
-        dry_run: bool,
-    ) -> Result<()> {
-        use std::path::PathBuf;
+    async fn handle_invoice(&self, args: InvoiceArgs) -> Result<()> {
+        let client = input_validation::validate_project_name(&args.client)?;
+        if args.to < args.from {
+            return Err(crate::TimeSpanError::InvalidInput(
+                "--to must not be before --from".to_string(),
+            ));
+        }
+        if args.round < 0 {
+            return Err(crate::TimeSpanError::InvalidInput(
+                "--round must not be negative".to_string(),
+            ));
+        }
 
-        let options = DiscoveryOptions {
-            base_path: PathBuf::from(path.clone()),
-            exclude_patterns: DiscoveryOptions::default().exclude_patterns,
-            project_prefix: if prefix.is_empty() {
-                None
-            } else {
-                Some(prefix)
-            },
-            dry_run,
+        let default_rate_cents = match args.rate {
+            Some(rate) => Some(input_validation::parse_amount_cents(&rate)?),
+            None => None,
+        };
+
+        let options = InvoiceOptions {
+            client,
+            start: args.from.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+            end: args.to.and_hms_opt(23, 59, 59).unwrap().and_utc(),
+            grouping: args.group_by,
+            rounding_increment_minutes: args.round,
+            default_rate_cents,
+            currency: args.currency,
+            dry_run: args.dry_run,
+        };
+
+        let invoice = match self.invoice_service.create_invoice(&options).await {
+            Ok(invoice) => invoice,
+            Err(e) => {
+                eprintln!("Error: {}", sanitize_error_message(&e));
+                return Err(e);
+            }
+        };
+
+        let rendered = match args.format {
+            InvoiceFormat::Markdown => self.invoice_service.render_markdown(&invoice),
+            InvoiceFormat::Html => self.invoice_service.render_html(&invoice),
+            InvoiceFormat::Json => self.invoice_service.export_invoice_json(&invoice)?,
         };
 
-        println!("🔍 Discovering client projects in: {}", path);
+        match args.output {
+            Some(path) => {
+                std::fs::write(&path, rendered)?;
+                println!("Wrote invoice {} to {}", invoice.number, path.display());
+            }
+            None => println!("{}", rendered),
+        }
+
+        if args.dry_run {
+            eprintln!("👁️ Preview only - invoice not saved and entries not locked.");
+        } else {
+            eprintln!(
+                "🔒 Invoice {} saved; {} time entries locked.",
+                invoice.number,
+                invoice.entry_ids().len()
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn handle_project_discover(&self, options: DiscoveryOptions) -> Result<()> {
+        let dry_run = options.dry_run;
+
+        for root in &options.roots {
+            if !root.is_dir() {
+                eprintln!("⚠️  Skipping missing directory: {}", root.display());
+                continue;
+            }
+            println!("🔍 Discovering client projects in: {}", root.display());
+            if root.join(IGNORE_FILE).is_file() {
+                println!("🙈 Applying {}", root.join(IGNORE_FILE).display());
+            }
+        }
+        if options.depth > 1 {
+            println!("📐 Projects {} levels down", options.depth);
+        }
         if dry_run {
             println!("👀 Running in preview mode - no projects will be created");
         }
@@ -485,6 +1970,13 @@ impl TimeSpanApp {
                     for dir in &result.discovered_directories {
                         let git_marker = if dir.is_git_repo { " 🔄" } else { "" };
                         println!("  • {}{}", dir.name, git_marker);
+                        if let Some(manifest) = &dir.manifest {
+                            println!(
+                                "    📄 {} declares '{}'",
+                                MANIFEST_FILE,
+                                manifest.project_name()
+                            );
+                        }
                         if let Some(desc) = &dir.suggested_description {
                             println!("    {}", desc);
                         }
@@ -512,6 +2004,49 @@ impl TimeSpanApp {
                     println!();
                 }
 
+                if !result.moved_projects.is_empty() {
+                    println!(
+                        "🚚 {} {} projects:",
+                        if dry_run { "Would move" } else { "Moved" },
+                        result.moved_projects.len()
+                    );
+                    for moved in &result.moved_projects {
+                        println!(
+                            "  > {} (matched by {})",
+                            moved.project.name,
+                            moved.evidence.as_str()
+                        );
+                        println!(
+                            "    {} -> {}",
+                            moved.previous_path.display(),
+                            moved.project.directory_path.as_deref().unwrap_or_default()
+                        );
+                    }
+                    println!();
+                }
+
+                if !result.orphaned_projects.is_empty() {
+                    println!(
+                        "👻 {} projects whose directory is gone:",
+                        result.orphaned_projects.len()
+                    );
+                    for project in &result.orphaned_projects {
+                        println!(
+                            "  ? {} ({})",
+                            project.name,
+                            project.directory_path.as_deref().unwrap_or_default()
+                        );
+                    }
+                    if !result.archived_projects.is_empty() {
+                        println!("  🗄️ Archived {}", result.archived_projects.len());
+                    } else if options.prune {
+                        println!("  🗄️ Would archive them");
+                    } else {
+                        println!("  💡 Use --prune to archive them");
+                    }
+                    println!();
+                }
+
                 if !result.skipped_directories.is_empty() {
                     println!(
                         "⏭️ Skipped {} directories:",
@@ -535,7 +2070,10 @@ impl TimeSpanApp {
                 if dry_run {
                     println!("👁️ Preview completed. Use without --dry-run to create projects.");
                 } else {
-                    let total = result.created_projects.len() + result.updated_projects.len();
+                    let total = result.created_projects.len()
+                        + result.updated_projects.len()
+                        + result.moved_projects.len()
+                        + result.archived_projects.len();
                     if total > 0 {
                         println!("🎉 Successfully processed {} project(s)!", total);
                     } else {
@@ -586,40 +2124,177 @@ impl TimeSpanApp {
 
     async fn handle_git(&self, command: GitCommands) -> Result<()> {
         match command {
-            GitCommands::Analyze { days, repo } => self.handle_git_analyze(days, repo).await,
+            GitCommands::Analyze {
+                days,
+                repo,
+                limit,
+                author,
+                all_authors,
+                refs,
+                merges,
+            } => {
+                let history = HistoryOptions {
+                    refs,
+                    merges,
+                    limit: Some(limit),
+                };
+                self.handle_git_analyze(days, repo, author_filter(author, all_authors), history)
+                    .await
+            }
             GitCommands::Status => self.handle_git_status().await,
             GitCommands::Import {
                 repo,
+                scan,
+                depth,
                 days,
+                limit,
                 project,
-            } => self.handle_git_import(repo, days, project).await,
-        }
-    }
-
-    async fn handle_git_analyze(&self, days: u32, repo_path: Option<PathBuf>) -> Result<()> {
-        let path = repo_path.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
-
-        println!("🔍 Analyzing git commits from: {}", path.display());
-        println!("📅 Looking back {} days", days);
-        println!();
-
-        let since = chrono::Utc::now() - chrono::Duration::days(days as i64);
-        match self
-            .git_service
-            .get_commits(&path, Some(since), Some(20))
-            .await
-        {
-            Ok(commits) => {
-                if commits.is_empty() {
-                    println!("📭 No commits found in the specified time range.");
-                    return Ok(());
+                dry_run,
+                estimator,
+                session_gap,
+                lead_in,
+                author,
+                all_authors,
+                refs,
+                merges,
+            } => {
+                if session_gap <= 0 || lead_in < 0 {
+                    return Err(crate::TimeSpanError::InvalidInput(
+                        "--session-gap must be positive and --lead-in not negative".to_string(),
+                    ));
                 }
-
-                println!("📊 Found {} commits:", commits.len());
-                println!();
-
+                let options = GitImportOptions {
+                    strategy: estimator,
+                    session: SessionSettings {
+                        max_gap: chrono::Duration::minutes(session_gap),
+                        lead_in: chrono::Duration::minutes(lead_in),
+                    },
+                    dry_run,
+                };
+                let author = author_filter(author, all_authors);
+                let history = HistoryOptions {
+                    refs,
+                    merges,
+                    limit,
+                };
+                match scan {
+                    Some(root) => {
+                        self.handle_git_import_scan(root, depth, days, author, history, options)
+                            .await
+                    }
+                    None => {
+                        self.handle_git_import(repo, days, project, author, history, options)
+                            .await
+                    }
+                }
+            }
+            GitCommands::Watch { interval, once } => self.handle_git_watch(interval, once).await,
+            GitCommands::Config {
+                command: GitConfigCommands::Show { repo },
+            } => self.handle_git_config_show(repo),
+            GitCommands::Annotate {
+                repo,
+                days,
+                limit,
+                author,
+                all_authors,
+                refs,
+                remove,
+            } => {
+                let history = HistoryOptions {
+                    refs,
+                    merges: None,
+                    limit,
+                };
+                self.handle_git_annotate(
+                    repo,
+                    days,
+                    author_filter(author, all_authors),
+                    history,
+                    remove,
+                )
+                .await
+            }
+            GitCommands::Calibrate { repo, dry_run } => {
+                self.handle_git_calibrate(repo, dry_run).await
+            }
+            GitCommands::Setup {
+                repo,
+                global,
+                uninstall,
+                post_checkout,
+            } => {
+                let kind = if post_checkout {
+                    HookKind::PostCheckout
+                } else {
+                    HookKind::PostCommit
+                };
+                self.handle_git_setup(repo, global, uninstall, kind)
+            }
+            GitCommands::SyncBranch { repo } => {
+                // Runs from the post-checkout hook: report problems but never fail
+                let path = repo.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+                if let Err(e) = self.sync_timer_branch(&path).await {
+                    eprintln!(
+                        "⚠️  Could not switch the timer to the new branch: {}",
+                        sanitize_error_message(&e)
+                    );
+                }
+                Ok(())
+            }
+            GitCommands::RecordCommit {
+                commit,
+                repo,
+                project,
+            } => {
+                // Runs from the post-commit hook: report problems but never fail
+                if let Err(e) = self.handle_git_record_commit(&commit, repo, project).await {
+                    eprintln!(
+                        "⚠️  Could not record commit: {}",
+                        sanitize_error_message(&e)
+                    );
+                }
+                Ok(())
+            }
+        }
+    }
+
+    async fn handle_git_analyze(
+        &self,
+        days: u32,
+        repo_path: Option<PathBuf>,
+        author: AuthorFilter,
+        history: HistoryOptions,
+    ) -> Result<()> {
+        let path = repo_path.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+
+        println!("🔍 Analyzing git commits from: {}", path.display());
+        println!("📅 Looking back {} days", days);
+
+        let authors = match self.git_service.resolve_authors(&path, &author) {
+            Ok(authors) => authors,
+            Err(e) => {
+                eprintln!("Error: {}", sanitize_error_message(&e));
+                return Err(e);
+            }
+        };
+        print_author_scope(&author, authors.as_ref());
+        println!();
+
+        let query = history.query(days, 20, authors);
+        match self.git_service.get_commits(&path, &query).await {
+            Ok(commits) => {
+                if commits.is_empty() {
+                    println!("📭 No commits found in the specified time range.");
+                    return Ok(());
+                }
+
+                println!("📊 Found {} commits:", commits.len());
+                println!();
+
                 let mut total_estimated_time = chrono::Duration::zero();
                 let mut commit_types = std::collections::HashMap::new();
+                let mut scopes = std::collections::BTreeMap::new();
 
                 for commit in &commits {
                     let analysis = self.git_service.analyze_commit(commit).await?;
@@ -643,15 +2318,25 @@ impl TimeSpanApp {
                         commit.insertions,
                         commit.deletions
                     );
+                    let commit_type = match &analysis.scope {
+                        Some(scope) => format!("{}({})", analysis.commit_type.as_str(), scope),
+                        None => analysis.commit_type.as_str().to_string(),
+                    };
                     println!(
-                        "   Type: {:?}, Confidence: {:.1}%",
-                        analysis.commit_type,
+                        "   Type: {}, Confidence: {:.1}%",
+                        commit_type,
                         analysis.complexity_score * 100.0
                     );
                     println!();
 
                     total_estimated_time += analysis.estimated_duration;
                     *commit_types.entry(analysis.commit_type).or_insert(0) += 1;
+                    if let Some(scope) = analysis.scope {
+                        let (count, time) =
+                            scopes.entry(scope).or_insert((0, chrono::Duration::zero()));
+                        *count += 1;
+                        *time += analysis.estimated_duration;
+                    }
                 }
 
                 let total_hours = total_estimated_time.num_hours();
@@ -666,7 +2351,28 @@ impl TimeSpanApp {
                     "   Average per commit: {}m",
                     total_estimated_time.num_minutes() / commits.len() as i64
                 );
-                println!("   Commit types: {:?}", commit_types);
+                let mut commit_types: Vec<_> = commit_types.into_iter().collect();
+                commit_types
+                    .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.as_str().cmp(b.0.as_str())));
+                println!(
+                    "   Commit types: {}",
+                    commit_types
+                        .iter()
+                        .map(|(commit_type, count)| format!("{} {}", commit_type.as_str(), count))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                if !scopes.is_empty() {
+                    println!("   By scope:");
+                    for (scope, (count, time)) in &scopes {
+                        println!(
+                            "     {:<16} {} commits, {}",
+                            scope,
+                            count,
+                            format_hours_minutes(*time)
+                        );
+                    }
+                }
 
                 // Try to detect associated project
                 if let Ok(Some(project_name)) = self.git_service.detect_project(&path).await {
@@ -703,6 +2409,32 @@ impl TimeSpanApp {
                     }
                 }
 
+                let hook_target = HookTarget::Repository(current_dir.clone());
+                let hook_service = GitHookService::new();
+                if hook_service
+                    .is_installed(&hook_target, HookKind::PostCommit)
+                    .unwrap_or(false)
+                {
+                    println!("   Post-commit hook: installed");
+                } else {
+                    println!("   Post-commit hook: not installed (run 'timespan git setup')");
+                }
+                if let Some(branch) = self.git_service.current_branch(&current_dir) {
+                    println!("   Branch: {}", branch);
+                }
+                if self.git_service.branches().enabled {
+                    if hook_service
+                        .is_installed(&hook_target, HookKind::PostCheckout)
+                        .unwrap_or(false)
+                    {
+                        println!("   Post-checkout hook: installed");
+                    } else {
+                        println!(
+                            "   Post-checkout hook: not installed (run 'timespan git setup --post-checkout')"
+                        );
+                    }
+                }
+
                 // Try to detect associated project
                 match self.git_service.detect_project(&current_dir).await {
                     Ok(Some(project_name)) => {
@@ -744,58 +2476,467 @@ impl TimeSpanApp {
         println!("Available commands:");
         println!("   timespan git analyze     # Analyze recent commits");
         println!("   timespan git import      # Import commits as time entries");
+        println!("   timespan git setup       # Record every commit automatically");
 
         Ok(())
     }
 
-    async fn handle_git_import(
+    async fn handle_git_calibrate(&self, repo_path: Option<PathBuf>, dry_run: bool) -> Result<()> {
+        let path = repo_path.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+
+        if dry_run {
+            println!("🔍 DRY RUN MODE - No changes will be made");
+        }
+        println!("🎯 Calibrating git time estimates for {}", path.display());
+        println!();
+
+        let calibration = match self.git_service.calibrate(&path, dry_run).await {
+            Ok(calibration) => calibration,
+            Err(e) => {
+                eprintln!("Error: {}", sanitize_error_message(&e));
+                return Err(e);
+            }
+        };
+
+        println!(
+            "   Commits matched to tracked time: {}",
+            calibration.sample_count
+        );
+
+        let print_multipliers =
+            |title: &str, multipliers: &std::collections::HashMap<String, f32>| {
+                if multipliers.is_empty() {
+                    println!("   {}: not enough samples", title);
+                    return;
+                }
+                println!("   {}:", title);
+                let mut multipliers: Vec<_> = multipliers.iter().collect();
+                multipliers.sort_by(|a, b| a.0.cmp(b.0));
+                for (key, multiplier) in multipliers {
+                    println!("     {:<14} ×{:.2}", key, multiplier);
+                }
+            };
+        print_multipliers(
+            "Commit type multipliers",
+            &calibration.commit_type_multipliers,
+        );
+        print_multipliers("File type multipliers", &calibration.file_type_multipliers);
+
+        println!();
+        println!(
+            "📏 Mean estimate error: {} before → {} after",
+            format_hours_minutes(calibration.mean_error_before),
+            format_hours_minutes(calibration.mean_error_after)
+        );
+        if dry_run {
+            println!("   Run without --dry-run to save and use these multipliers");
+        } else {
+            println!("✅ Calibration saved; future estimates for this repository use it");
+        }
+
+        Ok(())
+    }
+
+    async fn handle_git_watch(&self, interval: u64, once: bool) -> Result<()> {
+        if interval == 0 {
+            return Err(crate::TimeSpanError::InvalidInput(
+                "--interval must be at least 1 second".to_string(),
+            ));
+        }
+        if !once {
+            println!(
+                "👀 Watching project repositories every {}s (Ctrl-C to stop)",
+                interval
+            );
+        }
+
+        loop {
+            // Re-read projects each round so new ones are picked up
+            for project in self.project_service.list_projects().await? {
+                match self.git_service.sample_activity(&project).await {
+                    Ok(Some(pulse)) => println!(
+                        "{} {}: {} files, {} lines changed",
+                        pulse.sampled_at.format("%H:%M:%S"),
+                        pulse.project_name,
+                        pulse.changed_files,
+                        pulse.lines_changed
+                    ),
+                    Ok(None) => {}
+                    Err(e) => eprintln!(
+                        "⚠️  Could not sample '{}': {}",
+                        project.name,
+                        sanitize_error_message(&e)
+                    ),
+                }
+            }
+
+            if once {
+                return Ok(());
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+        }
+    }
+
+    async fn handle_git_annotate(
         &self,
         repo_path: Option<PathBuf>,
         days: u32,
-        project_name: Option<String>,
+        author: AuthorFilter,
+        history: HistoryOptions,
+        remove: bool,
     ) -> Result<()> {
         let path = repo_path.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
 
-        println!("📥 Importing git commits to TimeSpan");
+        if remove {
+            return match self.git_service.remove_notes(&path) {
+                Ok(0) => {
+                    println!("📭 No notes under {} in {}", NOTES_REF, path.display());
+                    Ok(())
+                }
+                Ok(removed) => {
+                    println!("🗑️  Removed {} note(s) from {}", removed, NOTES_REF);
+                    Ok(())
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", sanitize_error_message(&e));
+                    Err(e)
+                }
+            };
+        }
+
+        println!("📝 Annotating commits in {}", path.display());
+        println!("📅 Looking back {} days", days);
+
+        let authors = match self.git_service.resolve_authors(&path, &author) {
+            Ok(authors) => authors,
+            Err(e) => {
+                eprintln!("Error: {}", sanitize_error_message(&e));
+                return Err(e);
+            }
+        };
+        print_author_scope(&author, authors.as_ref());
+        println!();
+
+        let query = history.query(days, self.git_service.history().max_commits, authors);
+        let commits = self.git_service.get_commits(&path, &query).await?;
+        if commits.is_empty() {
+            println!("📭 No commits found in the specified time range.");
+            return Ok(());
+        }
+
+        let result = match self.git_service.annotate_commits(&path, &commits).await {
+            Ok(result) => result,
+            Err(e) => {
+                eprintln!("Error: {}", sanitize_error_message(&e));
+                return Err(e);
+            }
+        };
+
+        for time in &result.written {
+            println!(
+                "   {} {} ({}) {}",
+                &time.commit.hash[..8],
+                format_hours_minutes(time.duration),
+                time.source.as_str(),
+                time.commit.message.lines().next().unwrap_or("")
+            );
+        }
+        if !result.written.is_empty() {
+            println!();
+        }
+        println!(
+            "✅ Wrote {} note(s) to {}, {} already up to date",
+            result.written.len(),
+            NOTES_REF,
+            result.unchanged.len()
+        );
+        println!("   View them with 'git log --notes=timespan'");
+        println!(
+            "   Share them with 'git push origin {}' (teammates fetch the same ref)",
+            NOTES_REF
+        );
+
+        Ok(())
+    }
+
+    fn handle_git_config_show(&self, repo_path: Option<PathBuf>) -> Result<()> {
+        let path = repo_path.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+
+        // Re-read the file so a broken config is reported instead of skipped
+        let config = match GitConfig::load() {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Error: {}", sanitize_error_message(&e));
+                return Err(e);
+            }
+        };
+
+        match &config.source {
+            Some(source) => println!("⚙️  Git estimation config: {}", source.display()),
+            None => println!(
+                "⚙️  No config file at {}; using defaults",
+                GitConfig::default_path().display()
+            ),
+        }
         println!("   Repository: {}", path.display());
-        println!("   Days back: {}", days);
+        // Same for the manifest, which settings_for skips when it's broken
+        match ProjectManifest::find(&path) {
+            Ok(Some(manifest)) => println!("   Manifest: {}", manifest.path.display()),
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("Error: {}", sanitize_error_message(&e));
+                return Err(e);
+            }
+        }
+        println!();
+
+        let settings = config.settings_for(&path);
+
+        println!("⏱️  Base minutes per commit type:");
+        for commit_type in CommitType::ALL {
+            if let Some((minutes, source)) = settings.base_minutes.get(commit_type.as_str()) {
+                println!(
+                    "   {:<14} {:>4}m  ({})",
+                    commit_type.as_str(),
+                    minutes,
+                    source.as_str()
+                );
+            }
+        }
+        let (max_minutes, source) = settings.max_minutes_per_commit;
+        println!(
+            "   Max per commit {} ({})",
+            format_hours_minutes(chrono::Duration::minutes(max_minutes)),
+            source.as_str()
+        );
+
+        println!();
+        println!("📁 File weights (first match wins):");
+        for rule in &settings.file_weights {
+            println!(
+                "   {:<24} {:>5.2}  ({})",
+                rule.pattern,
+                rule.value,
+                rule.source.as_str()
+            );
+        }
 
-        // Detect or use provided project
-        let project_name = if let Some(name) = project_name {
-            name
+        println!();
+        println!("➕ Extra minutes per file (first match wins):");
+        for rule in &settings.file_minutes {
+            println!(
+                "   {:<24} {:>4}m  ({})",
+                rule.pattern,
+                rule.value,
+                rule.source.as_str()
+            );
+        }
+
+        Ok(())
+    }
+
+    fn handle_git_setup(
+        &self,
+        repo_path: Option<PathBuf>,
+        global: bool,
+        uninstall: bool,
+        kind: HookKind,
+    ) -> Result<()> {
+        let target = if global {
+            HookTarget::Global
         } else {
-            match self.git_service.detect_project(&path).await? {
-                Some(name) => name,
-                None => {
-                    println!("❌ No project specified and none could be auto-detected");
-                    println!("   Use --project <name> to specify a project");
-                    return Ok(());
+            HookTarget::Repository(
+                repo_path.unwrap_or_else(|| std::env::current_dir().unwrap_or_default()),
+            )
+        };
+        let hook_service = GitHookService::new();
+
+        if uninstall {
+            return match hook_service.uninstall(&target, kind) {
+                Ok(Some(path)) => {
+                    println!(
+                        "🗑️  Removed TimeSpan {} hook: {}",
+                        kind.name(),
+                        path.display()
+                    );
+                    Ok(())
+                }
+                Ok(None) => {
+                    println!("ℹ️  No TimeSpan {} hook installed", kind.name());
+                    Ok(())
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", sanitize_error_message(&e));
+                    Err(e)
+                }
+            };
+        }
+
+        match hook_service.install(&target, kind) {
+            Ok(installation) => {
+                let path = installation.hook_path.display();
+                let name = kind.name();
+                match installation.action {
+                    HookAction::Created => println!("✅ Installed {} hook: {}", name, path),
+                    HookAction::Appended => {
+                        println!("✅ Added TimeSpan to existing {} hook: {}", name, path)
+                    }
+                    HookAction::Chained(previous) => {
+                        println!("✅ Installed {} hook: {}", name, path);
+                        println!(
+                            "   Existing hook moved to {} and still runs first",
+                            previous.display()
+                        );
+                    }
+                    HookAction::Updated => println!("🔄 Updated {} hook: {}", name, path),
                 }
+                match kind {
+                    HookKind::PostCommit => {
+                        println!("   Each commit is now recorded with 'timespan git record-commit'")
+                    }
+                    HookKind::PostCheckout => {
+                        println!("   Branch switches now move the running timer with 'timespan git sync-branch'");
+                        if !self.git_service.branches().enabled {
+                            println!(
+                                "   💡 Set 'enabled = true' under [branches] in git-config.toml to turn switching on"
+                            );
+                        }
+                    }
+                }
+                Ok(())
             }
-        };
+            Err(e) => {
+                eprintln!("Error: {}", sanitize_error_message(&e));
+                Err(e)
+            }
+        }
+    }
+
+    async fn handle_git_record_commit(
+        &self,
+        revision: &str,
+        repo_path: Option<PathBuf>,
+        project_name: Option<String>,
+    ) -> Result<()> {
+        let path = repo_path.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+        let commit = self.git_service.get_commit(&path, revision).await?;
+        if commit.is_merge && self.git_service.history().merges == MergePolicy::Skip {
+            println!(
+                "⏭️  {} is a merge commit; not recorded",
+                commit.hash.chars().take(8).collect::<String>()
+            );
+            return Ok(());
+        }
 
-        // Get or create project
+        if project_name.is_none() {
+            if let Some((project, _)) = self
+                .project_service
+                .resolve_directory_project(&path, &self.git_service)
+                .await?
+            {
+                return self.record_commit(commit, &project).await;
+            }
+        }
+
+        let project_name = match project_name {
+            Some(name) => name,
+            None => self
+                .git_service
+                .detect_project(&path)
+                .await?
+                .ok_or_else(|| {
+                    crate::TimeSpanError::InvalidInput(
+                        "Could not detect a project for this repository".to_string(),
+                    )
+                })?,
+        };
         let project = match self.project_service.get_project(&project_name).await? {
             Some(project) => project,
             None => {
-                println!("📝 Creating new project: {}", project_name);
                 self.project_service
                     .create_project(
                         &project_name,
-                        Some(&format!("Auto-created from git import: {}", path.display())),
+                        Some(&format!("Auto-created from git commit: {}", path.display())),
                     )
                     .await?
             }
         };
+        self.record_commit(commit, &project).await
+    }
+
+    /// Import one commit into `project` and report its estimate
+    async fn record_commit(
+        &self,
+        commit: crate::models::GitCommit,
+        project: &crate::models::Project,
+    ) -> Result<()> {
+        let result = self
+            .git_service
+            .import_commits(
+                std::slice::from_ref(&commit),
+                project,
+                &GitImportOptions::default(),
+            )
+            .await?;
+
+        let short_hash = commit.hash.chars().take(8).collect::<String>();
+        match result.imported.first() {
+            Some(entry) => println!(
+                "⏱️  Recorded {} for {}: {}",
+                short_hash,
+                project.name,
+                format_hours_minutes(entry.estimated_time)
+            ),
+            None => println!("⏭️  {} already recorded", short_hash),
+        }
+        Ok(())
+    }
+
+    async fn handle_git_import(
+        &self,
+        repo_path: Option<PathBuf>,
+        days: u32,
+        project_name: Option<String>,
+        author: AuthorFilter,
+        history: HistoryOptions,
+        options: GitImportOptions,
+    ) -> Result<()> {
+        let path = repo_path.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+        let dry_run = options.dry_run;
+
+        if dry_run {
+            println!("🔍 DRY RUN MODE - No changes will be made");
+        }
+        println!("📥 Importing git commits to TimeSpan");
+        println!("   Repository: {}", path.display());
+        println!("   Days back: {}", days);
+        println!("   Estimator: {}", options.strategy.as_str());
+
+        let authors = self.git_service.resolve_authors(&path, &author)?;
+        print_author_scope(&author, authors.as_ref());
+
+        let (project, created) = match self
+            .resolve_import_project(&path, project_name, dry_run)
+            .await?
+        {
+            Some(found) => found,
+            None => {
+                println!("❌ No project specified and none could be auto-detected");
+                println!("   Use --project <name> to specify a project");
+                return Ok(());
+            }
+        };
+        match (created, dry_run) {
+            (true, true) => println!("📝 Would create new project: {}", project.name),
+            (true, false) => println!("📝 Created new project: {}", project.name),
+            _ => {}
+        }
 
         println!("   Target project: {}", project.name);
         println!();
 
-        let since = chrono::Utc::now() - chrono::Duration::days(days as i64);
-        let commits = self
-            .git_service
-            .get_commits(&path, Some(since), Some(50))
-            .await?;
+        let query = history.query(days, self.git_service.history().max_commits, authors);
+        let commits = self.git_service.get_commits(&path, &query).await?;
 
         if commits.is_empty() {
             println!("📭 No commits found in the specified time range.");
@@ -803,64 +2944,241 @@ impl TimeSpanApp {
         }
 
         println!("🔄 Processing {} commits...", commits.len());
+        let result = self
+            .git_service
+            .import_commits(&commits, &project, &options)
+            .await?;
+
         let mut total_time = chrono::Duration::zero();
-        let mut imported_count = 0;
+        for git_time_entry in &result.imported {
+            println!(
+                "   ✅ {} - {} (confidence {:.0}%)",
+                git_time_entry
+                    .commit_hash
+                    .chars()
+                    .take(8)
+                    .collect::<String>(),
+                format_hours_minutes(git_time_entry.estimated_time),
+                git_time_entry.confidence_score * 100.0
+            );
+            total_time += git_time_entry.estimated_time;
+        }
+        for hash in &result.skipped {
+            println!(
+                "   ⏭️  {} - already imported",
+                hash.chars().take(8).collect::<String>()
+            );
+        }
 
-        for commit in &commits {
-            let analysis = self.git_service.analyze_commit(commit).await?;
-            let git_time_entry = self
-                .git_service
-                .create_git_time_entry(&analysis, &project)
-                .await?;
-
-            // Convert to regular time entry
-            let mut time_entry = crate::models::TimeEntry::new(
-                project.id,
-                project.name.clone(),
-                Some(format!(
-                    "Git: {}",
-                    commit.message.lines().next().unwrap_or("No message")
-                )),
-                commit.timestamp,
+        println!();
+        if dry_run {
+            println!("🔍 Dry run completed!");
+            println!(
+                "   Commits that would be imported: {}",
+                result.imported.len()
             );
+        } else {
+            println!("🎉 Import completed!");
+            println!("   Commits imported: {}", result.imported.len());
+        }
+        println!("   Already imported: {}", result.skipped.len());
+        println!(
+            "   Total estimated time: {}",
+            format_hours_minutes(total_time)
+        );
+        if !result.imported.is_empty() {
+            println!(
+                "   Average per commit: {}m",
+                total_time.num_minutes() / result.imported.len() as i64
+            );
+        }
 
-            // Set the estimated time as the duration
-            time_entry.stop(commit.timestamp + git_time_entry.estimated_time)?;
-            time_entry.add_tag("git-import".to_string());
-            time_entry.add_tag(format!(
-                "commit-{}",
-                commit.hash.chars().take(8).collect::<String>()
-            ));
+        Ok(())
+    }
 
-            // Save to database (you would need to add this to repository trait)
-            // For now, we'll just print what we would do
-            let hours = git_time_entry.estimated_time.num_hours();
-            let minutes = git_time_entry.estimated_time.num_minutes() % 60;
+    /// Project for commits from `path`: the one named on the command line,
+    /// else the project whose directory contains the repository, one named
+    /// after the repository, the one its `.timespan.toml` declares, or the
+    /// repository's directory name. Missing projects are created unless this
+    /// is a dry run. The flag is true for new projects.
+    async fn resolve_import_project(
+        &self,
+        path: &std::path::Path,
+        project_name: Option<String>,
+        dry_run: bool,
+    ) -> Result<Option<(crate::models::Project, bool)>> {
+        let project_name = match project_name {
+            Some(name) => name,
+            None => {
+                if let Some(project) = self
+                    .project_service
+                    .find_project_for_directory(path)
+                    .await?
+                {
+                    return Ok(Some((project, false)));
+                }
+                if let Some((project, _)) = self.git_service.repository_project(path).await? {
+                    return Ok(Some((project, false)));
+                }
+                if let Some(manifest) = ProjectManifest::find(path)? {
+                    let existing = self
+                        .project_service
+                        .get_project(&manifest.project_name())
+                        .await?;
+                    if dry_run {
+                        return Ok(Some(match existing {
+                            Some(project) => (project, false),
+                            None => (
+                                crate::models::Project::new(
+                                    manifest.project_name(),
+                                    manifest.description.clone(),
+                                ),
+                                true,
+                            ),
+                        }));
+                    }
+                    let project = self
+                        .project_service
+                        .ensure_manifest_project(&manifest)
+                        .await?;
+                    return Ok(Some((project, existing.is_none())));
+                }
+                match self.git_service.detect_project(path).await? {
+                    Some(name) => name,
+                    None => return Ok(None),
+                }
+            }
+        };
 
-            println!(
-                "   ✅ {} - {}h {}m",
-                commit.hash.chars().take(8).collect::<String>(),
-                hours,
-                minutes
-            );
+        let project = match self.project_service.get_project(&project_name).await? {
+            Some(project) => return Ok(Some((project, false))),
+            None if dry_run => crate::models::Project::new(project_name, None),
+            None => {
+                self.project_service
+                    .create_project(
+                        &project_name,
+                        Some(&format!("Auto-created from git import: {}", path.display())),
+                    )
+                    .await?
+            }
+        };
+        Ok(Some((project, true)))
+    }
 
-            total_time += git_time_entry.estimated_time;
-            imported_count += 1;
+    async fn handle_git_import_scan(
+        &self,
+        root: PathBuf,
+        depth: usize,
+        days: u32,
+        author: AuthorFilter,
+        history: HistoryOptions,
+        options: GitImportOptions,
+    ) -> Result<()> {
+        if options.dry_run {
+            println!("🔍 DRY RUN MODE - No changes will be made");
         }
+        println!("📂 Scanning {} for git repositories", root.display());
 
-        let total_hours = total_time.num_hours();
-        let total_minutes = total_time.num_minutes() % 60;
+        let repositories = match self.git_service.find_repositories(&root, depth) {
+            Ok(repositories) => repositories,
+            Err(e) => {
+                eprintln!("Error: {}", sanitize_error_message(&e));
+                return Err(e);
+            }
+        };
+        if repositories.is_empty() {
+            println!("📭 No git repositories found.");
+            return Ok(());
+        }
 
+        let max_commits = self.git_service.history().max_commits;
+        println!("   Repositories: {}", repositories.len());
+        println!("   Days back: {}", days);
+        println!("   Estimator: {}", options.strategy.as_str());
+        println!(
+            "   Commits per repository: up to {}",
+            history.limit.unwrap_or(max_commits)
+        );
         println!();
-        println!("🎉 Import completed!");
-        println!("   Commits processed: {}", imported_count);
+
         println!(
-            "   Total estimated time: {}h {}m",
-            total_hours, total_minutes
+            "{:<30} {:<24} {:>8} {:>8} {:>10}",
+            "Repository", "Project", "Imported", "Skipped", "Estimated"
         );
+
+        let mut total_imported = 0;
+        let mut total_skipped = 0;
+        let mut total_time = chrono::Duration::zero();
+        let mut failures = Vec::new();
+
+        for path in &repositories {
+            let name = match path.strip_prefix(&root) {
+                Ok(relative) if !relative.as_os_str().is_empty() => relative.display().to_string(),
+                _ => ".".to_string(),
+            };
+
+            let imported = async {
+                let Some((project, created)) = self
+                    .resolve_import_project(path, None, options.dry_run)
+                    .await?
+                else {
+                    return Ok(None);
+                };
+                let authors = self.git_service.resolve_authors(path, &author)?;
+                let query = history.query(days, max_commits, authors);
+                let commits = self.git_service.get_commits(path, &query).await?;
+                let result = self
+                    .git_service
+                    .import_commits(&commits, &project, &options)
+                    .await?;
+                Ok::<_, crate::TimeSpanError>(Some((project, created, result)))
+            }
+            .await;
+
+            match imported {
+                Ok(Some((project, created, result))) => {
+                    let time: chrono::Duration = result
+                        .imported
+                        .iter()
+                        .map(|entry| entry.estimated_time)
+                        .sum();
+                    let project_name = if created {
+                        format!("{} (new)", project.name)
+                    } else {
+                        project.name
+                    };
+                    println!(
+                        "{:<30} {:<24} {:>8} {:>8} {:>10}",
+                        name,
+                        project_name,
+                        result.imported.len(),
+                        result.skipped.len(),
+                        format_hours_minutes(time)
+                    );
+                    total_imported += result.imported.len();
+                    total_skipped += result.skipped.len();
+                    total_time += time;
+                }
+                Ok(None) => failures.push((name, "no project could be detected".to_string())),
+                Err(e) => failures.push((name, sanitize_error_message(&e))),
+            }
+        }
+
+        println!();
+        for (name, reason) in &failures {
+            println!("   ❌ {}: {}", name, reason);
+        }
+        if options.dry_run {
+            println!("🔍 Dry run completed!");
+            println!("   Commits that would be imported: {}", total_imported);
+        } else {
+            println!("🎉 Import completed!");
+            println!("   Commits imported: {}", total_imported);
+        }
+        println!("   Already imported: {}", total_skipped);
         println!(
-            "   Average per commit: {}m",
-            total_time.num_minutes() / imported_count
+            "   Total estimated time: {}",
+            format_hours_minutes(total_time)
         );
 
         Ok(())
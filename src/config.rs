@@ -0,0 +1,1437 @@
+//! Git integration settings loaded from `git-config.toml`, and user
+//! settings such as discovery defaults loaded from `config.toml`.
+//!
+//! ```toml
+//! [estimation]
+//! max_minutes_per_commit = 240
+//!
+//! [estimation.base_minutes]
+//! feature = 45
+//! bugfix = 60
+//!
+//! [file_weights]
+//! "*.rs" = 1.5
+//! "src/generated/**" = 0
+//!
+//! [file_minutes]
+//! "*.rs" = 5
+//!
+//! [repos."~/code/app".file_weights]
+//! "*.sql" = 1.2
+//!
+//! # Emails that belong to the same person, keyed by the main one
+//! [identities]
+//! "jane@work.example" = ["jane@home.example"]
+//!
+//! # Which refs to read commits from, and whether merges are skipped or
+//! # kept with no estimated time
+//! [history]
+//! refs = ["refs/heads/*"]
+//! merges = "skip"
+//! max_commits = 50
+//!
+//! # Opt-in: split the running timer when the checked-out branch changes
+//! # and label the new timer with the ticket id from the branch name
+//! [branches]
+//! enabled = true
+//! ticket_pattern = '[A-Z][A-Z0-9]+-[0-9]+'
+//! ticket_field = "task"
+//!
+//! [branches.projects]
+//! "feature/ACME-*" = "[CLIENT] Acme"
+//!
+//! # Ticket references found in task descriptions and commit messages
+//! [references]
+//! patterns = ['\B#[0-9]+\b', '\b[A-Z][A-Z0-9]+-[0-9]+\b']
+//! ```
+//!
+//! Repository sections override the global values for that repository only.
+//! A `.timespan.toml` manifest in the repository sits between the two.
+//! File patterns are globs matched against repository-relative paths;
+//! repository patterns are checked before global ones and, within each, the
+//! longest matching pattern wins.
+//!
+//! ```toml
+//! # config.toml
+//!
+//! # Scanned by `project discover` when no --path is given; `exclude`
+//! # replaces the built-in list and `prefix = ""` turns the prefix off
+//! [discovery]
+//! roots = ["~/work/clients", "~/work/agency"]
+//! prefix = "[CLIENT]"
+//! exclude = [".*", "*.pdf", "node_modules"]
+//!
+//! # Descriptions for new projects by directory-name glob, longest first;
+//! # {name}, {path} and {git} (" (Git repository)" or "") are filled in.
+//! # These are added to the built-in "*" and "*Release*" rules.
+//! [discovery.descriptions]
+//! "*" = "Client project{git} Location: {path}"
+//! "Internal_*" = "Internal project{git}"
+//! ```
+
+use globset::{Glob, GlobMatcher};
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use toml::{Table, Value};
+
+use crate::manifest::ProjectManifest;
+use crate::models::{CommitType, MergePolicy, TicketField};
+use crate::{Result, TimeSpanError};
+
+/// Environment variable that points at an alternative config file
+pub const CONFIG_ENV: &str = "TIMESPAN_GIT_CONFIG";
+const CONFIG_FILE: &str = "git-config.toml";
+/// Environment variable that points at an alternative user config file
+pub const USER_CONFIG_ENV: &str = "TIMESPAN_CONFIG";
+const USER_CONFIG_FILE: &str = "config.toml";
+/// Jira-style ids such as `ACME-123`
+const DEFAULT_TICKET_PATTERN: &str = "[A-Z][A-Z0-9]+-[0-9]+";
+/// Issue numbers (`#123`, but not `C#1`) and prefixed ids (`ACME-45`, `GH-7`)
+const DEFAULT_REFERENCE_PATTERNS: [&str; 2] = [r"\B#[0-9]+\b", r"\b[A-Z][A-Z0-9]+-[0-9]+\b"];
+const DEFAULT_DISCOVERY_PREFIX: &str = "[CLIENT]";
+/// Hidden directories, loose files and editor directories
+const DEFAULT_DISCOVERY_EXCLUDES: [&str; 21] = [
+    ".DS_Store",
+    ".git",
+    ".github",
+    ".*", // Exclude all hidden directories/files
+    "*.pdf",
+    "*.mp4",
+    "*.zip",
+    "*.whisper",
+    "*.html",
+    "*.mht",
+    "*.pages",
+    "*.md",
+    // Common IDE/editor directories
+    ".vscode",
+    ".idea",
+    ".claude",
+    ".cursor",
+    ".vscode-insiders",
+    ".atom",
+    ".sublime-text",
+    ".vim",
+    ".emacs.d",
+];
+/// Description templates by directory-name glob
+const DEFAULT_DESCRIPTION_TEMPLATES: [(&str, &str); 2] = [
+    ("*Release*", "Product release work{git} Location: {path}"),
+    ("*", "Client project{git} Location: {path}"),
+];
+
+/// Values as written in one section of the config file. Unset values fall
+/// back to the enclosing layer.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EstimationConfig {
+    /// Base minutes keyed by `CommitType::as_str()`
+    pub base_minutes: BTreeMap<String, i64>,
+    pub max_minutes_per_commit: Option<i64>,
+    pub file_weights: BTreeMap<String, f64>,
+    pub file_minutes: BTreeMap<String, i64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GitConfig {
+    /// File the config was read from; `None` when running on defaults
+    pub source: Option<PathBuf>,
+    pub global: EstimationConfig,
+    pub repositories: Vec<(PathBuf, EstimationConfig)>,
+    /// Groups of author emails (lowercased) that belong to one person; the
+    /// first email is the main one
+    pub identities: Vec<Vec<String>>,
+    pub history: HistoryConfig,
+    pub branches: BranchConfig,
+    pub references: ReferenceConfig,
+    settings_cache: SettingsCache,
+}
+
+/// User settings from `config.toml`
+#[derive(Debug, Clone, Default)]
+pub struct UserConfig {
+    /// File the config was read from; `None` when running on defaults
+    pub source: Option<PathBuf>,
+    pub discovery: DiscoveryConfig,
+}
+
+/// Settings already resolved by `settings_for`, keyed by repository path.
+/// A cloned config starts empty since the clone's sections may be changed.
+#[derive(Debug, Default)]
+struct SettingsCache(Mutex<HashMap<PathBuf, Arc<EstimationSettings>>>);
+
+impl Clone for SettingsCache {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+/// Which commits are read from a repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryConfig {
+    /// Ref names, revisions or globs such as `refs/heads/*`
+    pub refs: Vec<String>,
+    pub merges: MergePolicy,
+    /// Most commits `git import` reads per repository
+    pub max_commits: usize,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            refs: vec!["refs/heads/*".to_string()],
+            merges: MergePolicy::Skip,
+            max_commits: 50,
+        }
+    }
+}
+
+/// How the running timer follows the checked-out branch
+#[derive(Debug, Clone)]
+pub struct BranchConfig {
+    /// Switching is opt-in; branches are recorded either way
+    pub enabled: bool,
+    /// Finds the ticket id in a branch name; the first capture group is used
+    /// when the pattern has one
+    pub ticket_pattern: Regex,
+    pub ticket_field: TicketField,
+    /// Branch globs mapped to project names, longest pattern first
+    pub projects: Vec<BranchRule>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BranchRule {
+    pub pattern: String,
+    pub project: String,
+    matcher: GlobMatcher,
+}
+
+impl Default for BranchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ticket_pattern: Regex::new(DEFAULT_TICKET_PATTERN).unwrap(),
+            ticket_field: TicketField::Task,
+            projects: Vec::new(),
+        }
+    }
+}
+
+impl BranchConfig {
+    /// The ticket id in `branch`, e.g. `ACME-123` for `feature/ACME-123-login`
+    pub fn ticket(&self, branch: &str) -> Option<String> {
+        let captures = self.ticket_pattern.captures(branch)?;
+        captures
+            .get(1)
+            .or_else(|| captures.get(0))
+            .map(|ticket| ticket.as_str().to_string())
+    }
+
+    /// Project that work on `branch` belongs to, if a rule matches
+    pub fn project_for(&self, branch: &str) -> Option<&str> {
+        self.projects
+            .iter()
+            .find(|rule| rule.matcher.is_match(branch))
+            .map(|rule| rule.project.as_str())
+    }
+}
+
+/// Finds ticket and issue references in free text
+#[derive(Debug, Clone)]
+pub struct ReferenceConfig {
+    /// Each match is a reference; the first capture group is used when the
+    /// pattern has one
+    pub patterns: Vec<Regex>,
+}
+
+impl Default for ReferenceConfig {
+    fn default() -> Self {
+        Self {
+            patterns: DEFAULT_REFERENCE_PATTERNS
+                .iter()
+                .map(|pattern| Regex::new(pattern).unwrap())
+                .collect(),
+        }
+    }
+}
+
+impl ReferenceConfig {
+    /// Every distinct reference in `text`, in order of appearance
+    pub fn extract(&self, text: &str) -> Vec<String> {
+        let mut found: Vec<(usize, String)> = Vec::new();
+        for pattern in &self.patterns {
+            for captures in pattern.captures_iter(text) {
+                if let Some(reference) = captures.get(1).or_else(|| captures.get(0)) {
+                    found.push((reference.start(), reference.as_str().to_string()));
+                }
+            }
+        }
+        found.sort_by_key(|(position, _)| *position);
+
+        let mut references: Vec<String> = Vec::new();
+        for (_, reference) in found {
+            if !references.contains(&reference) {
+                references.push(reference);
+            }
+        }
+        references
+    }
+}
+
+/// Defaults for `project discover`
+#[derive(Debug, Clone)]
+pub struct DiscoveryConfig {
+    /// Directories scanned when no path is given on the command line
+    pub roots: Vec<PathBuf>,
+    /// Put before discovered project names; `None` when configured as ""
+    pub prefix: Option<String>,
+    /// Directory globs that are never scanned
+    pub exclude: Vec<String>,
+    /// Description templates by directory-name glob, longest pattern first
+    pub descriptions: Vec<DescriptionRule>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DescriptionRule {
+    pub pattern: String,
+    pub template: String,
+    matcher: GlobMatcher,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            roots: Vec::new(),
+            prefix: Some(DEFAULT_DISCOVERY_PREFIX.to_string()),
+            exclude: DEFAULT_DISCOVERY_EXCLUDES
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .collect(),
+            descriptions: DEFAULT_DESCRIPTION_TEMPLATES
+                .iter()
+                .map(|(pattern, template)| DescriptionRule {
+                    pattern: pattern.to_string(),
+                    template: template.to_string(),
+                    matcher: Glob::new(pattern).unwrap().compile_matcher(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl DiscoveryConfig {
+    /// Description for a new project in `path`, named `name` below its root,
+    /// from the first template whose pattern matches the name
+    pub fn description(&self, name: &str, path: &Path, is_git_repo: bool) -> Option<String> {
+        let rule = self
+            .descriptions
+            .iter()
+            .find(|rule| rule.matcher.is_match(name))?;
+        let git = if is_git_repo { " (Git repository)" } else { "" };
+        Some(
+            rule.template
+                .replace("{name}", name)
+                .replace("{path}", &path.display().to_string())
+                .replace("{git}", git),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    Global,
+    /// The repository's `.timespan.toml`
+    Manifest,
+    Repository,
+}
+
+impl ConfigSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfigSource::Default => "default",
+            ConfigSource::Global => "global",
+            ConfigSource::Manifest => "manifest",
+            ConfigSource::Repository => "repository",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FileRule<T> {
+    pub pattern: String,
+    pub value: T,
+    pub source: ConfigSource,
+    matcher: GlobMatcher,
+}
+
+/// Effective estimation settings for one repository
+#[derive(Debug, Clone)]
+pub struct EstimationSettings {
+    pub base_minutes: BTreeMap<String, (i64, ConfigSource)>,
+    pub max_minutes_per_commit: (i64, ConfigSource),
+    /// In match order
+    pub file_weights: Vec<FileRule<f64>>,
+    /// In match order
+    pub file_minutes: Vec<FileRule<i64>>,
+}
+
+impl EstimationConfig {
+    /// The values that used to be hardcoded in `GitService`
+    pub fn builtin() -> Self {
+        let base_minutes = [
+            (CommitType::Feature, 45),
+            (CommitType::BugFix, 60),
+            (CommitType::Refactor, 30),
+            (CommitType::Documentation, 15),
+            (CommitType::Test, 25),
+            (CommitType::Chore, 10),
+            (CommitType::Perf, 40),
+            (CommitType::CI, 15),
+            (CommitType::Build, 15),
+            (CommitType::Style, 5),
+            (CommitType::Revert, 10),
+            (CommitType::Other, 20),
+        ]
+        .into_iter()
+        .map(|(commit_type, minutes)| (commit_type.as_str().to_string(), minutes))
+        .collect();
+
+        let file_weights = [
+            ("*.rs", 1.5),
+            ("*.py", 1.3),
+            ("*.js", 1.2),
+            ("*.ts", 1.2),
+            ("*.java", 1.4),
+            ("*.cpp", 1.4),
+            ("*.c", 1.4),
+            ("*.md", 0.5),
+            ("*.txt", 0.5),
+            ("*.json", 0.3),
+            ("*.toml", 0.3),
+            ("*.yaml", 0.3),
+            ("*.yml", 0.3),
+            ("*.html", 0.7),
+            ("*.css", 0.7),
+        ]
+        .into_iter()
+        .map(|(pattern, weight)| (pattern.to_string(), weight))
+        .collect();
+
+        let file_minutes = [("*.rs", 5), ("*.js", 3), ("*.ts", 3), ("*.md", 1)]
+            .into_iter()
+            .map(|(pattern, minutes)| (pattern.to_string(), minutes))
+            .collect();
+
+        Self {
+            base_minutes,
+            max_minutes_per_commit: Some(240),
+            file_weights,
+            file_minutes,
+        }
+    }
+}
+
+impl GitConfig {
+    /// `$TIMESPAN_GIT_CONFIG`, else `~/.timespan/git-config.toml` when it
+    /// exists, else `<config dir>/timespan/git-config.toml`
+    pub fn default_path() -> PathBuf {
+        config_path(CONFIG_ENV, CONFIG_FILE)
+    }
+
+    /// Load the config from the default location; a missing file means
+    /// defaults
+    pub fn load() -> Result<Self> {
+        let path = Self::default_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::load_from(&path)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            TimeSpanError::Config(format!("{}: could not read file: {}", path.display(), e))
+        })?;
+
+        let mut config = Self::parse(&content)
+            .map_err(|e| TimeSpanError::Config(format!("{}: {}", path.display(), e)))?;
+        config.source = Some(path.to_path_buf());
+        Ok(config)
+    }
+
+    /// Parse config text. Errors name the offending key, e.g.
+    /// `estimation.base_minutes.featur: unknown commit type`.
+    pub fn parse(content: &str) -> std::result::Result<Self, String> {
+        let table: Table = content
+            .parse()
+            .map_err(|e: toml::de::Error| format!("invalid TOML: {}", e.message().trim_end()))?;
+
+        let mut config = Self::default();
+        let mut global = Table::new();
+
+        for (key, value) in table {
+            match key.as_str() {
+                "repos" => {
+                    let repos = expect_table(&value, &[&key])?;
+                    for (repo, section) in repos {
+                        let section = expect_table(section, &[&key, repo])?;
+                        let parsed = parse_estimation(section, &[&key, repo])?;
+                        config.repositories.push((expand_home(repo), parsed));
+                    }
+                }
+                "identities" => {
+                    config.identities = parse_identities(expect_table(&value, &[&key])?)?;
+                }
+                "history" => {
+                    config.history = parse_history(expect_table(&value, &[&key])?)?;
+                }
+                "branches" => {
+                    config.branches = parse_branches(expect_table(&value, &[&key])?)?;
+                }
+                "references" => {
+                    config.references = parse_references(expect_table(&value, &[&key])?)?;
+                }
+                "discovery" => {
+                    return Err(format!(
+                        "{}: discovery settings belong in {}",
+                        key, USER_CONFIG_FILE
+                    ));
+                }
+                _ => {
+                    global.insert(key, value);
+                }
+            }
+        }
+
+        config.global = parse_estimation(&global, &[])?;
+        Ok(config)
+    }
+
+    /// Effective settings for `repo_path`: built-in defaults, overlaid with
+    /// the global section, the repository's `.timespan.toml` and then the
+    /// matching repository section. A manifest that fails to parse is
+    /// skipped here; `timespan start` and `git config show` report it.
+    /// The result is cached per repository path for the life of the config.
+    pub fn settings_for(&self, repo_path: &Path) -> Arc<EstimationSettings> {
+        let mut cache = self.settings_cache.0.lock().unwrap();
+        if let Some(settings) = cache.get(repo_path) {
+            return Arc::clone(settings);
+        }
+
+        let manifest = ProjectManifest::find(repo_path).ok().flatten();
+        let settings = Arc::new(
+            self.settings_with_manifest(repo_path, manifest.as_ref().map(|m| &m.estimation)),
+        );
+        cache.insert(repo_path.to_path_buf(), Arc::clone(&settings));
+        settings
+    }
+
+    fn settings_with_manifest(
+        &self,
+        repo_path: &Path,
+        manifest: Option<&EstimationConfig>,
+    ) -> EstimationSettings {
+        let builtin = EstimationConfig::builtin();
+        let repo = self.repository_section(repo_path);
+
+        let mut base_minutes = BTreeMap::new();
+        for (layer, source) in [
+            (Some(&builtin), ConfigSource::Default),
+            (Some(&self.global), ConfigSource::Global),
+            (manifest, ConfigSource::Manifest),
+            (repo, ConfigSource::Repository),
+        ] {
+            if let Some(layer) = layer {
+                for (key, minutes) in &layer.base_minutes {
+                    base_minutes.insert(key.clone(), (*minutes, source));
+                }
+            }
+        }
+
+        let max_minutes_per_commit = repo
+            .and_then(|r| r.max_minutes_per_commit)
+            .map(|m| (m, ConfigSource::Repository))
+            .or_else(|| {
+                manifest
+                    .and_then(|m| m.max_minutes_per_commit)
+                    .map(|m| (m, ConfigSource::Manifest))
+            })
+            .or_else(|| {
+                self.global
+                    .max_minutes_per_commit
+                    .map(|m| (m, ConfigSource::Global))
+            })
+            .unwrap_or((
+                builtin.max_minutes_per_commit.unwrap_or(240),
+                ConfigSource::Default,
+            ));
+
+        EstimationSettings {
+            base_minutes,
+            max_minutes_per_commit,
+            file_weights: merge_rules(
+                &builtin.file_weights,
+                &self.global.file_weights,
+                manifest.map(|m| &m.file_weights),
+                repo.map(|r| &r.file_weights),
+            ),
+            file_minutes: merge_rules(
+                &builtin.file_minutes,
+                &self.global.file_minutes,
+                manifest.map(|m| &m.file_minutes),
+                repo.map(|r| &r.file_minutes),
+            ),
+        }
+    }
+
+    /// Every email that belongs to the same person as `email`, main email
+    /// first; just `email` when it isn't part of an identity
+    pub fn identity_emails(&self, email: &str) -> Vec<String> {
+        let email = email.trim().to_lowercase();
+        self.identities
+            .iter()
+            .find(|group| group.contains(&email))
+            .cloned()
+            .unwrap_or_else(|| vec![email])
+    }
+
+    fn repository_section(&self, repo_path: &Path) -> Option<&EstimationConfig> {
+        let wanted = canonical(repo_path);
+        self.repositories
+            .iter()
+            .find(|(path, _)| canonical(path) == wanted)
+            .map(|(_, section)| section)
+    }
+}
+
+impl UserConfig {
+    /// `$TIMESPAN_CONFIG`, else `~/.timespan/config.toml` when it exists,
+    /// else `<config dir>/timespan/config.toml`
+    pub fn default_path() -> PathBuf {
+        config_path(USER_CONFIG_ENV, USER_CONFIG_FILE)
+    }
+
+    /// Load the config from the default location; a missing file means
+    /// defaults
+    pub fn load() -> Result<Self> {
+        let path = Self::default_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::load_from(&path)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            TimeSpanError::Config(format!("{}: could not read file: {}", path.display(), e))
+        })?;
+
+        let mut config = Self::parse(&content)
+            .map_err(|e| TimeSpanError::Config(format!("{}: {}", path.display(), e)))?;
+        config.source = Some(path.to_path_buf());
+        Ok(config)
+    }
+
+    /// Parse config text. Errors name the offending key, e.g.
+    /// `discovery.roots[1]: expected a directory`.
+    pub fn parse(content: &str) -> std::result::Result<Self, String> {
+        let table: Table = content
+            .parse()
+            .map_err(|e: toml::de::Error| format!("invalid TOML: {}", e.message().trim_end()))?;
+
+        let mut config = Self::default();
+        for (key, value) in table {
+            match key.as_str() {
+                "discovery" => {
+                    config.discovery = parse_discovery(expect_table(&value, &[&key])?)?;
+                }
+                _ => return Err(format!("{}: unknown key", key_path(&[&key]))),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// `$<env>`, else `~/.timespan/<file>` when it exists, else
+/// `<config dir>/timespan/<file>`
+fn config_path(env: &str, file: &str) -> PathBuf {
+    if let Some(path) = std::env::var_os(env).filter(|p| !p.is_empty()) {
+        return PathBuf::from(path);
+    }
+
+    let legacy = dirs::home_dir().map(|home| home.join(".timespan").join(file));
+    if let Some(legacy) = legacy.filter(|path| path.exists()) {
+        return legacy;
+    }
+
+    dirs::config_dir()
+        .map(|dir| dir.join("timespan"))
+        .unwrap_or_else(|| PathBuf::from(".timespan"))
+        .join(file)
+}
+
+impl EstimationSettings {
+    pub fn base_minutes(&self, commit_type: &CommitType) -> i64 {
+        self.base_minutes
+            .get(commit_type.as_str())
+            .map(|(minutes, _)| *minutes)
+            .unwrap_or(20)
+    }
+
+    pub fn max_per_commit(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.max_minutes_per_commit.0)
+    }
+
+    /// Complexity weight for a file; 1.0 when no pattern matches
+    pub fn file_weight(&self, file: &str) -> f64 {
+        first_match(&self.file_weights, file).unwrap_or(1.0)
+    }
+
+    /// Extra minutes a changed file adds; 0 when no pattern matches
+    pub fn file_minutes(&self, file: &str) -> i64 {
+        first_match(&self.file_minutes, file).unwrap_or(0)
+    }
+
+    /// Files weighted 0 (e.g. generated code) don't count towards estimates
+    pub fn is_ignored(&self, file: &str) -> bool {
+        self.file_weight(file) == 0.0
+    }
+}
+
+fn first_match<T: Copy>(rules: &[FileRule<T>], file: &str) -> Option<T> {
+    rules
+        .iter()
+        .find(|rule| rule.matcher.is_match(file))
+        .map(|rule| rule.value)
+}
+
+/// Repository rules first, then manifest rules, then global rules overlaid
+/// on the defaults; each group ordered longest pattern first
+fn merge_rules<T: Copy>(
+    builtin: &BTreeMap<String, T>,
+    global: &BTreeMap<String, T>,
+    manifest: Option<&BTreeMap<String, T>>,
+    repo: Option<&BTreeMap<String, T>>,
+) -> Vec<FileRule<T>> {
+    let ordered = |rules: Vec<FileRule<T>>| {
+        let mut rules = rules;
+        rules.sort_by(|a, b| {
+            b.pattern
+                .len()
+                .cmp(&a.pattern.len())
+                .then_with(|| a.pattern.cmp(&b.pattern))
+        });
+        rules
+    };
+    let rule = |pattern: &String, value: &T, source| FileRule {
+        pattern: pattern.clone(),
+        value: *value,
+        source,
+        // Patterns were validated when the config was parsed
+        matcher: Glob::new(pattern)
+            .map(|glob| glob.compile_matcher())
+            .unwrap_or_else(|_| Glob::new("").unwrap().compile_matcher()),
+    };
+
+    let mut rules = match repo {
+        Some(repo) => ordered(
+            repo.iter()
+                .map(|(p, v)| rule(p, v, ConfigSource::Repository))
+                .collect(),
+        ),
+        None => Vec::new(),
+    };
+    if let Some(manifest) = manifest {
+        rules.extend(ordered(
+            manifest
+                .iter()
+                .map(|(p, v)| rule(p, v, ConfigSource::Manifest))
+                .collect(),
+        ));
+    }
+
+    let mut layered: BTreeMap<&String, (&T, ConfigSource)> = builtin
+        .iter()
+        .map(|(p, v)| (p, (v, ConfigSource::Default)))
+        .collect();
+    for (p, v) in global {
+        layered.insert(p, (v, ConfigSource::Global));
+    }
+    rules.extend(ordered(
+        layered
+            .into_iter()
+            .map(|(p, (v, source))| rule(p, v, source))
+            .collect(),
+    ));
+
+    rules
+}
+
+pub(crate) fn parse_estimation(
+    table: &Table,
+    prefix: &[&str],
+) -> std::result::Result<EstimationConfig, String> {
+    let mut config = EstimationConfig::default();
+
+    for (key, value) in table {
+        let path = [prefix, &[key.as_str()]].concat();
+        match key.as_str() {
+            "estimation" => {
+                for (field, value) in expect_table(value, &path)? {
+                    let field_path = [path.as_slice(), &[field.as_str()]].concat();
+                    match field.as_str() {
+                        "max_minutes_per_commit" => {
+                            config.max_minutes_per_commit =
+                                Some(expect_minutes(value, &field_path, false)?);
+                        }
+                        "base_minutes" => {
+                            for (name, value) in expect_table(value, &field_path)? {
+                                let name_path = [field_path.as_slice(), &[name.as_str()]].concat();
+                                let commit_type: CommitType = name.parse().map_err(|_| {
+                                    format!(
+                                        "{}: unknown commit type (expected one of: {})",
+                                        key_path(&name_path),
+                                        CommitType::ALL.map(|t| t.as_str()).join(", ")
+                                    )
+                                })?;
+                                config.base_minutes.insert(
+                                    commit_type.as_str().to_string(),
+                                    expect_minutes(value, &name_path, true)?,
+                                );
+                            }
+                        }
+                        _ => return Err(format!("{}: unknown key", key_path(&field_path))),
+                    }
+                }
+            }
+            "file_weights" => {
+                for (pattern, value) in expect_table(value, &path)? {
+                    let pattern_path = [path.as_slice(), &[pattern.as_str()]].concat();
+                    validate_glob(pattern, &pattern_path)?;
+                    let weight = match value {
+                        Value::Integer(i) => *i as f64,
+                        Value::Float(f) => *f,
+                        _ => return Err(format!("{}: expected a number", key_path(&pattern_path))),
+                    };
+                    if !weight.is_finite() || weight < 0.0 {
+                        return Err(format!(
+                            "{}: weight must not be negative",
+                            key_path(&pattern_path)
+                        ));
+                    }
+                    config.file_weights.insert(pattern.clone(), weight);
+                }
+            }
+            "file_minutes" => {
+                for (pattern, value) in expect_table(value, &path)? {
+                    let pattern_path = [path.as_slice(), &[pattern.as_str()]].concat();
+                    validate_glob(pattern, &pattern_path)?;
+                    config
+                        .file_minutes
+                        .insert(pattern.clone(), expect_minutes(value, &pattern_path, true)?);
+                }
+            }
+            _ => return Err(format!("{}: unknown key", key_path(&path))),
+        }
+    }
+
+    Ok(config)
+}
+
+fn parse_identities(table: &Table) -> std::result::Result<Vec<Vec<String>>, String> {
+    let mut identities: Vec<Vec<String>> = Vec::new();
+
+    for (main, aliases) in table {
+        let path = ["identities", main.as_str()];
+        let mut group = vec![expect_email(main, &path)?];
+
+        let aliases = aliases
+            .as_array()
+            .ok_or_else(|| format!("{}: expected a list of emails", key_path(&path)))?;
+        for (index, alias) in aliases.iter().enumerate() {
+            let alias_path = format!("{}[{}]", key_path(&path), index);
+            let alias = alias
+                .as_str()
+                .ok_or_else(|| format!("{}: expected an email", alias_path))
+                .and_then(|alias| {
+                    expect_email(alias, &path)
+                        .map_err(|_| format!("{}: expected an email", alias_path))
+                })?;
+            if !group.contains(&alias) {
+                group.push(alias);
+            }
+        }
+
+        for email in &group {
+            if identities.iter().any(|other| other.contains(email)) {
+                return Err(format!(
+                    "{}: {} already belongs to another identity",
+                    key_path(&path),
+                    email
+                ));
+            }
+        }
+        identities.push(group);
+    }
+
+    Ok(identities)
+}
+
+fn parse_history(table: &Table) -> std::result::Result<HistoryConfig, String> {
+    let mut history = HistoryConfig::default();
+
+    for (key, value) in table {
+        let path = ["history", key.as_str()];
+        match key.as_str() {
+            "refs" => {
+                let refs = value
+                    .as_array()
+                    .filter(|refs| !refs.is_empty())
+                    .ok_or_else(|| {
+                        format!("{}: expected a non-empty list of refs", key_path(&path))
+                    })?;
+                history.refs = refs
+                    .iter()
+                    .enumerate()
+                    .map(|(index, git_ref)| {
+                        git_ref
+                            .as_str()
+                            .map(str::trim)
+                            .filter(|git_ref| !git_ref.is_empty())
+                            .map(str::to_string)
+                            .ok_or_else(|| {
+                                format!("{}[{}]: expected a ref", key_path(&path), index)
+                            })
+                    })
+                    .collect::<std::result::Result<_, _>>()?;
+            }
+            "merges" => {
+                history.merges = value
+                    .as_str()
+                    .and_then(|policy| policy.parse().ok())
+                    .ok_or_else(|| format!("{}: expected \"skip\" or \"zero\"", key_path(&path)))?;
+            }
+            "max_commits" => {
+                history.max_commits = value
+                    .as_integer()
+                    .filter(|max| *max > 0)
+                    .map(|max| max as usize)
+                    .ok_or_else(|| format!("{}: expected a positive number", key_path(&path)))?;
+            }
+            _ => return Err(format!("{}: unknown key", key_path(&path))),
+        }
+    }
+
+    Ok(history)
+}
+
+fn parse_branches(table: &Table) -> std::result::Result<BranchConfig, String> {
+    let mut branches = BranchConfig::default();
+
+    for (key, value) in table {
+        let path = ["branches", key.as_str()];
+        match key.as_str() {
+            "enabled" => {
+                branches.enabled = value
+                    .as_bool()
+                    .ok_or_else(|| format!("{}: expected true or false", key_path(&path)))?;
+            }
+            "ticket_pattern" => {
+                let pattern = value
+                    .as_str()
+                    .ok_or_else(|| format!("{}: expected a regex", key_path(&path)))?;
+                branches.ticket_pattern = Regex::new(pattern)
+                    .map_err(|_| format!("{}: invalid regex", key_path(&path)))?;
+            }
+            "ticket_field" => {
+                branches.ticket_field = value
+                    .as_str()
+                    .and_then(|field| field.parse().ok())
+                    .ok_or_else(|| format!("{}: expected \"task\" or \"tag\"", key_path(&path)))?;
+            }
+            "projects" => {
+                for (pattern, project) in expect_table(value, &path)? {
+                    let rule_path = ["branches", "projects", pattern.as_str()];
+                    let project = project
+                        .as_str()
+                        .map(str::trim)
+                        .filter(|project| !project.is_empty())
+                        .ok_or_else(|| {
+                            format!("{}: expected a project name", key_path(&rule_path))
+                        })?;
+                    let matcher = Glob::new(pattern)
+                        .map_err(|e| {
+                            format!("{}: invalid pattern: {}", key_path(&rule_path), e.kind())
+                        })?
+                        .compile_matcher();
+                    branches.projects.push(BranchRule {
+                        pattern: pattern.clone(),
+                        project: project.to_string(),
+                        matcher,
+                    });
+                }
+                branches
+                    .projects
+                    .sort_by_key(|rule| std::cmp::Reverse(rule.pattern.len()));
+            }
+            _ => return Err(format!("{}: unknown key", key_path(&path))),
+        }
+    }
+
+    Ok(branches)
+}
+
+fn parse_references(table: &Table) -> std::result::Result<ReferenceConfig, String> {
+    let mut references = ReferenceConfig::default();
+
+    for (key, value) in table {
+        let path = ["references", key.as_str()];
+        match key.as_str() {
+            "patterns" => {
+                let patterns = value
+                    .as_array()
+                    .ok_or_else(|| format!("{}: expected a list of regexes", key_path(&path)))?;
+                references.patterns = patterns
+                    .iter()
+                    .enumerate()
+                    .map(|(index, pattern)| {
+                        pattern
+                            .as_str()
+                            .and_then(|pattern| Regex::new(pattern).ok())
+                            .ok_or_else(|| {
+                                format!("{}[{}]: expected a valid regex", key_path(&path), index)
+                            })
+                    })
+                    .collect::<std::result::Result<_, _>>()?;
+            }
+            _ => return Err(format!("{}: unknown key", key_path(&path))),
+        }
+    }
+
+    Ok(references)
+}
+
+fn parse_discovery(table: &Table) -> std::result::Result<DiscoveryConfig, String> {
+    let mut discovery = DiscoveryConfig::default();
+
+    for (key, value) in table {
+        let path = ["discovery", key.as_str()];
+        match key.as_str() {
+            "roots" => {
+                discovery.roots = expect_strings(value, &path, "directory")?
+                    .iter()
+                    .map(|root| expand_home(root))
+                    .collect();
+            }
+            "prefix" => {
+                let prefix = value
+                    .as_str()
+                    .ok_or_else(|| format!("{}: expected a string", key_path(&path)))?
+                    .trim();
+                discovery.prefix = (!prefix.is_empty()).then(|| prefix.to_string());
+            }
+            "exclude" => {
+                discovery.exclude = expect_strings(value, &path, "glob")?;
+            }
+            "descriptions" => {
+                for (pattern, template) in expect_table(value, &path)? {
+                    let rule_path = ["discovery", "descriptions", pattern.as_str()];
+                    let template = template
+                        .as_str()
+                        .ok_or_else(|| format!("{}: expected a template", key_path(&rule_path)))?;
+                    let matcher = Glob::new(pattern)
+                        .map_err(|e| {
+                            format!("{}: invalid pattern: {}", key_path(&rule_path), e.kind())
+                        })?
+                        .compile_matcher();
+                    discovery
+                        .descriptions
+                        .retain(|rule| rule.pattern != *pattern);
+                    discovery.descriptions.push(DescriptionRule {
+                        pattern: pattern.clone(),
+                        template: template.to_string(),
+                        matcher,
+                    });
+                }
+                discovery
+                    .descriptions
+                    .sort_by_key(|rule| std::cmp::Reverse(rule.pattern.len()));
+            }
+            _ => return Err(format!("{}: unknown key", key_path(&path))),
+        }
+    }
+
+    Ok(discovery)
+}
+
+/// A list of non-empty strings, e.g. `roots = ["~/work"]`
+fn expect_strings(
+    value: &Value,
+    path: &[&str],
+    what: &str,
+) -> std::result::Result<Vec<String>, String> {
+    let items = value
+        .as_array()
+        .ok_or_else(|| format!("{}: expected a list", key_path(path)))?;
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            item.as_str()
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .ok_or_else(|| format!("{}[{}]: expected a {}", key_path(path), index, what))
+        })
+        .collect()
+}
+
+fn expect_email(value: &str, path: &[&str]) -> std::result::Result<String, String> {
+    let email = value.trim().to_lowercase();
+    match email.split_once('@') {
+        Some((user, domain)) if !user.is_empty() && !domain.is_empty() => Ok(email),
+        _ => Err(format!("{}: expected an email", key_path(path))),
+    }
+}
+
+pub(crate) fn expect_table<'a>(
+    value: &'a Value,
+    path: &[&str],
+) -> std::result::Result<&'a Table, String> {
+    value
+        .as_table()
+        .ok_or_else(|| format!("{}: expected a table", key_path(path)))
+}
+
+fn expect_minutes(
+    value: &Value,
+    path: &[&str],
+    allow_zero: bool,
+) -> std::result::Result<i64, String> {
+    match value.as_integer() {
+        Some(minutes) if minutes > 0 || (allow_zero && minutes == 0) => Ok(minutes),
+        _ if allow_zero => Err(format!(
+            "{}: expected a whole number of minutes (0 or more)",
+            key_path(path)
+        )),
+        _ => Err(format!(
+            "{}: expected a positive whole number of minutes",
+            key_path(path)
+        )),
+    }
+}
+
+fn validate_glob(pattern: &str, path: &[&str]) -> std::result::Result<(), String> {
+    Glob::new(pattern)
+        .map(|_| ())
+        .map_err(|e| format!("{}: invalid pattern: {}", key_path(path), e.kind()))
+}
+
+/// Dotted key path as it would be written in TOML, quoting non-bare keys
+pub(crate) fn key_path(path: &[&str]) -> String {
+    path.iter()
+        .map(|key| {
+            let bare = !key.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            if bare {
+                key.to_string()
+            } else {
+                format!("\"{}\"", key.replace('"', "\\\""))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .map(|home| home.join(rest))
+            .unwrap_or_else(|| PathBuf::from(path)),
+        None => PathBuf::from(path),
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_match_builtin_values() {
+        let settings = GitConfig::default().settings_for(Path::new("/repo"));
+
+        assert_eq!(settings.base_minutes(&CommitType::BugFix), 60);
+        assert_eq!(settings.max_per_commit(), chrono::Duration::hours(4));
+        assert_eq!(settings.file_weight("src/main.rs"), 1.5);
+        assert_eq!(settings.file_weight("Makefile"), 1.0);
+        assert_eq!(settings.file_minutes("web/app.ts"), 3);
+        assert_eq!(settings.file_minutes("styles.css"), 0);
+    }
+
+    #[test]
+    fn test_global_and_repository_overrides() {
+        let config = GitConfig::parse(
+            r#"
+            [estimation]
+            max_minutes_per_commit = 120
+
+            [estimation.base_minutes]
+            feature = 90
+
+            [file_weights]
+            "src/generated/**" = 0
+
+            [repos."/work/app".estimation.base_minutes]
+            feature = 30
+
+            [repos."/work/app".file_weights]
+            "*.rs" = 2.0
+            "#,
+        )
+        .unwrap();
+
+        let global = config.settings_for(Path::new("/elsewhere"));
+        assert_eq!(global.base_minutes(&CommitType::Feature), 90);
+        assert_eq!(global.base_minutes(&CommitType::Chore), 10);
+        assert_eq!(global.max_per_commit(), chrono::Duration::hours(2));
+        assert!(global.is_ignored("src/generated/schema.rs"));
+        assert_eq!(global.file_weight("src/lib.rs"), 1.5);
+
+        let repo = config.settings_for(Path::new("/work/app"));
+        assert_eq!(repo.base_minutes(&CommitType::Feature), 30);
+        assert_eq!(
+            repo.base_minutes.get("feature").unwrap().1,
+            ConfigSource::Repository
+        );
+        // Repository patterns are checked before global ones
+        assert_eq!(repo.file_weight("src/generated/schema.rs"), 2.0);
+
+        // Later lookups reuse the resolved settings
+        assert!(Arc::ptr_eq(
+            &repo,
+            &config.settings_for(Path::new("/work/app"))
+        ));
+        assert!(!Arc::ptr_eq(
+            &repo,
+            &config.clone().settings_for(Path::new("/work/app"))
+        ));
+    }
+
+    #[test]
+    fn test_identity_emails() {
+        let config = GitConfig::parse(
+            r#"
+            [identities]
+            "Jane@Work.example" = ["jane@home.example", "1+jane@users.noreply.github.com"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.identity_emails("JANE@home.example"),
+            vec![
+                "jane@work.example",
+                "jane@home.example",
+                "1+jane@users.noreply.github.com"
+            ]
+        );
+        assert_eq!(
+            config.identity_emails("sam@work.example"),
+            vec!["sam@work.example"]
+        );
+    }
+
+    #[test]
+    fn test_branch_tickets_and_projects() {
+        let config = GitConfig::parse(
+            r#"
+            [branches]
+            enabled = true
+            ticket_field = "tag"
+
+            [branches.projects]
+            "feature/*" = "Internal"
+            "feature/ACME-*" = "[CLIENT] Acme"
+            "#,
+        )
+        .unwrap();
+        let branches = &config.branches;
+
+        assert!(branches.enabled);
+        assert_eq!(branches.ticket_field, TicketField::Tag);
+        assert_eq!(
+            branches.ticket("feature/ACME-123-login").as_deref(),
+            Some("ACME-123")
+        );
+        assert_eq!(branches.ticket("main"), None);
+        assert_eq!(
+            branches.project_for("feature/ACME-123-login"),
+            Some("[CLIENT] Acme")
+        );
+        assert_eq!(branches.project_for("feature/search"), Some("Internal"));
+        assert_eq!(branches.project_for("main"), None);
+
+        let custom = GitConfig::parse("[branches]\nticket_pattern = 'gh-([0-9]+)'").unwrap();
+        assert!(!custom.branches.enabled);
+        assert_eq!(
+            custom.branches.ticket("fix/gh-42-crash").as_deref(),
+            Some("42")
+        );
+    }
+
+    #[test]
+    fn test_reference_extraction() {
+        let references = ReferenceConfig::default();
+        assert_eq!(
+            references.extract("Fix GH-7 crash (#123), see ACME-45 and #123 again; not C#1"),
+            vec!["GH-7", "#123", "ACME-45"]
+        );
+        assert!(references.extract("Refactor parser").is_empty());
+
+        let custom = GitConfig::parse("[references]\npatterns = ['issue-([0-9]+)']").unwrap();
+        assert_eq!(custom.references.extract("issue-9 and #1"), vec!["9"]);
+    }
+
+    #[test]
+    fn test_discovery_settings() {
+        let defaults = DiscoveryConfig::default();
+        assert!(defaults.roots.is_empty());
+        assert_eq!(defaults.prefix.as_deref(), Some("[CLIENT]"));
+        assert!(defaults.exclude.contains(&".git".to_string()));
+        assert_eq!(
+            defaults
+                .description("Acme", Path::new("/work/Acme"), true)
+                .as_deref(),
+            Some("Client project (Git repository) Location: /work/Acme")
+        );
+        assert_eq!(
+            defaults
+                .description("Acme-Release", Path::new("/work/Acme-Release"), false)
+                .as_deref(),
+            Some("Product release work Location: /work/Acme-Release")
+        );
+
+        let config = UserConfig::parse(
+            r#"
+            [discovery]
+            roots = ["/work/clients", "/work/agency"]
+            prefix = ""
+            exclude = ["archive"]
+
+            [discovery.descriptions]
+            "*" = "Client work"
+            "*Release*" = "Release {name}{git}"
+            "#,
+        )
+        .unwrap();
+        let discovery = &config.discovery;
+
+        assert_eq!(
+            discovery.roots,
+            vec![
+                PathBuf::from("/work/clients"),
+                PathBuf::from("/work/agency")
+            ]
+        );
+        assert_eq!(discovery.prefix, None);
+        assert_eq!(discovery.exclude, vec!["archive"]);
+        assert_eq!(
+            discovery
+                .description("Acme/Release-2", Path::new("/x"), false)
+                .as_deref(),
+            Some("Release Acme/Release-2")
+        );
+        assert_eq!(
+            discovery
+                .description("Acme", Path::new("/x"), true)
+                .as_deref(),
+            Some("Client work")
+        );
+    }
+
+    #[test]
+    fn test_validation_errors_name_the_key() {
+        let cases = [
+            (
+                "[estimation.base_minutes]\nfeatur = 10",
+                "estimation.base_minutes.featur",
+            ),
+            (
+                "[estimation]\nmax_minutes_per_commit = 0",
+                "estimation.max_minutes_per_commit",
+            ),
+            ("[file_weights]\n\"src/[\" = 1", "file_weights.\"src/[\""),
+            (
+                "[file_minutes]\n\"*.rs\" = \"five\"",
+                "file_minutes.\"*.rs\"",
+            ),
+            (
+                "[repos.\"/x\".estimation]\ncap = 1",
+                "repos.\"/x\".estimation.cap",
+            ),
+            ("[estimaton]\n", "estimaton"),
+            ("[identities]\njane = []", "identities.jane"),
+            ("[history]\nmerges = \"drop\"", "history.merges"),
+            ("[history]\nrefs = [\"main\", 3]", "history.refs[1]"),
+            (
+                "[branches]\nticket_pattern = \"(\"",
+                "branches.ticket_pattern",
+            ),
+            (
+                "[references]\npatterns = [\"#\", \"[\"]",
+                "references.patterns[1]",
+            ),
+            (
+                "[branches.projects]\n\"feature/*\" = 3",
+                "branches.projects.\"feature/*\"",
+            ),
+            (
+                "[identities]\n\"a@x.io\" = [\"b\"]",
+                "identities.\"a@x.io\"[0]",
+            ),
+            // Discovery settings live in config.toml
+            ("[discovery]\nprefix = \"\"", "discovery"),
+        ];
+
+        for (content, key) in cases {
+            let error = GitConfig::parse(content).unwrap_err();
+            assert!(
+                error.starts_with(&format!("{}:", key)),
+                "expected error for {} but got: {}",
+                key,
+                error
+            );
+        }
+
+        let user_cases = [
+            (
+                "[discovery]\nroots = [\"/work\", \"\"]",
+                "discovery.roots[1]",
+            ),
+            (
+                "[discovery.descriptions]\n\"[\" = \"x\"",
+                "discovery.descriptions.\"[\"",
+            ),
+            ("[discovery]\ndepth = 2", "discovery.depth"),
+            ("[estimation]\n", "estimation"),
+        ];
+
+        for (content, key) in user_cases {
+            let error = UserConfig::parse(content).unwrap_err();
+            assert!(
+                error.starts_with(&format!("{}:", key)),
+                "expected error for {} but got: {}",
+                key,
+                error
+            );
+        }
+    }
+}
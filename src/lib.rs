@@ -1,4 +1,6 @@
 pub mod cli;
+pub mod config;
+pub mod manifest;
 pub mod models;
 pub mod repository;
 pub mod services;
@@ -21,6 +23,10 @@ pub enum TimeSpanError {
     ProjectHasTimeEntries(String),
     #[error("Invalid duration format: {0}")]
     InvalidDuration(String),
+    #[error("Time entry is locked by an invoice: {0}")]
+    TimeEntryLocked(String),
+    #[error("Configuration error: {0}")]
+    Config(String),
     #[error("Invalid input: {0}")]
     InvalidInput(String),
     #[error("IO error: {0}")]
@@ -0,0 +1,237 @@
+//! Per-directory project manifests (`.timespan.toml`), found in a directory
+//! or any of its ancestors.
+//!
+//! ```toml
+//! [project]
+//! name = "Website"          # defaults to the directory name
+//! client = "Acme"           # makes it "[CLIENT] Acme/Website"
+//! description = "Marketing site rebuild"
+//! tags = ["frontend"]       # added to every timer started here
+//! billable = true
+//! hourly_rate = 120.50
+//!
+//! # Same keys as a [repos."<path>"] section in git-config.toml
+//! [estimation.base_minutes]
+//! feature = 30
+//!
+//! [file_weights]
+//! "*.tsx" = 1.3
+//! ```
+//!
+//! Estimation values here sit between the global section of
+//! `git-config.toml` and its repository sections.
+
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+use crate::config::{expect_table, key_path, parse_estimation, EstimationConfig};
+use crate::{Result, TimeSpanError};
+
+pub const MANIFEST_FILE: &str = ".timespan.toml";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectManifest {
+    /// The `.timespan.toml` this was read from
+    pub path: PathBuf,
+    pub name: Option<String>,
+    pub client: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub billable: Option<bool>,
+    /// Hourly billing rate in minor currency units (e.g. cents)
+    pub hourly_rate_cents: Option<i64>,
+    pub estimation: EstimationConfig,
+}
+
+impl ProjectManifest {
+    /// The nearest `.timespan.toml` in `start` or one of its ancestors
+    pub fn find(start: &Path) -> Result<Option<Self>> {
+        let start = std::fs::canonicalize(start).unwrap_or_else(|_| start.to_path_buf());
+        match start
+            .ancestors()
+            .map(|dir| dir.join(MANIFEST_FILE))
+            .find(|path| path.is_file())
+        {
+            Some(path) => Self::load(&path).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            TimeSpanError::Config(format!("{}: could not read file: {}", path.display(), e))
+        })?;
+
+        let mut manifest = Self::parse(&content)
+            .map_err(|e| TimeSpanError::Config(format!("{}: {}", path.display(), e)))?;
+        manifest.path = path.to_path_buf();
+        Ok(manifest)
+    }
+
+    /// Parse manifest text. Errors name the offending key, e.g.
+    /// `project.hourly_rate: expected a non-negative amount`.
+    pub fn parse(content: &str) -> std::result::Result<Self, String> {
+        let mut table: Table = content
+            .parse()
+            .map_err(|e: toml::de::Error| format!("invalid TOML: {}", e.message().trim_end()))?;
+
+        let mut manifest = Self {
+            path: PathBuf::new(),
+            name: None,
+            client: None,
+            description: None,
+            tags: Vec::new(),
+            billable: None,
+            hourly_rate_cents: None,
+            estimation: EstimationConfig::default(),
+        };
+
+        if let Some(project) = table.remove("project") {
+            for (key, value) in expect_table(&project, &["project"])? {
+                let path = ["project", key.as_str()];
+                match key.as_str() {
+                    "name" => manifest.name = Some(expect_text(value, &path)?),
+                    "client" => manifest.client = Some(expect_text(value, &path)?),
+                    "description" => manifest.description = Some(expect_text(value, &path)?),
+                    "tags" => {
+                        let tags = value
+                            .as_array()
+                            .ok_or_else(|| format!("{}: expected a list", key_path(&path)))?;
+                        for tag in tags {
+                            manifest.tags.push(expect_text(tag, &path)?);
+                        }
+                    }
+                    "billable" => {
+                        manifest.billable = Some(value.as_bool().ok_or_else(|| {
+                            format!("{}: expected true or false", key_path(&path))
+                        })?);
+                    }
+                    "hourly_rate" => {
+                        let rate = match value {
+                            Value::Integer(rate) => *rate as f64,
+                            Value::Float(rate) => *rate,
+                            _ => -1.0,
+                        };
+                        if !rate.is_finite() || rate < 0.0 {
+                            return Err(format!(
+                                "{}: expected a non-negative amount",
+                                key_path(&path)
+                            ));
+                        }
+                        manifest.hourly_rate_cents = Some((rate * 100.0).round() as i64);
+                    }
+                    _ => return Err(format!("{}: unknown key", key_path(&path))),
+                }
+            }
+        }
+
+        manifest.estimation = parse_estimation(&table, &[])?;
+        Ok(manifest)
+    }
+
+    /// Directory the manifest describes
+    pub fn directory(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new("."))
+    }
+
+    /// `name`, else the directory name; with a client, `[CLIENT] <client>/<name>`
+    pub fn project_name(&self) -> String {
+        let name = self.name.clone().unwrap_or_else(|| {
+            self.directory()
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+        match &self.client {
+            Some(client) if client.eq_ignore_ascii_case(&name) => format!("[CLIENT] {}", client),
+            Some(client) => format!("[CLIENT] {}/{}", client, name),
+            None => name,
+        }
+    }
+}
+
+fn expect_text(value: &Value, path: &[&str]) -> std::result::Result<String, String> {
+    value
+        .as_str()
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| format!("{}: expected a non-empty string", key_path(path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ConfigSource, GitConfig};
+    use crate::models::CommitType;
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest = ProjectManifest::parse(
+            r#"
+            [project]
+            name = "Website"
+            client = "Acme"
+            tags = ["frontend", "web"]
+            billable = false
+            hourly_rate = 120.5
+
+            [estimation.base_minutes]
+            feature = 30
+
+            [file_weights]
+            "*.tsx" = 1.3
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.project_name(), "[CLIENT] Acme/Website");
+        assert_eq!(manifest.tags, vec!["frontend", "web"]);
+        assert_eq!(manifest.billable, Some(false));
+        assert_eq!(manifest.hourly_rate_cents, Some(12050));
+        assert_eq!(manifest.estimation.base_minutes["feature"], 30);
+        assert_eq!(manifest.estimation.file_weights["*.tsx"], 1.3);
+
+        let errors = [
+            ("[project]\nhourly_rate = -5", "project.hourly_rate"),
+            ("[project]\ntags = \"web\"", "project.tags"),
+            ("[project]\nowner = \"me\"", "project.owner: unknown key"),
+            (
+                "[estimation.base_minutes]\nfeat = 3",
+                "estimation.base_minutes.feat",
+            ),
+        ];
+        for (content, expected) in errors {
+            let error = ProjectManifest::parse(content).unwrap_err();
+            assert!(error.contains(expected), "{}: {}", content, error);
+        }
+    }
+
+    #[test]
+    fn test_find_manifest_in_ancestors() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let nested = dir.path().join("app").join("src");
+        std::fs::create_dir_all(&nested).unwrap();
+        assert!(ProjectManifest::find(&nested).unwrap().is_none());
+
+        std::fs::write(
+            dir.path().join("app").join(MANIFEST_FILE),
+            "[project]\nclient = \"Acme\"\n\n[estimation]\nmax_minutes_per_commit = 90\n",
+        )
+        .unwrap();
+        let manifest = ProjectManifest::find(&nested).unwrap().unwrap();
+        assert_eq!(manifest.project_name(), "[CLIENT] Acme/app");
+        assert!(manifest.directory().ends_with("app"));
+
+        // Estimation overrides apply below the global config
+        let settings = GitConfig::default().settings_for(&nested);
+        assert_eq!(
+            settings.max_minutes_per_commit,
+            (90, ConfigSource::Manifest)
+        );
+        assert_eq!(settings.base_minutes(&CommitType::Feature), 45);
+
+        std::fs::write(dir.path().join("app").join(MANIFEST_FILE), "[project\n").unwrap();
+        assert!(ProjectManifest::find(&nested).is_err());
+    }
+}
@@ -1,6 +1,6 @@
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -10,6 +10,18 @@ pub struct Project {
     pub description: Option<String>,
     pub directory_path: Option<String>,
     pub is_client_project: bool,
+    /// Hourly billing rate in minor currency units (e.g. cents)
+    pub hourly_rate_cents: Option<i64>,
+    /// Non-billable projects never appear on invoices
+    #[serde(default = "default_billable")]
+    pub billable: bool,
+    /// Overrides the report-wide rounding rule for this project
+    pub rounding_rule: Option<RoundingRule>,
+    /// `origin` URL of the project's repository, used to recognise the
+    /// directory after it moves
+    pub git_remote_url: Option<String>,
+    /// Set when discovery prunes a project whose directory is gone
+    pub archived_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -24,6 +36,14 @@ pub struct TimeEntry {
     pub end_time: Option<DateTime<Utc>>,
     pub duration: Option<Duration>,
     pub tags: Vec<String>,
+    /// Invoice this entry was billed on; invoiced entries are locked
+    pub invoice_id: Option<Uuid>,
+    /// Git branch checked out when the entry was tracked
+    #[serde(default)]
+    pub branch: Option<String>,
+    /// Commits made in the project's repository while the entry was tracked
+    #[serde(default)]
+    pub commit_hashes: Vec<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -36,11 +56,19 @@ pub struct Timer {
     pub task_description: Option<String>,
     pub start_time: DateTime<Utc>,
     pub tags: Vec<String>,
+    /// Git branch checked out when the timer started
+    #[serde(default)]
+    pub branch: Option<String>,
+    /// Working directory of the repository `branch` was read from
+    #[serde(default)]
+    pub repository: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TimeReport {
     pub total_duration: Duration,
+    /// Total after rounding; `None` when no rounding was applied
+    pub rounded_total_duration: Option<Duration>,
     pub entries: Vec<TimeEntry>,
     pub project_summaries: Vec<ProjectSummary>,
     pub date_range: DateRange,
@@ -50,15 +78,183 @@ pub struct TimeReport {
 pub struct ProjectSummary {
     pub project_name: String,
     pub total_duration: Duration,
+    pub rounded_duration: Option<Duration>,
+    pub rounding_rule: Option<RoundingRule>,
     pub entry_count: usize,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportPeriod {
+    Day,
+    Week,
+    Month,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectChange {
+    Appeared,
+    Disappeared,
+    Increased,
+    Decreased,
+    Unchanged,
+}
+
+/// Per-project difference between two periods. Durations are in seconds so
+/// the JSON export can be consumed directly by dashboards.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectDelta {
+    pub project_name: String,
+    pub current_seconds: i64,
+    pub previous_seconds: i64,
+    pub delta_seconds: i64,
+    /// `None` when the project had no time in the previous period
+    pub percent_change: Option<f64>,
+    pub change: ProjectChange,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeriodComparison {
+    pub period: ReportPeriod,
+    pub current_range: DateRange,
+    pub previous_range: DateRange,
+    pub current_seconds: i64,
+    pub previous_seconds: i64,
+    pub delta_seconds: i64,
+    pub percent_change: Option<f64>,
+    pub projects: Vec<ProjectDelta>,
+}
+
+/// Weekly projects × days grid. Cell values are in seconds; `days` always
+/// holds the seven dates Monday to Sunday.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Timesheet {
+    pub week_start: NaiveDate,
+    pub days: Vec<NaiveDate>,
+    pub rows: Vec<TimesheetRow>,
+    pub daily_totals: Vec<i64>,
+    pub total_seconds: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimesheetRow {
+    pub project_name: String,
+    pub daily_seconds: Vec<i64>,
+    pub total_seconds: i64,
+}
+
+/// Commit velocity over a period, built from imported git time entries.
+/// Durations are in seconds so the JSON export can be consumed directly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitActivityReport {
+    pub date_range: DateRange,
+    pub total_commits: usize,
+    pub estimated_seconds: i64,
+    pub average_seconds: i64,
+    /// `None` when there were no commits
+    pub average_confidence: Option<f32>,
+    /// Every day in the range, including days without commits
+    pub daily: Vec<DailyCommits>,
+    pub most_active_day: Option<DailyCommits>,
+    /// Most estimated time first
+    pub repositories: Vec<RepositoryActivity>,
+    /// Most commits first
+    pub commit_types: Vec<CommitTypeActivity>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyCommits {
+    pub date: NaiveDate,
+    pub commits: usize,
+    pub estimated_seconds: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepositoryActivity {
+    pub repository_path: PathBuf,
+    /// Last path component of the repository
+    pub name: String,
+    pub projects: Vec<String>,
+    pub commits: usize,
+    pub estimated_seconds: i64,
+    pub average_confidence: f32,
+    pub last_commit_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitTypeActivity {
+    pub commit_type: CommitType,
+    pub commits: usize,
+    pub estimated_seconds: i64,
+}
+
+/// Rounding direction; reports and project overrides default to rounding
+/// up, the same way invoices bill time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundingMode {
+    #[default]
+    Up,
+    Down,
+    Nearest,
+}
+
+/// Whether rounding applies to every entry or to each day's total
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundingScope {
+    Entry,
+    Daily,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundingRule {
+    pub mode: RoundingMode,
+    pub scope: RoundingScope,
+    pub increment_minutes: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DateRange {
     pub start: DateTime<Utc>,
     pub end: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InvoiceGrouping {
+    Project,
+    Task,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceLineItem {
+    pub project_name: String,
+    pub description: String,
+    pub entry_ids: Vec<Uuid>,
+    pub duration: Duration,
+    pub billed_duration: Duration,
+    pub hourly_rate_cents: Option<i64>,
+    pub amount_cents: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Invoice {
+    pub id: Uuid,
+    pub number: String,
+    pub client: String,
+    pub period: DateRange,
+    pub grouping: InvoiceGrouping,
+    pub rounding_increment_minutes: i64,
+    pub currency: String,
+    pub line_items: Vec<InvoiceLineItem>,
+    pub total_duration: Duration,
+    pub total_billed_duration: Duration,
+    pub total_amount_cents: i64,
+    pub created_at: DateTime<Utc>,
+}
+
 impl Project {
     pub fn new(name: String, description: Option<String>) -> Self {
         let now = Utc::now();
@@ -68,6 +264,11 @@ impl Project {
             description,
             directory_path: None,
             is_client_project: false,
+            hourly_rate_cents: None,
+            billable: true,
+            rounding_rule: None,
+            git_remote_url: None,
+            archived_at: None,
             created_at: now,
             updated_at: now,
         }
@@ -85,6 +286,11 @@ impl Project {
             description,
             directory_path: Some(directory_path),
             is_client_project: true,
+            hourly_rate_cents: None,
+            billable: true,
+            rounding_rule: None,
+            git_remote_url: None,
+            archived_at: None,
             created_at: now,
             updated_at: now,
         }
@@ -94,6 +300,66 @@ impl Project {
         self.description = description;
         self.updated_at = Utc::now();
     }
+
+    pub fn set_hourly_rate(&mut self, hourly_rate_cents: Option<i64>) {
+        self.hourly_rate_cents = hourly_rate_cents;
+        self.updated_at = Utc::now();
+    }
+
+    pub fn set_rounding_rule(&mut self, rounding_rule: Option<RoundingRule>) {
+        self.rounding_rule = rounding_rule;
+        self.updated_at = Utc::now();
+    }
+
+    pub fn is_archived(&self) -> bool {
+        self.archived_at.is_some()
+    }
+
+    /// Check whether this project belongs to the given client, ignoring a
+    /// bracketed prefix such as `[CLIENT]`. Nested projects named
+    /// `<client>/<project>` belong to their client too.
+    pub fn matches_client(&self, client: &str) -> bool {
+        let client = client.trim();
+        let name = self.name.trim();
+        let bare_name = name
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once("] "))
+            .map(|(_, rest)| rest.trim())
+            .unwrap_or(name);
+        let client_part = bare_name
+            .split_once('/')
+            .map(|(client, _)| client)
+            .unwrap_or(bare_name);
+
+        name.eq_ignore_ascii_case(client)
+            || bare_name.eq_ignore_ascii_case(client)
+            || client_part.eq_ignore_ascii_case(client)
+    }
+}
+
+fn default_billable() -> bool {
+    true
+}
+
+/// The rule that picked the project for a working directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProjectMatch {
+    /// The project's `directory_path` contains the directory
+    Directory(PathBuf),
+    /// The enclosing git repository's name or `origin` remote names the project
+    Repository(PathBuf),
+    /// A `.timespan.toml` in the directory or one of its ancestors
+    Manifest(PathBuf),
+}
+
+impl ProjectMatch {
+    pub fn describe(&self) -> String {
+        match self {
+            ProjectMatch::Directory(path) => format!("project directory {}", path.display()),
+            ProjectMatch::Repository(path) => format!("git repository {}", path.display()),
+            ProjectMatch::Manifest(path) => format!("manifest {}", path.display()),
+        }
+    }
 }
 
 impl TimeEntry {
@@ -113,6 +379,9 @@ impl TimeEntry {
             end_time: None,
             duration: None,
             tags: Vec::new(),
+            invoice_id: None,
+            branch: None,
+            commit_hashes: Vec::new(),
             created_at: now,
             updated_at: now,
         }
@@ -147,6 +416,10 @@ impl TimeEntry {
         self.end_time.is_none()
     }
 
+    pub fn is_invoiced(&self) -> bool {
+        self.invoice_id.is_some()
+    }
+
     pub fn current_duration(&self) -> Duration {
         match self.end_time {
             Some(end) => end - self.start_time,
@@ -169,6 +442,8 @@ impl Timer {
             task_description,
             start_time,
             tags: Vec::new(),
+            branch: None,
+            repository: None,
         }
     }
 
@@ -198,6 +473,8 @@ impl TimeReport {
                 .or_insert_with(|| ProjectSummary {
                     project_name: entry.project_name.clone(),
                     total_duration: Duration::zero(),
+                    rounded_duration: None,
+                    rounding_rule: None,
                     entry_count: 0,
                 });
 
@@ -211,11 +488,576 @@ impl TimeReport {
 
         Self {
             total_duration,
+            rounded_total_duration: None,
             entries,
             project_summaries,
             date_range: DateRange { start, end },
         }
     }
+
+    /// Fill in rounded durations next to the raw ones. Projects with an
+    /// entry in `overrides` use that rule, all others use `default_rule`;
+    /// projects without any rule keep their raw duration.
+    pub fn apply_rounding(
+        &mut self,
+        default_rule: Option<RoundingRule>,
+        overrides: &std::collections::HashMap<String, RoundingRule>,
+    ) {
+        let mut rounded_total = Duration::zero();
+
+        for summary in &mut self.project_summaries {
+            let rule = overrides
+                .get(&summary.project_name)
+                .copied()
+                .or(default_rule);
+
+            let rounded = match rule {
+                Some(rule) => {
+                    let entries: Vec<&TimeEntry> = self
+                        .entries
+                        .iter()
+                        .filter(|e| e.project_name == summary.project_name)
+                        .collect();
+                    rule.round_entries(&entries)
+                }
+                None => summary.total_duration,
+            };
+
+            summary.rounded_duration = Some(rounded);
+            summary.rounding_rule = rule;
+            rounded_total += rounded;
+        }
+
+        self.rounded_total_duration = Some(rounded_total);
+    }
+}
+
+impl ReportPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportPeriod::Day => "day",
+            ReportPeriod::Week => "week",
+            ReportPeriod::Month => "month",
+        }
+    }
+
+    /// The day, Monday-to-Sunday week or calendar month containing `date`
+    pub fn range_containing(&self, date: DateTime<Utc>) -> DateRange {
+        use chrono::{Datelike, Months};
+
+        let day = date.date_naive();
+        let (first, last) = match self {
+            ReportPeriod::Day => (day, day),
+            ReportPeriod::Week => {
+                let monday = day - Duration::days(day.weekday().num_days_from_monday() as i64);
+                (monday, monday + Duration::days(6))
+            }
+            ReportPeriod::Month => {
+                let first = day.with_day(1).unwrap();
+                let last = first.checked_add_months(Months::new(1)).unwrap() - Duration::days(1);
+                (first, last)
+            }
+        };
+
+        DateRange {
+            start: first.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+            end: last.and_hms_opt(23, 59, 59).unwrap().and_utc(),
+        }
+    }
+}
+
+impl std::str::FromStr for ReportPeriod {
+    type Err = crate::TimeSpanError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "day" | "today" => Ok(ReportPeriod::Day),
+            "week" => Ok(ReportPeriod::Week),
+            "month" => Ok(ReportPeriod::Month),
+            other => Err(crate::TimeSpanError::InvalidInput(format!(
+                "Unknown report period: {}",
+                other
+            ))),
+        }
+    }
+}
+
+impl ProjectDelta {
+    pub fn new(project_name: String, current: Duration, previous: Duration) -> Self {
+        let current_seconds = current.num_seconds();
+        let previous_seconds = previous.num_seconds();
+        let delta_seconds = current_seconds - previous_seconds;
+
+        let change = match (current_seconds, previous_seconds) {
+            (c, 0) if c > 0 => ProjectChange::Appeared,
+            (0, p) if p > 0 => ProjectChange::Disappeared,
+            _ if delta_seconds > 0 => ProjectChange::Increased,
+            _ if delta_seconds < 0 => ProjectChange::Decreased,
+            _ => ProjectChange::Unchanged,
+        };
+
+        Self {
+            project_name,
+            current_seconds,
+            previous_seconds,
+            delta_seconds,
+            percent_change: percent_change(current_seconds, previous_seconds),
+            change,
+        }
+    }
+}
+
+impl PeriodComparison {
+    pub fn new(period: ReportPeriod, current: &TimeReport, previous: &TimeReport) -> Self {
+        let mut totals: std::collections::BTreeMap<String, (Duration, Duration)> =
+            std::collections::BTreeMap::new();
+        for summary in &current.project_summaries {
+            totals
+                .entry(summary.project_name.clone())
+                .or_insert((Duration::zero(), Duration::zero()))
+                .0 += summary.total_duration;
+        }
+        for summary in &previous.project_summaries {
+            totals
+                .entry(summary.project_name.clone())
+                .or_insert((Duration::zero(), Duration::zero()))
+                .1 += summary.total_duration;
+        }
+
+        let mut projects: Vec<ProjectDelta> = totals
+            .into_iter()
+            .map(|(name, (current, previous))| ProjectDelta::new(name, current, previous))
+            .collect();
+        // Largest movers first
+        projects.sort_by(|a, b| {
+            b.delta_seconds
+                .abs()
+                .cmp(&a.delta_seconds.abs())
+                .then_with(|| a.project_name.cmp(&b.project_name))
+        });
+
+        let current_seconds = current.total_duration.num_seconds();
+        let previous_seconds = previous.total_duration.num_seconds();
+
+        Self {
+            period,
+            current_range: current.date_range.clone(),
+            previous_range: previous.date_range.clone(),
+            current_seconds,
+            previous_seconds,
+            delta_seconds: current_seconds - previous_seconds,
+            percent_change: percent_change(current_seconds, previous_seconds),
+            projects,
+        }
+    }
+}
+
+impl CommitActivityReport {
+    /// Summarise the entries committed within `date_range`
+    pub fn new(entries: &[GitTimeEntry], date_range: DateRange) -> Self {
+        let entries: Vec<&GitTimeEntry> = entries
+            .iter()
+            .filter(|entry| {
+                entry.committed_at >= date_range.start && entry.committed_at <= date_range.end
+            })
+            .collect();
+
+        let total_commits = entries.len();
+        let estimated_seconds: i64 = entries.iter().map(|e| e.estimated_time.num_seconds()).sum();
+        let average_seconds = if total_commits > 0 {
+            estimated_seconds / total_commits as i64
+        } else {
+            0
+        };
+        let average_confidence = (total_commits > 0).then(|| {
+            entries.iter().map(|e| e.confidence_score).sum::<f32>() / total_commits as f32
+        });
+
+        let mut daily = Vec::new();
+        let mut day = date_range.start.date_naive();
+        while day <= date_range.end.date_naive() {
+            let on_day: Vec<_> = entries
+                .iter()
+                .filter(|entry| entry.committed_at.date_naive() == day)
+                .collect();
+            daily.push(DailyCommits {
+                date: day,
+                commits: on_day.len(),
+                estimated_seconds: on_day.iter().map(|e| e.estimated_time.num_seconds()).sum(),
+            });
+            day += Duration::days(1);
+        }
+
+        // Earliest day wins a tie
+        let most_active_day = daily
+            .iter()
+            .filter(|day| day.commits > 0)
+            .fold(None::<&DailyCommits>, |best, day| match best {
+                Some(best) if best.commits >= day.commits => Some(best),
+                _ => Some(day),
+            })
+            .cloned();
+
+        let mut commit_types: Vec<CommitTypeActivity> = Vec::new();
+        for entry in &entries {
+            match commit_types
+                .iter_mut()
+                .find(|activity| activity.commit_type == entry.commit_type)
+            {
+                Some(activity) => {
+                    activity.commits += 1;
+                    activity.estimated_seconds += entry.estimated_time.num_seconds();
+                }
+                None => commit_types.push(CommitTypeActivity {
+                    commit_type: entry.commit_type.clone(),
+                    commits: 1,
+                    estimated_seconds: entry.estimated_time.num_seconds(),
+                }),
+            }
+        }
+        commit_types.sort_by(|a, b| {
+            b.commits
+                .cmp(&a.commits)
+                .then_with(|| a.commit_type.as_str().cmp(b.commit_type.as_str()))
+        });
+
+        Self {
+            date_range,
+            total_commits,
+            estimated_seconds,
+            average_seconds,
+            average_confidence,
+            daily,
+            most_active_day,
+            repositories: RepositoryActivity::from_entries(&entries),
+            commit_types,
+        }
+    }
+}
+
+impl RepositoryActivity {
+    /// One row per repository, most estimated time first
+    pub fn from_entries(entries: &[&GitTimeEntry]) -> Vec<Self> {
+        let mut repositories: Vec<Self> = Vec::new();
+
+        for entry in entries {
+            let seconds = entry.estimated_time.num_seconds();
+            match repositories
+                .iter_mut()
+                .find(|repo| repo.repository_path == entry.repository_path)
+            {
+                Some(repo) => {
+                    // Running total; divided by the count below
+                    repo.average_confidence += entry.confidence_score;
+                    repo.commits += 1;
+                    repo.estimated_seconds += seconds;
+                    repo.last_commit_at = repo.last_commit_at.max(entry.committed_at);
+                    if !repo.projects.contains(&entry.project_name) {
+                        repo.projects.push(entry.project_name.clone());
+                    }
+                }
+                None => repositories.push(Self {
+                    repository_path: entry.repository_path.clone(),
+                    name: entry
+                        .repository_path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_else(|| entry.repository_path.display().to_string()),
+                    projects: vec![entry.project_name.clone()],
+                    commits: 1,
+                    estimated_seconds: seconds,
+                    average_confidence: entry.confidence_score,
+                    last_commit_at: entry.committed_at,
+                }),
+            }
+        }
+
+        for repo in &mut repositories {
+            repo.average_confidence /= repo.commits as f32;
+        }
+        repositories.sort_by(|a, b| {
+            b.estimated_seconds
+                .cmp(&a.estimated_seconds)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        repositories
+    }
+}
+
+impl Timesheet {
+    /// Build the grid from a weekly report. Entries are bucketed by the day
+    /// they started on; running entries are ignored.
+    pub fn from_report(report: &TimeReport) -> Self {
+        let week_start = report.date_range.start.date_naive();
+        let days: Vec<NaiveDate> = (0..7)
+            .map(|offset| week_start + Duration::days(offset))
+            .collect();
+
+        let mut rows: std::collections::BTreeMap<String, Vec<i64>> =
+            std::collections::BTreeMap::new();
+        for entry in &report.entries {
+            let Some(duration) = entry.duration else {
+                continue;
+            };
+            let offset = (entry.start_time.date_naive() - week_start).num_days();
+            if !(0..7).contains(&offset) {
+                continue;
+            }
+            rows.entry(entry.project_name.clone())
+                .or_insert_with(|| vec![0; 7])[offset as usize] += duration.num_seconds();
+        }
+
+        let mut daily_totals = vec![0; 7];
+        let rows: Vec<TimesheetRow> = rows
+            .into_iter()
+            .map(|(project_name, daily_seconds)| {
+                for (total, seconds) in daily_totals.iter_mut().zip(&daily_seconds) {
+                    *total += seconds;
+                }
+                TimesheetRow {
+                    project_name,
+                    total_seconds: daily_seconds.iter().sum(),
+                    daily_seconds,
+                }
+            })
+            .collect();
+
+        Self {
+            week_start,
+            days,
+            total_seconds: daily_totals.iter().sum(),
+            daily_totals,
+            rows,
+        }
+    }
+
+    /// Format a cell as decimal hours ("7.25") or hours and minutes ("7:15")
+    pub fn format_seconds(seconds: i64, decimal: bool) -> String {
+        if decimal {
+            format!("{:.2}", seconds as f64 / 3600.0)
+        } else {
+            let minutes = seconds / 60;
+            format!("{}:{:02}", minutes / 60, minutes % 60)
+        }
+    }
+}
+
+fn percent_change(current: i64, previous: i64) -> Option<f64> {
+    if previous == 0 {
+        None
+    } else {
+        Some((current - previous) as f64 / previous as f64 * 100.0)
+    }
+}
+
+impl RoundingMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoundingMode::Up => "up",
+            RoundingMode::Down => "down",
+            RoundingMode::Nearest => "nearest",
+        }
+    }
+}
+
+impl std::str::FromStr for RoundingMode {
+    type Err = crate::TimeSpanError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "up" => Ok(RoundingMode::Up),
+            "down" => Ok(RoundingMode::Down),
+            "nearest" => Ok(RoundingMode::Nearest),
+            other => Err(crate::TimeSpanError::InvalidInput(format!(
+                "Unknown rounding mode: {}",
+                other
+            ))),
+        }
+    }
+}
+
+impl RoundingScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoundingScope::Entry => "entry",
+            RoundingScope::Daily => "daily",
+        }
+    }
+}
+
+impl std::str::FromStr for RoundingScope {
+    type Err = crate::TimeSpanError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "entry" => Ok(RoundingScope::Entry),
+            "daily" | "day" => Ok(RoundingScope::Daily),
+            other => Err(crate::TimeSpanError::InvalidInput(format!(
+                "Unknown rounding scope: {}",
+                other
+            ))),
+        }
+    }
+}
+
+impl RoundingRule {
+    pub fn new(mode: RoundingMode, scope: RoundingScope, increment_minutes: i64) -> Self {
+        Self {
+            mode,
+            scope,
+            increment_minutes,
+        }
+    }
+
+    /// Round a single duration to the rule's increment
+    pub fn round(&self, duration: Duration) -> Duration {
+        if self.increment_minutes <= 0 {
+            return duration;
+        }
+
+        let increment = self.increment_minutes * 60;
+        let seconds = duration.num_seconds();
+        let rounded = match self.mode {
+            RoundingMode::Up => (seconds + increment - 1).div_euclid(increment),
+            RoundingMode::Down => seconds.div_euclid(increment),
+            RoundingMode::Nearest => (seconds + increment / 2).div_euclid(increment),
+        };
+
+        Duration::seconds(rounded * increment)
+    }
+
+    /// Round a set of entries according to the rule's scope
+    pub fn round_entries(&self, entries: &[&TimeEntry]) -> Duration {
+        match self.scope {
+            RoundingScope::Entry => entries
+                .iter()
+                .filter_map(|e| e.duration)
+                .fold(Duration::zero(), |acc, d| acc + self.round(d)),
+            RoundingScope::Daily => {
+                let mut daily_totals = std::collections::BTreeMap::new();
+                for entry in entries {
+                    if let Some(duration) = entry.duration {
+                        *daily_totals
+                            .entry(entry.start_time.date_naive())
+                            .or_insert_with(Duration::zero) += duration;
+                    }
+                }
+
+                daily_totals
+                    .into_values()
+                    .fold(Duration::zero(), |acc, d| acc + self.round(d))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for RoundingRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} to {}m per {}",
+            self.mode.as_str(),
+            self.increment_minutes,
+            self.scope.as_str()
+        )
+    }
+}
+
+impl InvoiceGrouping {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InvoiceGrouping::Project => "project",
+            InvoiceGrouping::Task => "task",
+        }
+    }
+}
+
+impl std::str::FromStr for InvoiceGrouping {
+    type Err = crate::TimeSpanError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "project" => Ok(InvoiceGrouping::Project),
+            "task" => Ok(InvoiceGrouping::Task),
+            other => Err(crate::TimeSpanError::InvalidInput(format!(
+                "Unknown invoice grouping: {}",
+                other
+            ))),
+        }
+    }
+}
+
+impl InvoiceLineItem {
+    pub fn new(
+        project_name: String,
+        description: String,
+        entry_ids: Vec<Uuid>,
+        duration: Duration,
+        rounding_increment_minutes: i64,
+        hourly_rate_cents: Option<i64>,
+    ) -> Self {
+        let billed_duration = RoundingRule::new(
+            RoundingMode::Up,
+            RoundingScope::Entry,
+            rounding_increment_minutes,
+        )
+        .round(duration);
+        let amount_cents = hourly_rate_cents
+            .map(|rate| (billed_duration.num_seconds() * rate + 1800) / 3600)
+            .unwrap_or(0);
+
+        Self {
+            project_name,
+            description,
+            entry_ids,
+            duration,
+            billed_duration,
+            hourly_rate_cents,
+            amount_cents,
+        }
+    }
+}
+
+impl Invoice {
+    pub fn new(
+        number: String,
+        client: String,
+        period: DateRange,
+        grouping: InvoiceGrouping,
+        rounding_increment_minutes: i64,
+        currency: String,
+        line_items: Vec<InvoiceLineItem>,
+    ) -> Self {
+        let total_duration = line_items
+            .iter()
+            .fold(Duration::zero(), |acc, item| acc + item.duration);
+        let total_billed_duration = line_items
+            .iter()
+            .fold(Duration::zero(), |acc, item| acc + item.billed_duration);
+        let total_amount_cents = line_items.iter().map(|item| item.amount_cents).sum();
+
+        Self {
+            id: Uuid::new_v4(),
+            number,
+            client,
+            period,
+            grouping,
+            rounding_increment_minutes,
+            currency,
+            line_items,
+            total_duration,
+            total_billed_duration,
+            total_amount_cents,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn entry_ids(&self) -> Vec<Uuid> {
+        self.line_items
+            .iter()
+            .flat_map(|item| item.entry_ids.iter().copied())
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -255,6 +1097,17 @@ mod tests {
         assert!(project.updated_at > original_updated_at);
     }
 
+    #[test]
+    fn test_project_matches_client() {
+        let project = Project::new("[CLIENT] Acme".to_string(), None);
+
+        assert!(project.matches_client("Acme"));
+        assert!(project.matches_client("acme"));
+        assert!(project.matches_client("[CLIENT] Acme"));
+        assert!(!project.matches_client("Acme Labs"));
+        assert!(Project::new("Acme".to_string(), None).matches_client("Acme"));
+    }
+
     #[test]
     fn test_time_entry_creation() {
         let project_id = Uuid::new_v4();
@@ -413,6 +1266,392 @@ mod tests {
         assert_eq!(report.date_range.start, start_time);
         assert_eq!(report.date_range.end, end_time);
     }
+
+    #[test]
+    fn test_rounding_rule_modes() {
+        let d = Duration::minutes(7);
+        assert_eq!(RoundingMode::default(), RoundingMode::Up);
+
+        let up = RoundingRule::new(RoundingMode::Up, RoundingScope::Entry, 6);
+        let down = RoundingRule::new(RoundingMode::Down, RoundingScope::Entry, 6);
+        let nearest = RoundingRule::new(RoundingMode::Nearest, RoundingScope::Entry, 15);
+
+        assert_eq!(up.round(d), Duration::minutes(12));
+        assert_eq!(down.round(d), Duration::minutes(6));
+        assert_eq!(nearest.round(d), Duration::zero());
+        assert_eq!(nearest.round(Duration::minutes(8)), Duration::minutes(15));
+        assert_eq!(up.round(Duration::minutes(12)), Duration::minutes(12));
+    }
+
+    #[test]
+    fn test_rounding_scope_entry_vs_daily() {
+        let project_id = Uuid::new_v4();
+        let mut entries = Vec::new();
+        for hour in [9, 11] {
+            let start = Utc.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap();
+            let mut entry = TimeEntry::new(project_id, "A".to_string(), None, start);
+            entry.stop(start + Duration::minutes(5)).unwrap();
+            entries.push(entry);
+        }
+        let refs: Vec<&TimeEntry> = entries.iter().collect();
+
+        let per_entry = RoundingRule::new(RoundingMode::Up, RoundingScope::Entry, 15);
+        let per_day = RoundingRule::new(RoundingMode::Up, RoundingScope::Daily, 15);
+
+        assert_eq!(per_entry.round_entries(&refs), Duration::minutes(30));
+        assert_eq!(per_day.round_entries(&refs), Duration::minutes(15));
+    }
+
+    #[test]
+    fn test_time_report_apply_rounding_with_override() {
+        let project_id = Uuid::new_v4();
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap();
+
+        let mut entry_a = TimeEntry::new(project_id, "A".to_string(), None, start);
+        entry_a.stop(start + Duration::minutes(7)).unwrap();
+        let mut entry_b = TimeEntry::new(project_id, "B".to_string(), None, start);
+        entry_b.stop(start + Duration::minutes(7)).unwrap();
+
+        let mut report = TimeReport::new(vec![entry_a, entry_b], start, start);
+        let mut overrides = std::collections::HashMap::new();
+        overrides.insert(
+            "B".to_string(),
+            RoundingRule::new(RoundingMode::Up, RoundingScope::Entry, 6),
+        );
+
+        report.apply_rounding(
+            Some(RoundingRule::new(
+                RoundingMode::Up,
+                RoundingScope::Entry,
+                15,
+            )),
+            &overrides,
+        );
+
+        assert_eq!(report.total_duration, Duration::minutes(14));
+        assert_eq!(report.rounded_total_duration, Some(Duration::minutes(27)));
+        for summary in &report.project_summaries {
+            assert_eq!(summary.total_duration, Duration::minutes(7));
+        }
+    }
+
+    #[test]
+    fn test_period_comparison_deltas() {
+        let project_id = Uuid::new_v4();
+        let day = |d: u32| Utc.with_ymd_and_hms(2024, 1, d, 9, 0, 0).unwrap();
+        let entry = |name: &str, d: u32, minutes: i64| {
+            let mut e = TimeEntry::new(project_id, name.to_string(), None, day(d));
+            e.stop(day(d) + Duration::minutes(minutes)).unwrap();
+            e
+        };
+
+        let previous = TimeReport::new(
+            vec![entry("Kept", 1, 60), entry("Dropped", 1, 30)],
+            day(1),
+            day(7),
+        );
+        let current = TimeReport::new(
+            vec![entry("Kept", 8, 90), entry("New", 8, 15)],
+            day(8),
+            day(14),
+        );
+
+        let comparison = PeriodComparison::new(ReportPeriod::Week, &current, &previous);
+
+        assert_eq!(comparison.delta_seconds, 15 * 60);
+        let find = |name: &str| {
+            comparison
+                .projects
+                .iter()
+                .find(|p| p.project_name == name)
+                .unwrap()
+        };
+        assert_eq!(find("Kept").change, ProjectChange::Increased);
+        assert_eq!(find("Kept").percent_change, Some(50.0));
+        assert_eq!(find("New").change, ProjectChange::Appeared);
+        assert_eq!(find("New").percent_change, None);
+        assert_eq!(find("Dropped").change, ProjectChange::Disappeared);
+        assert_eq!(find("Dropped").percent_change, Some(-100.0));
+        assert_eq!(comparison.projects[0].project_name, "Dropped");
+    }
+
+    #[test]
+    fn test_timesheet_grid_totals() {
+        let project_id = Uuid::new_v4();
+        let at = |d: u32, h: u32| Utc.with_ymd_and_hms(2024, 1, d, h, 0, 0).unwrap();
+        let entry = |name: &str, d: u32, minutes: i64| {
+            let mut e = TimeEntry::new(project_id, name.to_string(), None, at(d, 9));
+            e.stop(at(d, 9) + Duration::minutes(minutes)).unwrap();
+            e
+        };
+
+        // 2024-01-01 is a Monday
+        let report = TimeReport::new(
+            vec![
+                entry("Alpha", 1, 60),
+                entry("Alpha", 1, 30),
+                entry("Beta", 3, 435),
+                entry("Alpha", 7, 15),
+            ],
+            at(1, 0),
+            Utc.with_ymd_and_hms(2024, 1, 7, 23, 59, 59).unwrap(),
+        );
+
+        let sheet = Timesheet::from_report(&report);
+
+        assert_eq!(sheet.days.len(), 7);
+        assert_eq!(sheet.rows.len(), 2);
+        assert_eq!(sheet.rows[0].project_name, "Alpha");
+        assert_eq!(sheet.rows[0].daily_seconds, vec![5400, 0, 0, 0, 0, 0, 900]);
+        assert_eq!(sheet.rows[0].total_seconds, 6300);
+        assert_eq!(sheet.daily_totals[2], 435 * 60);
+        assert_eq!(sheet.total_seconds, 6300 + 435 * 60);
+
+        assert_eq!(Timesheet::format_seconds(435 * 60, true), "7.25");
+        assert_eq!(Timesheet::format_seconds(435 * 60, false), "7:15");
+    }
+
+    #[test]
+    fn test_invoice_line_item_rounding_and_amount() {
+        let item = InvoiceLineItem::new(
+            "Project A".to_string(),
+            "Project A".to_string(),
+            vec![Uuid::new_v4()],
+            Duration::minutes(62),
+            15,
+            Some(10_000),
+        );
+
+        assert_eq!(item.billed_duration, Duration::minutes(75));
+        assert_eq!(item.amount_cents, 12_500);
+
+        let unrounded = InvoiceLineItem::new(
+            "Project A".to_string(),
+            "Project A".to_string(),
+            Vec::new(),
+            Duration::minutes(62),
+            0,
+            None,
+        );
+        assert_eq!(unrounded.billed_duration, Duration::minutes(62));
+        assert_eq!(unrounded.amount_cents, 0);
+    }
+
+    #[test]
+    fn test_invoice_totals() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2024, 1, 31, 23, 59, 59).unwrap();
+        let items = vec![
+            InvoiceLineItem::new(
+                "A".to_string(),
+                "A".to_string(),
+                vec![Uuid::new_v4()],
+                Duration::minutes(50),
+                30,
+                Some(6_000),
+            ),
+            InvoiceLineItem::new(
+                "B".to_string(),
+                "B".to_string(),
+                vec![Uuid::new_v4(), Uuid::new_v4()],
+                Duration::minutes(30),
+                30,
+                Some(6_000),
+            ),
+        ];
+
+        let invoice = Invoice::new(
+            "INV-2024-0001".to_string(),
+            "Acme".to_string(),
+            DateRange { start, end },
+            InvoiceGrouping::Project,
+            30,
+            "USD".to_string(),
+            items,
+        );
+
+        assert_eq!(invoice.total_duration, Duration::minutes(80));
+        assert_eq!(invoice.total_billed_duration, Duration::minutes(90));
+        assert_eq!(invoice.total_amount_cents, 9_000);
+        assert_eq!(invoice.entry_ids().len(), 3);
+    }
+
+    #[test]
+    fn test_conventional_commit_parse() {
+        let message = "feat(api)!: accept batch uploads\n\n\
+            Uploads are now split into chunks.\n\n\
+            Second paragraph.\n\n\
+            Reviewed-by: Sam\n\
+            Refs #42\n\
+            BREAKING CHANGE: the single upload endpoint\n  is gone\n";
+
+        let commit = ConventionalCommit::parse(message).unwrap();
+        assert_eq!(commit.kind, "feat");
+        assert_eq!(commit.scope.as_deref(), Some("api"));
+        assert!(commit.breaking);
+        assert_eq!(commit.description, "accept batch uploads");
+        assert_eq!(
+            commit.body.as_deref(),
+            Some("Uploads are now split into chunks.\n\nSecond paragraph.")
+        );
+        assert_eq!(
+            commit.footers,
+            vec![
+                ("Reviewed-by".to_string(), "Sam".to_string()),
+                ("Refs".to_string(), "42".to_string()),
+                (
+                    "BREAKING CHANGE".to_string(),
+                    "the single upload endpoint\nis gone".to_string()
+                ),
+            ]
+        );
+        assert_eq!(commit.commit_type(), CommitType::Feature);
+
+        let commit = ConventionalCommit::parse("perf: cache parsed manifests").unwrap();
+        assert_eq!(commit.commit_type(), CommitType::Perf);
+        assert_eq!(commit.scope, None);
+        assert!(!commit.breaking);
+        assert!(commit.footers.is_empty());
+
+        assert!(ConventionalCommit::parse("Address review comments").is_none());
+        assert!(ConventionalCommit::parse("feat(): empty scope").is_none());
+        assert!(ConventionalCommit::parse("WIP: not done").is_some());
+    }
+
+    #[test]
+    fn test_detect_commit_type_fallback() {
+        let detect = |message: &str| {
+            GitCommit::new(
+                "abc".to_string(),
+                message.to_string(),
+                "Author".to_string(),
+                "author@example.com".to_string(),
+                Utc::now(),
+                PathBuf::from("/repo"),
+            )
+            .detect_commit_type()
+        };
+
+        assert_eq!(detect("ci(release): publish on tag"), CommitType::CI);
+        assert_eq!(detect("Revert \"feat: batch uploads\""), CommitType::Revert);
+        assert_eq!(detect("Address review comments"), CommitType::Other);
+        assert_eq!(detect("Use latest toolchain"), CommitType::Other);
+        assert_eq!(detect("Fix error when adding users"), CommitType::BugFix);
+        assert_eq!(detect("Update parser tests"), CommitType::Test);
+        assert_eq!(detect("wip: half done"), CommitType::Other);
+        // Subsystem prefixes aren't types; the subject decides
+        assert_eq!(detect("api: add endpoint"), CommitType::Feature);
+        assert_eq!(detect("net: fix crash on empty reply"), CommitType::BugFix);
+    }
+
+    #[test]
+    fn test_activity_gaps_skip_tracked_time() {
+        let project = Project::new("App".to_string(), None);
+        let base = Utc.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap();
+        let pulse = |minutes: i64| ActivityPulse {
+            id: Uuid::new_v4(),
+            project_id: project.id,
+            project_name: project.name.clone(),
+            repository_path: PathBuf::from("/code/app"),
+            sampled_at: base + Duration::minutes(minutes),
+            changed_files: 1,
+            lines_changed: 4,
+            fingerprint: minutes.to_string(),
+        };
+        // 9:00–9:40 every 5 minutes, then a lone pulse at 11:00
+        let pulses: Vec<ActivityPulse> =
+            (0..=8).map(|i| pulse(i * 5)).chain([pulse(120)]).collect();
+
+        let mut tracked = TimeEntry::new(
+            project.id,
+            project.name.clone(),
+            None,
+            base + Duration::minutes(10),
+        );
+        tracked.stop(base + Duration::minutes(25)).unwrap();
+
+        let gaps = ActivityGap::find(
+            &pulses,
+            &[tracked],
+            Duration::minutes(15),
+            Duration::minutes(5),
+        );
+        let spans: Vec<(i64, i64)> = gaps
+            .iter()
+            .map(|gap| {
+                (
+                    (gap.start - base).num_minutes(),
+                    (gap.end - base).num_minutes(),
+                )
+            })
+            .collect();
+        assert_eq!(spans, vec![(0, 10), (25, 40)]);
+        assert_eq!(gaps[0].pulse_count, 3);
+        assert_eq!(gaps[1].duration(), Duration::minutes(15));
+        assert_eq!(gaps[1].project_name, "App");
+    }
+
+    #[test]
+    fn test_commit_activity_report() {
+        let project_id = Uuid::new_v4();
+        let entry = |repo: &str, day: u32, hour: u32, message: &str, minutes: i64| {
+            let commit = GitCommit::new(
+                format!("{}-{}-{}", repo, day, hour),
+                message.to_string(),
+                "Author".to_string(),
+                "author@example.com".to_string(),
+                Utc.with_ymd_and_hms(2024, 1, day, hour, 0, 0).unwrap(),
+                PathBuf::from(repo),
+            );
+            GitTimeEntry::new(
+                &commit,
+                project_id,
+                "App".to_string(),
+                Duration::minutes(minutes),
+                0.5,
+            )
+        };
+        let entries = vec![
+            entry("/code/app", 2, 9, "feat: add login", 60),
+            entry("/code/app", 2, 15, "fix: login redirect", 30),
+            entry("/code/api", 4, 10, "feat: token endpoint", 90),
+            entry("/code/api", 4, 11, "docs: endpoints", 15),
+            // Outside the week
+            entry("/code/api", 8, 10, "feat: next week", 45),
+        ];
+
+        let week = ReportPeriod::Week
+            .range_containing(Utc.with_ymd_and_hms(2024, 1, 3, 12, 0, 0).unwrap());
+        assert_eq!(
+            week.start,
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            week.end,
+            Utc.with_ymd_and_hms(2024, 1, 7, 23, 59, 59).unwrap()
+        );
+
+        let report = CommitActivityReport::new(&entries, week);
+        assert_eq!(report.total_commits, 4);
+        assert_eq!(report.estimated_seconds, 195 * 60);
+        assert_eq!(report.average_seconds, 195 * 60 / 4);
+        assert_eq!(report.average_confidence, Some(0.5));
+        assert_eq!(report.daily.len(), 7);
+        assert_eq!(report.daily[0].commits, 0);
+
+        // Tuesday and Thursday tie; the earlier day wins
+        let busiest = report.most_active_day.unwrap();
+        assert_eq!(busiest.date, NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+        assert_eq!(busiest.commits, 2);
+
+        assert_eq!(report.repositories[0].name, "api");
+        assert_eq!(report.repositories[0].estimated_seconds, 105 * 60);
+        assert_eq!(report.repositories[1].name, "app");
+
+        assert_eq!(report.commit_types[0].commit_type, CommitType::Feature);
+        assert_eq!(report.commit_types[0].commits, 2);
+        assert_eq!(report.commit_types.len(), 3);
+    }
 }
 
 // Git Integration Models
@@ -427,17 +1666,43 @@ pub struct GitCommit {
     pub insertions: u32,
     pub deletions: u32,
     pub repository_path: PathBuf,
+    /// More than one parent; merges carry no file changes of their own
+    #[serde(default)]
+    pub is_merge: bool,
+}
+
+/// Where the time attributed to a commit comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeSource {
+    /// Time entries linked to the commit, or calibrated actual time
+    Tracked,
+    Estimated,
+}
+
+impl TimeSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimeSource::Tracked => "tracked",
+            TimeSource::Estimated => "estimated",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GitTimeEntry {
     pub id: Uuid,
     pub commit_hash: String,
+    pub repository_path: PathBuf,
+    pub committed_at: DateTime<Utc>,
     pub project_id: Uuid,
     pub project_name: String,
+    /// Time entry created for this commit when it was imported
+    pub time_entry_id: Option<Uuid>,
     pub estimated_time: Duration,
     pub actual_time: Option<Duration>,
     pub confidence_score: f32,
+    pub commit_type: CommitType,
     pub created_at: DateTime<Utc>,
 }
 
@@ -447,10 +1712,13 @@ pub struct CommitAnalysis {
     pub complexity_score: f32,
     pub file_type_weights: std::collections::HashMap<String, f32>,
     pub commit_type: CommitType,
+    /// Conventional Commits scope, if the message has one
+    pub scope: Option<String>,
     pub estimated_duration: Duration,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum CommitType {
     Feature,
     BugFix,
@@ -458,9 +1726,130 @@ pub enum CommitType {
     Documentation,
     Test,
     Chore,
+    Perf,
+    CI,
+    Build,
+    Style,
+    Revert,
     Other,
 }
 
+/// A commit message in Conventional Commits form:
+/// `type(scope)!: description`, then an optional body and footers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConventionalCommit {
+    /// Type as written, lowercased (e.g. `feat`, `fix`)
+    pub kind: String,
+    pub scope: Option<String>,
+    /// Set by a `!` before the colon or a `BREAKING CHANGE` footer
+    pub breaking: bool,
+    pub description: String,
+    pub body: Option<String>,
+    /// `(token, value)` pairs in message order
+    pub footers: Vec<(String, String)>,
+}
+
+/// Per-repository multipliers fitted by comparing commit estimates with
+/// tracked time. Keys are `CommitType::as_str()` values and lowercase file
+/// extensions; anything missing uses 1.0.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GitCalibration {
+    pub repository_path: PathBuf,
+    pub commit_type_multipliers: std::collections::HashMap<String, f32>,
+    pub file_type_multipliers: std::collections::HashMap<String, f32>,
+    pub sample_count: usize,
+    /// Mean absolute estimate error over the samples before calibrating
+    pub mean_error_before: Duration,
+    /// Mean absolute estimate error over the samples with these multipliers
+    pub mean_error_after: Duration,
+    pub calibrated_at: DateTime<Utc>,
+}
+
+/// How time is estimated for imported commits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EstimationStrategy {
+    /// Per-commit guess from commit type, size and file types
+    Heuristic,
+    /// Time between an author's commits within the same work session
+    Session,
+}
+
+/// What to do with merge commits when reading history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergePolicy {
+    /// Leave merges out entirely
+    Skip,
+    /// Keep merges but estimate no time for them
+    Zero,
+}
+
+/// Where a branch's ticket id goes when the timer follows the branch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TicketField {
+    /// Replace the task description
+    Task,
+    /// Replace the previous branch's ticket tag
+    Tag,
+}
+
+/// A time entry together with the commits linked to it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntryDetails {
+    pub entry: TimeEntry,
+    /// Linked commits that could still be read from the repository
+    pub commits: Vec<GitCommit>,
+    /// Linked hashes that are no longer in the repository
+    pub missing_commits: Vec<String>,
+}
+
+/// Where a ticket or issue reference was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferenceSource {
+    /// The time entry's task description
+    Task,
+    /// The message of the commit the entry was imported from
+    Commit,
+}
+
+/// A ticket or issue reference such as `#123` or `ACME-45` attached to a
+/// time entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryReference {
+    pub time_entry_id: Uuid,
+    pub reference: String,
+    pub source: ReferenceSource,
+}
+
+/// Time tracked against one reference. An entry that mentions several
+/// references counts in full towards each of them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TicketSummary {
+    pub reference: String,
+    pub total_seconds: i64,
+    pub entry_count: usize,
+    /// Entries imported from commits that mention the reference
+    pub commit_count: usize,
+    pub projects: Vec<String>,
+    pub first_tracked: DateTime<Utc>,
+    pub last_tracked: DateTime<Utc>,
+}
+
+/// Time tracked on one ticket (or on a branch without a ticket id)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchTimeSummary {
+    /// The ticket id, else the branch name, else `(no branch)`
+    pub key: String,
+    pub ticket: Option<String>,
+    pub branches: Vec<String>,
+    pub projects: Vec<String>,
+    pub entry_count: usize,
+    pub total_seconds: i64,
+}
+
 impl GitCommit {
     pub fn new(
         hash: String,
@@ -480,6 +1869,7 @@ impl GitCommit {
             insertions: 0,
             deletions: 0,
             repository_path,
+            is_merge: false,
         }
     }
 
@@ -487,30 +1877,143 @@ impl GitCommit {
         self.insertions + self.deletions
     }
 
+    /// Classify the commit from its Conventional Commits header, falling
+    /// back to keywords for messages that don't follow the format
     pub fn detect_commit_type(&self) -> CommitType {
-        let msg = self.message.to_lowercase();
-
-        if msg.starts_with("feat") || msg.contains("feature") || msg.contains("add") {
-            CommitType::Feature
-        } else if msg.starts_with("fix") || msg.contains("bug") || msg.contains("error") {
-            CommitType::BugFix
-        } else if msg.starts_with("refactor") || msg.contains("refactor") {
-            CommitType::Refactor
-        } else if msg.starts_with("docs") || msg.contains("documentation") {
-            CommitType::Documentation
-        } else if msg.starts_with("test") || msg.contains("test") {
-            CommitType::Test
-        } else if msg.starts_with("chore") || msg.contains("chore") {
-            CommitType::Chore
-        } else {
-            CommitType::Other
+        match ConventionalCommit::parse(&self.message) {
+            Some(conventional) => conventional.commit_type(),
+            None => CommitType::from_keywords(&self.message),
+        }
+    }
+
+    /// Conventional Commits scope, e.g. `api` in `fix(api): ...`
+    pub fn scope(&self) -> Option<String> {
+        ConventionalCommit::parse(&self.message).and_then(|conventional| conventional.scope)
+    }
+}
+
+impl ConventionalCommit {
+    /// Parse a full commit message; `None` if the header doesn't follow the
+    /// Conventional Commits format
+    pub fn parse(message: &str) -> Option<Self> {
+        let message = message.trim();
+        let (header, rest) = message.split_once('\n').unwrap_or((message, ""));
+        let (prefix, description) = header.trim_end().split_once(": ")?;
+        let description = description.trim();
+        if description.is_empty() {
+            return None;
+        }
+
+        let (prefix, mut breaking) = match prefix.strip_suffix('!') {
+            Some(prefix) => (prefix, true),
+            None => (prefix, false),
+        };
+
+        let (kind, scope) = match prefix.split_once('(') {
+            Some((kind, scope)) => {
+                let scope = scope.strip_suffix(')')?.trim();
+                if scope.is_empty() || scope.contains(['(', ')']) {
+                    return None;
+                }
+                (kind, Some(scope.to_string()))
+            }
+            None => (prefix, None),
+        };
+
+        if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return None;
+        }
+
+        let mut paragraphs: Vec<String> = Vec::new();
+        let mut current: Vec<&str> = Vec::new();
+        for line in rest.lines() {
+            if line.trim().is_empty() {
+                if !current.is_empty() {
+                    paragraphs.push(current.join("\n"));
+                    current.clear();
+                }
+            } else {
+                current.push(line.trim_end());
+            }
+        }
+        if !current.is_empty() {
+            paragraphs.push(current.join("\n"));
+        }
+
+        // Footers are the last paragraph when it starts with a footer token
+        let mut footers: Vec<(String, String)> = Vec::new();
+        if let Some(last) = paragraphs.last() {
+            let starts_with_footer = last.lines().next().and_then(footer_line).is_some();
+            if starts_with_footer {
+                for line in last.lines() {
+                    match footer_line(line) {
+                        Some(footer) => footers.push(footer),
+                        None => {
+                            if let Some((_, value)) = footers.last_mut() {
+                                value.push('\n');
+                                value.push_str(line.trim());
+                            }
+                        }
+                    }
+                }
+                paragraphs.pop();
+            }
+        }
+
+        breaking |= footers
+            .iter()
+            .any(|(token, _)| token == "BREAKING CHANGE" || token == "BREAKING-CHANGE");
+
+        let body = (!paragraphs.is_empty()).then(|| paragraphs.join("\n\n"));
+
+        Some(Self {
+            kind: kind.to_lowercase(),
+            scope,
+            breaking,
+            description: description.to_string(),
+            body,
+            footers,
+        })
+    }
+
+    /// Map the type onto `CommitType`. Unrecognised types, such as the
+    /// subsystem in `api: add endpoint`, are classified by the description's
+    /// keywords instead.
+    pub fn commit_type(&self) -> CommitType {
+        match self.kind.as_str() {
+            "feat" | "feature" => CommitType::Feature,
+            "fix" | "bugfix" | "hotfix" => CommitType::BugFix,
+            "refactor" => CommitType::Refactor,
+            "docs" | "doc" => CommitType::Documentation,
+            "test" | "tests" => CommitType::Test,
+            "chore" => CommitType::Chore,
+            "perf" => CommitType::Perf,
+            "ci" => CommitType::CI,
+            "build" => CommitType::Build,
+            "style" => CommitType::Style,
+            "revert" => CommitType::Revert,
+            _ => CommitType::from_keywords(&self.description),
         }
     }
 }
 
+/// `Token: value` or `Token #value`, where the token is a single word or
+/// `BREAKING CHANGE`
+fn footer_line(line: &str) -> Option<(String, String)> {
+    if let Some(value) = line.strip_prefix("BREAKING CHANGE: ") {
+        return Some(("BREAKING CHANGE".to_string(), value.trim().to_string()));
+    }
+
+    let (token, value) = line.split_once(": ").or_else(|| line.split_once(" #"))?;
+    if token.is_empty() || !token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return None;
+    }
+    Some((token.to_string(), value.trim().to_string()))
+}
+
 impl GitTimeEntry {
     pub fn new(
-        commit_hash: String,
+        commit: &GitCommit,
         project_id: Uuid,
         project_name: String,
         estimated_time: Duration,
@@ -518,12 +2021,16 @@ impl GitTimeEntry {
     ) -> Self {
         Self {
             id: Uuid::new_v4(),
-            commit_hash,
+            commit_hash: commit.hash.clone(),
+            repository_path: commit.repository_path.clone(),
+            committed_at: commit.timestamp,
             project_id,
             project_name,
+            time_entry_id: None,
             estimated_time,
             actual_time: None,
             confidence_score,
+            commit_type: commit.detect_commit_type(),
             created_at: Utc::now(),
         }
     }
@@ -532,3 +2039,366 @@ impl GitTimeEntry {
         self.actual_time = Some(actual_time);
     }
 }
+
+impl CommitType {
+    pub const ALL: [CommitType; 12] = [
+        CommitType::Feature,
+        CommitType::BugFix,
+        CommitType::Refactor,
+        CommitType::Documentation,
+        CommitType::Test,
+        CommitType::Chore,
+        CommitType::Perf,
+        CommitType::CI,
+        CommitType::Build,
+        CommitType::Style,
+        CommitType::Revert,
+        CommitType::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommitType::Feature => "feature",
+            CommitType::BugFix => "bugfix",
+            CommitType::Refactor => "refactor",
+            CommitType::Documentation => "documentation",
+            CommitType::Test => "test",
+            CommitType::Chore => "chore",
+            CommitType::Perf => "perf",
+            CommitType::CI => "ci",
+            CommitType::Build => "build",
+            CommitType::Style => "style",
+            CommitType::Revert => "revert",
+            CommitType::Other => "other",
+        }
+    }
+
+    /// Keyword guess for messages that aren't Conventional Commits. Matches
+    /// whole words only, and the first word (usually the imperative verb)
+    /// wins over words later in the subject.
+    pub fn from_keywords(message: &str) -> Self {
+        let subject = message.lines().next().unwrap_or("").to_lowercase();
+        let words: Vec<&str> = subject
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        let keyword_type = |word: &str| {
+            let commit_type = match word {
+                "revert" | "reverts" | "reverted" => CommitType::Revert,
+                "feat" | "feature" | "add" | "adds" | "added" | "adding" | "implement"
+                | "implements" | "implemented" | "introduce" | "introduces" => CommitType::Feature,
+                "fix" | "fixes" | "fixed" | "fixing" | "bug" | "bugfix" | "hotfix" | "error"
+                | "crash" => CommitType::BugFix,
+                "refactor" | "refactors" | "refactored" | "refactoring" | "cleanup"
+                | "restructure" => CommitType::Refactor,
+                "docs" | "doc" | "documentation" | "readme" => CommitType::Documentation,
+                "test" | "tests" | "testing" => CommitType::Test,
+                "perf" | "performance" | "optimize" | "optimise" | "speed" => CommitType::Perf,
+                "ci" | "pipeline" | "workflow" => CommitType::CI,
+                "build" | "deps" | "dependency" | "dependencies" | "bump" => CommitType::Build,
+                "style" | "format" | "formatting" | "lint" | "whitespace" => CommitType::Style,
+                "chore" => CommitType::Chore,
+                _ => return None,
+            };
+            Some(commit_type)
+        };
+
+        if let Some(commit_type) = words.first().and_then(|word| keyword_type(word)) {
+            return commit_type;
+        }
+
+        // Otherwise use the highest-priority keyword anywhere in the subject
+        Self::ALL
+            .into_iter()
+            .find(|commit_type| {
+                words
+                    .iter()
+                    .any(|word| keyword_type(word).as_ref() == Some(commit_type))
+            })
+            .unwrap_or(CommitType::Other)
+    }
+}
+
+impl std::str::FromStr for CommitType {
+    type Err = crate::TimeSpanError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "feature" => Ok(CommitType::Feature),
+            "bugfix" => Ok(CommitType::BugFix),
+            "refactor" => Ok(CommitType::Refactor),
+            "documentation" => Ok(CommitType::Documentation),
+            "test" => Ok(CommitType::Test),
+            "chore" => Ok(CommitType::Chore),
+            "perf" => Ok(CommitType::Perf),
+            "ci" => Ok(CommitType::CI),
+            "build" => Ok(CommitType::Build),
+            "style" => Ok(CommitType::Style),
+            "revert" => Ok(CommitType::Revert),
+            "other" => Ok(CommitType::Other),
+            other => Err(crate::TimeSpanError::InvalidInput(format!(
+                "Unknown commit type: {}",
+                other
+            ))),
+        }
+    }
+}
+
+impl GitCalibration {
+    pub fn commit_type_multiplier(&self, commit_type: &CommitType) -> f32 {
+        self.commit_type_multipliers
+            .get(commit_type.as_str())
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    /// Average multiplier over the extensions of `files`
+    pub fn file_type_factor(&self, files: &[String]) -> f32 {
+        if files.is_empty() || self.file_type_multipliers.is_empty() {
+            return 1.0;
+        }
+
+        let total: f32 = files
+            .iter()
+            .map(|file| {
+                file_extension(file)
+                    .and_then(|ext| self.file_type_multipliers.get(&ext))
+                    .copied()
+                    .unwrap_or(1.0)
+            })
+            .sum();
+        total / files.len() as f32
+    }
+}
+
+/// A duration as whole hours and minutes, e.g. `2h 5m`
+pub fn format_hours_minutes(duration: Duration) -> String {
+    format!("{}h {}m", duration.num_hours(), duration.num_minutes() % 60)
+}
+
+/// Lowercase extension of a path, if it has one
+pub fn file_extension(file: &str) -> Option<String> {
+    std::path::Path::new(file)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+}
+
+impl EstimationStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EstimationStrategy::Heuristic => "heuristic",
+            EstimationStrategy::Session => "session",
+        }
+    }
+}
+
+impl MergePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MergePolicy::Skip => "skip",
+            MergePolicy::Zero => "zero",
+        }
+    }
+}
+
+impl std::str::FromStr for MergePolicy {
+    type Err = crate::TimeSpanError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(MergePolicy::Skip),
+            "zero" => Ok(MergePolicy::Zero),
+            other => Err(crate::TimeSpanError::InvalidInput(format!(
+                "Unknown merge policy: {} (expected skip or zero)",
+                other
+            ))),
+        }
+    }
+}
+
+impl TicketField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TicketField::Task => "task",
+            TicketField::Tag => "tag",
+        }
+    }
+}
+
+impl std::str::FromStr for TicketField {
+    type Err = crate::TimeSpanError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "task" => Ok(TicketField::Task),
+            "tag" => Ok(TicketField::Tag),
+            other => Err(crate::TimeSpanError::InvalidInput(format!(
+                "Unknown ticket field: {} (expected task or tag)",
+                other
+            ))),
+        }
+    }
+}
+
+impl ReferenceSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReferenceSource::Task => "task",
+            ReferenceSource::Commit => "commit",
+        }
+    }
+}
+
+impl std::str::FromStr for ReferenceSource {
+    type Err = crate::TimeSpanError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "task" => Ok(ReferenceSource::Task),
+            "commit" => Ok(ReferenceSource::Commit),
+            other => Err(crate::TimeSpanError::InvalidInput(format!(
+                "Unknown reference source: {}",
+                other
+            ))),
+        }
+    }
+}
+
+impl std::str::FromStr for EstimationStrategy {
+    type Err = crate::TimeSpanError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "heuristic" => Ok(EstimationStrategy::Heuristic),
+            "session" | "sessions" => Ok(EstimationStrategy::Session),
+            other => Err(crate::TimeSpanError::InvalidInput(format!(
+                "Unknown estimation strategy: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// A working-tree sample that differed from the previous one, i.e. evidence
+/// that someone was editing files in the repository around `sampled_at`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivityPulse {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub project_name: String,
+    pub repository_path: PathBuf,
+    pub sampled_at: DateTime<Utc>,
+    /// Modified, added, deleted and untracked files
+    pub changed_files: u32,
+    /// Inserted plus deleted lines against HEAD
+    pub lines_changed: u32,
+    /// Identifies the working-tree state, so unchanged samples are skipped
+    pub fingerprint: String,
+}
+
+/// A stretch of working-tree activity that no time entry covers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivityGap {
+    pub project_id: Uuid,
+    pub project_name: String,
+    pub repository_path: PathBuf,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub duration_seconds: i64,
+    pub pulse_count: usize,
+}
+
+impl ActivityGap {
+    /// Group each repository's pulses into stretches (pulses at most
+    /// `max_gap` apart), cut out the time covered by `entries` and keep the
+    /// pieces lasting at least `min_length`. Any entry counts as covering,
+    /// whichever project it was tracked on.
+    pub fn find(
+        pulses: &[ActivityPulse],
+        entries: &[TimeEntry],
+        max_gap: Duration,
+        min_length: Duration,
+    ) -> Vec<ActivityGap> {
+        let mut covered: Vec<(DateTime<Utc>, DateTime<Utc>)> = entries
+            .iter()
+            .map(|entry| (entry.start_time, entry.end_time.unwrap_or_else(Utc::now)))
+            .collect();
+        covered.sort();
+
+        let mut by_repository: std::collections::BTreeMap<&Path, Vec<&ActivityPulse>> =
+            std::collections::BTreeMap::new();
+        for pulse in pulses {
+            by_repository
+                .entry(pulse.repository_path.as_path())
+                .or_default()
+                .push(pulse);
+        }
+
+        let mut gaps = Vec::new();
+        for mut repository_pulses in by_repository.into_values() {
+            repository_pulses.sort_by_key(|pulse| pulse.sampled_at);
+
+            let mut stretches: Vec<Vec<&ActivityPulse>> = Vec::new();
+            for pulse in repository_pulses {
+                match stretches.last_mut() {
+                    Some(stretch)
+                        if pulse.sampled_at - stretch.last().unwrap().sampled_at <= max_gap =>
+                    {
+                        stretch.push(pulse)
+                    }
+                    _ => stretches.push(vec![pulse]),
+                }
+            }
+
+            for stretch in stretches {
+                let first = stretch[0];
+                let mut pieces = vec![(first.sampled_at, stretch.last().unwrap().sampled_at)];
+                for &(covered_start, covered_end) in &covered {
+                    pieces = pieces
+                        .into_iter()
+                        .flat_map(|(start, end)| {
+                            if covered_end <= start || covered_start >= end {
+                                return vec![(start, end)];
+                            }
+                            let mut rest = Vec::new();
+                            if covered_start > start {
+                                rest.push((start, covered_start));
+                            }
+                            if covered_end < end {
+                                rest.push((covered_end, end));
+                            }
+                            rest
+                        })
+                        .collect();
+                }
+
+                for (start, end) in pieces {
+                    if end - start < min_length {
+                        continue;
+                    }
+                    gaps.push(ActivityGap {
+                        project_id: first.project_id,
+                        project_name: first.project_name.clone(),
+                        repository_path: first.repository_path.clone(),
+                        start,
+                        end,
+                        duration_seconds: (end - start).num_seconds(),
+                        pulse_count: stretch
+                            .iter()
+                            .filter(|pulse| pulse.sampled_at >= start && pulse.sampled_at <= end)
+                            .count(),
+                    });
+                }
+            }
+        }
+
+        gaps.sort_by_key(|gap| gap.start);
+        gaps
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::seconds(self.duration_seconds)
+    }
+}
@@ -4,7 +4,10 @@ use rusqlite::{params, Connection, Row};
 use std::path::Path;
 use uuid::Uuid;
 
-use crate::models::{Project, TimeEntry, Timer};
+use crate::models::{
+    ActivityPulse, CommitType, EntryReference, GitCalibration, GitTimeEntry, Invoice,
+    InvoiceGrouping, Project, ReferenceSource, TimeEntry, Timer,
+};
 use crate::{Result, TimeSpanError};
 
 #[async_trait]
@@ -18,6 +21,8 @@ pub trait Repository: Send + Sync {
 
     async fn create_time_entry(&self, entry: &TimeEntry) -> Result<()>;
     async fn get_time_entry_by_id(&self, id: Uuid) -> Result<Option<TimeEntry>>;
+    /// Entries whose id starts with `prefix`, newest first
+    async fn find_time_entries_by_id_prefix(&self, prefix: &str) -> Result<Vec<TimeEntry>>;
     async fn get_active_time_entry(&self) -> Result<Option<TimeEntry>>;
     async fn list_time_entries_by_project(&self, project_id: Uuid) -> Result<Vec<TimeEntry>>;
     async fn list_time_entries_by_date_range(
@@ -32,6 +37,61 @@ pub trait Repository: Send + Sync {
     async fn get_active_timer(&self) -> Result<Option<Timer>>;
     async fn clear_active_timer(&self) -> Result<()>;
 
+    /// Persist an invoice and lock every time entry it bills
+    async fn create_invoice(&self, invoice: &Invoice) -> Result<()>;
+    async fn get_invoice_by_number(&self, number: &str) -> Result<Option<Invoice>>;
+    async fn list_invoices(&self) -> Result<Vec<Invoice>>;
+    /// Invoices whose number starts with `prefix`, e.g. `INV-2024-`
+    async fn count_invoices_numbered(&self, prefix: &str) -> Result<usize>;
+
+    /// Persist a time entry together with the commit it was estimated from.
+    /// Returns `false` without writing anything when the commit was already
+    /// imported.
+    async fn import_git_time_entry(
+        &self,
+        entry: &TimeEntry,
+        git_entry: &GitTimeEntry,
+    ) -> Result<bool>;
+    async fn get_git_time_entry_by_commit(&self, commit_hash: &str)
+        -> Result<Option<GitTimeEntry>>;
+    async fn list_git_time_entries(&self) -> Result<Vec<GitTimeEntry>>;
+    async fn set_git_actual_time(
+        &self,
+        commit_hash: &str,
+        actual_time: chrono::Duration,
+    ) -> Result<()>;
+
+    /// Attach ticket references to time entries; references an entry already
+    /// has are left alone
+    async fn save_entry_references(&self, references: &[EntryReference]) -> Result<()>;
+    /// Swap the entry's references from `source` for `references`
+    async fn replace_entry_references(
+        &self,
+        time_entry_id: Uuid,
+        source: ReferenceSource,
+        references: &[EntryReference],
+    ) -> Result<()>;
+    /// References of the entries that started within the range
+    async fn list_entry_references(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<EntryReference>>;
+
+    async fn save_activity_pulse(&self, pulse: &ActivityPulse) -> Result<()>;
+    /// The most recent pulse recorded for a repository
+    async fn latest_activity_pulse(&self, repository_path: &Path) -> Result<Option<ActivityPulse>>;
+    /// Pulses sampled within the range, oldest first
+    async fn list_activity_pulses(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<ActivityPulse>>;
+
+    /// Insert or replace the calibration for a repository
+    async fn save_git_calibration(&self, calibration: &GitCalibration) -> Result<()>;
+    async fn get_git_calibration(&self, repository_path: &Path) -> Result<Option<GitCalibration>>;
+
     // Test helper methods
     async fn clear_all(&self) -> Result<()>;
 }
@@ -83,6 +143,11 @@ impl SqliteRepository {
                 description TEXT,
                 directory_path TEXT,
                 is_client_project BOOLEAN DEFAULT FALSE,
+                hourly_rate_cents INTEGER,
+                billable BOOLEAN NOT NULL DEFAULT TRUE,
+                rounding_rule TEXT, -- JSON object
+                git_remote_url TEXT,
+                archived_at TEXT,
                 created_at TEXT NOT NULL,
                 updated_at TEXT NOT NULL
             )
@@ -101,6 +166,9 @@ impl SqliteRepository {
                 end_time TEXT,
                 duration_seconds INTEGER,
                 tags TEXT, -- JSON array
+                invoice_id TEXT,
+                branch TEXT,
+                commit_hashes TEXT, -- JSON array
                 created_at TEXT NOT NULL,
                 updated_at TEXT NOT NULL,
                 FOREIGN KEY (project_id) REFERENCES projects (id)
@@ -117,17 +185,122 @@ impl SqliteRepository {
                 project_name TEXT NOT NULL,
                 task_description TEXT,
                 start_time TEXT NOT NULL,
-                tags TEXT -- JSON array
+                tags TEXT, -- JSON array
+                branch TEXT,
+                repository TEXT
+            )
+            "#,
+            [],
+        )?;
+
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS invoices (
+                id TEXT PRIMARY KEY,
+                number TEXT UNIQUE NOT NULL,
+                client TEXT NOT NULL,
+                period_start TEXT NOT NULL,
+                period_end TEXT NOT NULL,
+                grouping TEXT NOT NULL,
+                rounding_increment_minutes INTEGER NOT NULL,
+                currency TEXT NOT NULL,
+                line_items TEXT NOT NULL, -- JSON array
+                total_duration_seconds INTEGER NOT NULL,
+                total_billed_seconds INTEGER NOT NULL,
+                total_amount_cents INTEGER NOT NULL,
+                created_at TEXT NOT NULL
+            )
+            "#,
+            [],
+        )?;
+
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS git_time_entries (
+                id TEXT PRIMARY KEY,
+                commit_hash TEXT UNIQUE NOT NULL,
+                repository_path TEXT NOT NULL,
+                committed_at TEXT NOT NULL,
+                project_id TEXT NOT NULL,
+                project_name TEXT NOT NULL,
+                time_entry_id TEXT,
+                estimated_seconds INTEGER NOT NULL,
+                actual_seconds INTEGER,
+                confidence_score REAL NOT NULL,
+                commit_type TEXT,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (project_id) REFERENCES projects (id),
+                FOREIGN KEY (time_entry_id) REFERENCES time_entries (id)
+            )
+            "#,
+            [],
+        )?;
+
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS git_calibrations (
+                repository_path TEXT PRIMARY KEY,
+                commit_type_multipliers TEXT NOT NULL, -- JSON object
+                file_type_multipliers TEXT NOT NULL, -- JSON object
+                sample_count INTEGER NOT NULL,
+                mean_error_before_seconds INTEGER NOT NULL,
+                mean_error_after_seconds INTEGER NOT NULL,
+                calibrated_at TEXT NOT NULL
+            )
+            "#,
+            [],
+        )?;
+
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS entry_references (
+                time_entry_id TEXT NOT NULL,
+                reference TEXT NOT NULL,
+                source TEXT NOT NULL,
+                PRIMARY KEY (time_entry_id, reference),
+                FOREIGN KEY (time_entry_id) REFERENCES time_entries (id)
+            )
+            "#,
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_entry_references_reference ON entry_references (reference)",
+            [],
+        )?;
+
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS activity_pulses (
+                id TEXT PRIMARY KEY,
+                project_id TEXT NOT NULL,
+                project_name TEXT NOT NULL,
+                repository_path TEXT NOT NULL,
+                sampled_at TEXT NOT NULL,
+                changed_files INTEGER NOT NULL,
+                lines_changed INTEGER NOT NULL,
+                fingerprint TEXT NOT NULL,
+                FOREIGN KEY (project_id) REFERENCES projects (id)
             )
             "#,
             [],
         )?;
 
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_activity_pulses_repository ON activity_pulses (repository_path, sampled_at)",
+            [],
+        )?;
+
         self.migrate_database_schema(&conn)?;
 
         Ok(())
     }
 
+    fn has_column(conn: &Connection, table: &str, column: &str) -> bool {
+        conn.prepare(&format!("SELECT {} FROM {} LIMIT 1", column, table))
+            .is_ok()
+    }
+
     fn migrate_database_schema(&self, conn: &Connection) -> Result<()> {
         // Check if we need to add the new columns to existing projects table
         let has_directory_path = conn
@@ -149,10 +322,65 @@ impl SqliteRepository {
             )?;
         }
 
+        if !Self::has_column(conn, "projects", "hourly_rate_cents") {
+            conn.execute(
+                "ALTER TABLE projects ADD COLUMN hourly_rate_cents INTEGER",
+                [],
+            )?;
+        }
+
+        if !Self::has_column(conn, "projects", "rounding_rule") {
+            conn.execute("ALTER TABLE projects ADD COLUMN rounding_rule TEXT", [])?;
+        }
+
+        if !Self::has_column(conn, "projects", "billable") {
+            conn.execute(
+                "ALTER TABLE projects ADD COLUMN billable BOOLEAN NOT NULL DEFAULT TRUE",
+                [],
+            )?;
+        }
+
+        if !Self::has_column(conn, "projects", "git_remote_url") {
+            conn.execute("ALTER TABLE projects ADD COLUMN git_remote_url TEXT", [])?;
+        }
+
+        if !Self::has_column(conn, "projects", "archived_at") {
+            conn.execute("ALTER TABLE projects ADD COLUMN archived_at TEXT", [])?;
+        }
+
+        if !Self::has_column(conn, "time_entries", "invoice_id") {
+            conn.execute("ALTER TABLE time_entries ADD COLUMN invoice_id TEXT", [])?;
+        }
+
+        if !Self::has_column(conn, "time_entries", "branch") {
+            conn.execute("ALTER TABLE time_entries ADD COLUMN branch TEXT", [])?;
+        }
+
+        if !Self::has_column(conn, "time_entries", "commit_hashes") {
+            conn.execute("ALTER TABLE time_entries ADD COLUMN commit_hashes TEXT", [])?;
+        }
+
+        if !Self::has_column(conn, "active_timer", "branch") {
+            conn.execute("ALTER TABLE active_timer ADD COLUMN branch TEXT", [])?;
+        }
+
+        if !Self::has_column(conn, "active_timer", "repository") {
+            conn.execute("ALTER TABLE active_timer ADD COLUMN repository TEXT", [])?;
+        }
+
+        if !Self::has_column(conn, "git_time_entries", "commit_type") {
+            conn.execute(
+                "ALTER TABLE git_time_entries ADD COLUMN commit_type TEXT",
+                [],
+            )?;
+        }
+
         Ok(())
     }
 
     fn project_from_row(row: &Row) -> rusqlite::Result<Project> {
+        let rounding_rule: Option<String> = row.get("rounding_rule")?;
+
         Ok(Project {
             id: Uuid::parse_str(&row.get::<_, String>("id")?).unwrap(),
             name: row.get("name")?,
@@ -161,6 +389,14 @@ impl SqliteRepository {
             is_client_project: row
                 .get::<_, Option<bool>>("is_client_project")?
                 .unwrap_or(false),
+            hourly_rate_cents: row.get("hourly_rate_cents")?,
+            billable: row.get::<_, Option<bool>>("billable")?.unwrap_or(true),
+            rounding_rule: rounding_rule.and_then(|json| serde_json::from_str(&json).ok()),
+            git_remote_url: row.get("git_remote_url")?,
+            archived_at: row
+                .get::<_, Option<String>>("archived_at")?
+                .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+                .map(|dt| dt.with_timezone(&Utc)),
             created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>("created_at")?)
                 .unwrap()
                 .with_timezone(&Utc),
@@ -188,6 +424,9 @@ impl SqliteRepository {
         let duration_seconds: Option<i64> = row.get("duration_seconds")?;
         let duration = duration_seconds.map(chrono::Duration::seconds);
 
+        let invoice_id: Option<String> = row.get("invoice_id")?;
+        let commit_hashes_json: Option<String> = row.get("commit_hashes")?;
+
         Ok(TimeEntry {
             id: Uuid::parse_str(&row.get::<_, String>("id")?).unwrap(),
             project_id: Uuid::parse_str(&row.get::<_, String>("project_id")?).unwrap(),
@@ -199,6 +438,11 @@ impl SqliteRepository {
             end_time,
             duration,
             tags,
+            invoice_id: invoice_id.map(|id| Uuid::parse_str(&id).unwrap()),
+            branch: row.get("branch")?,
+            commit_hashes: commit_hashes_json
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default(),
             created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>("created_at")?)
                 .unwrap()
                 .with_timezone(&Utc),
@@ -225,6 +469,98 @@ impl SqliteRepository {
                 .unwrap()
                 .with_timezone(&Utc),
             tags,
+            branch: row.get("branch")?,
+            repository: row.get("repository")?,
+        })
+    }
+
+    fn git_time_entry_from_row(row: &Row) -> rusqlite::Result<GitTimeEntry> {
+        let time_entry_id: Option<String> = row.get("time_entry_id")?;
+        let actual_seconds: Option<i64> = row.get("actual_seconds")?;
+        let commit_type: Option<String> = row.get("commit_type")?;
+
+        Ok(GitTimeEntry {
+            id: Uuid::parse_str(&row.get::<_, String>("id")?).unwrap(),
+            commit_hash: row.get("commit_hash")?,
+            repository_path: std::path::PathBuf::from(row.get::<_, String>("repository_path")?),
+            committed_at: DateTime::parse_from_rfc3339(&row.get::<_, String>("committed_at")?)
+                .unwrap()
+                .with_timezone(&Utc),
+            project_id: Uuid::parse_str(&row.get::<_, String>("project_id")?).unwrap(),
+            project_name: row.get("project_name")?,
+            time_entry_id: time_entry_id.map(|id| Uuid::parse_str(&id).unwrap()),
+            estimated_time: chrono::Duration::seconds(row.get("estimated_seconds")?),
+            actual_time: actual_seconds.map(chrono::Duration::seconds),
+            confidence_score: row.get::<_, f64>("confidence_score")? as f32,
+            // Entries imported before commit types were stored
+            commit_type: commit_type
+                .and_then(|commit_type| commit_type.parse().ok())
+                .unwrap_or(CommitType::Other),
+            created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>("created_at")?)
+                .unwrap()
+                .with_timezone(&Utc),
+        })
+    }
+
+    fn activity_pulse_from_row(row: &Row) -> rusqlite::Result<ActivityPulse> {
+        Ok(ActivityPulse {
+            id: Uuid::parse_str(&row.get::<_, String>("id")?).unwrap(),
+            project_id: Uuid::parse_str(&row.get::<_, String>("project_id")?).unwrap(),
+            project_name: row.get("project_name")?,
+            repository_path: std::path::PathBuf::from(row.get::<_, String>("repository_path")?),
+            sampled_at: DateTime::parse_from_rfc3339(&row.get::<_, String>("sampled_at")?)
+                .unwrap()
+                .with_timezone(&Utc),
+            changed_files: row.get("changed_files")?,
+            lines_changed: row.get("lines_changed")?,
+            fingerprint: row.get("fingerprint")?,
+        })
+    }
+
+    fn git_calibration_from_row(row: &Row) -> rusqlite::Result<GitCalibration> {
+        let commit_type_multipliers: String = row.get("commit_type_multipliers")?;
+        let file_type_multipliers: String = row.get("file_type_multipliers")?;
+
+        Ok(GitCalibration {
+            repository_path: std::path::PathBuf::from(row.get::<_, String>("repository_path")?),
+            commit_type_multipliers: serde_json::from_str(&commit_type_multipliers)
+                .unwrap_or_default(),
+            file_type_multipliers: serde_json::from_str(&file_type_multipliers).unwrap_or_default(),
+            sample_count: row.get::<_, i64>("sample_count")? as usize,
+            mean_error_before: chrono::Duration::seconds(row.get("mean_error_before_seconds")?),
+            mean_error_after: chrono::Duration::seconds(row.get("mean_error_after_seconds")?),
+            calibrated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>("calibrated_at")?)
+                .unwrap()
+                .with_timezone(&Utc),
+        })
+    }
+
+    fn invoice_from_row(row: &Row) -> rusqlite::Result<Invoice> {
+        let grouping: String = row.get("grouping")?;
+        let line_items: String = row.get("line_items")?;
+
+        Ok(Invoice {
+            id: Uuid::parse_str(&row.get::<_, String>("id")?).unwrap(),
+            number: row.get("number")?,
+            client: row.get("client")?,
+            period: crate::models::DateRange {
+                start: DateTime::parse_from_rfc3339(&row.get::<_, String>("period_start")?)
+                    .unwrap()
+                    .with_timezone(&Utc),
+                end: DateTime::parse_from_rfc3339(&row.get::<_, String>("period_end")?)
+                    .unwrap()
+                    .with_timezone(&Utc),
+            },
+            grouping: grouping.parse().unwrap_or(InvoiceGrouping::Project),
+            rounding_increment_minutes: row.get("rounding_increment_minutes")?,
+            currency: row.get("currency")?,
+            line_items: serde_json::from_str(&line_items).unwrap_or_default(),
+            total_duration: chrono::Duration::seconds(row.get("total_duration_seconds")?),
+            total_billed_duration: chrono::Duration::seconds(row.get("total_billed_seconds")?),
+            total_amount_cents: row.get("total_amount_cents")?,
+            created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>("created_at")?)
+                .unwrap()
+                .with_timezone(&Utc),
         })
     }
 }
@@ -234,14 +570,23 @@ impl Repository for SqliteRepository {
     async fn create_project(&self, project: &Project) -> Result<()> {
         let conn = self.connection.lock().unwrap();
 
+        let rounding_rule = project
+            .rounding_rule
+            .map(|rule| serde_json::to_string(&rule).unwrap());
+
         let result = conn.execute(
-            "INSERT INTO projects (id, name, description, directory_path, is_client_project, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            "INSERT INTO projects (id, name, description, directory_path, is_client_project, hourly_rate_cents, billable, rounding_rule, git_remote_url, archived_at, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
             params![
                 project.id.to_string(),
                 project.name,
                 project.description,
                 project.directory_path,
                 project.is_client_project,
+                project.hourly_rate_cents,
+                project.billable,
+                rounding_rule,
+                project.git_remote_url,
+                project.archived_at.map(|dt| dt.to_rfc3339()),
                 project.created_at.to_rfc3339(),
                 project.updated_at.to_rfc3339(),
             ],
@@ -265,7 +610,7 @@ impl Repository for SqliteRepository {
     async fn get_project_by_name(&self, name: &str) -> Result<Option<Project>> {
         let conn = self.connection.lock().unwrap();
 
-        let mut stmt = conn.prepare("SELECT id, name, description, directory_path, is_client_project, created_at, updated_at FROM projects WHERE name = ?1")?;
+        let mut stmt = conn.prepare("SELECT id, name, description, directory_path, is_client_project, hourly_rate_cents, billable, rounding_rule, git_remote_url, archived_at, created_at, updated_at FROM projects WHERE name = ?1")?;
         let mut rows = stmt.query_map(params![name], Self::project_from_row)?;
 
         if let Some(row) = rows.next() {
@@ -278,7 +623,7 @@ impl Repository for SqliteRepository {
     async fn get_project_by_id(&self, id: Uuid) -> Result<Option<Project>> {
         let conn = self.connection.lock().unwrap();
 
-        let mut stmt = conn.prepare("SELECT id, name, description, directory_path, is_client_project, created_at, updated_at FROM projects WHERE id = ?1")?;
+        let mut stmt = conn.prepare("SELECT id, name, description, directory_path, is_client_project, hourly_rate_cents, billable, rounding_rule, git_remote_url, archived_at, created_at, updated_at FROM projects WHERE id = ?1")?;
         let mut rows = stmt.query_map(params![id.to_string()], Self::project_from_row)?;
 
         if let Some(row) = rows.next() {
@@ -291,7 +636,7 @@ impl Repository for SqliteRepository {
     async fn list_projects(&self) -> Result<Vec<Project>> {
         let conn = self.connection.lock().unwrap();
 
-        let mut stmt = conn.prepare("SELECT id, name, description, directory_path, is_client_project, created_at, updated_at FROM projects ORDER BY name")?;
+        let mut stmt = conn.prepare("SELECT id, name, description, directory_path, is_client_project, hourly_rate_cents, billable, rounding_rule, git_remote_url, archived_at, created_at, updated_at FROM projects ORDER BY name")?;
         let project_iter = stmt.query_map([], Self::project_from_row)?;
 
         let mut projects = Vec::new();
@@ -305,14 +650,23 @@ impl Repository for SqliteRepository {
     async fn update_project(&self, project: &Project) -> Result<()> {
         let conn = self.connection.lock().unwrap();
 
+        let rounding_rule = project
+            .rounding_rule
+            .map(|rule| serde_json::to_string(&rule).unwrap());
+
         conn.execute(
-            "UPDATE projects SET name = ?2, description = ?3, directory_path = ?4, is_client_project = ?5, updated_at = ?6 WHERE id = ?1",
+            "UPDATE projects SET name = ?2, description = ?3, directory_path = ?4, is_client_project = ?5, hourly_rate_cents = ?6, billable = ?7, rounding_rule = ?8, git_remote_url = ?9, archived_at = ?10, updated_at = ?11 WHERE id = ?1",
             params![
                 project.id.to_string(),
                 project.name,
                 project.description,
                 project.directory_path,
                 project.is_client_project,
+                project.hourly_rate_cents,
+                project.billable,
+                rounding_rule,
+                project.git_remote_url,
+                project.archived_at.map(|dt| dt.to_rfc3339()),
                 project.updated_at.to_rfc3339(),
             ],
         )?;
@@ -359,8 +713,9 @@ impl Repository for SqliteRepository {
         conn.execute(
             r#"
             INSERT INTO time_entries 
-            (id, project_id, project_name, task_description, start_time, end_time, duration_seconds, tags, created_at, updated_at) 
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            (id, project_id, project_name, task_description, start_time, end_time, duration_seconds, tags, branch,
+             commit_hashes, created_at, updated_at) 
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
             "#,
             params![
                 entry.id.to_string(),
@@ -371,6 +726,8 @@ impl Repository for SqliteRepository {
                 end_time,
                 duration_seconds,
                 tags_json,
+                entry.branch,
+                commit_hashes_json(entry),
                 entry.created_at.to_rfc3339(),
                 entry.updated_at.to_rfc3339(),
             ],
@@ -385,7 +742,8 @@ impl Repository for SqliteRepository {
         let mut stmt = conn.prepare(
             r#"
             SELECT id, project_id, project_name, task_description, start_time, end_time, 
-                   duration_seconds, tags, created_at, updated_at 
+                   duration_seconds, tags, invoice_id, branch, commit_hashes,
+                   created_at, updated_at 
             FROM time_entries WHERE id = ?1
             "#,
         )?;
@@ -398,13 +756,42 @@ impl Repository for SqliteRepository {
         }
     }
 
+    async fn find_time_entries_by_id_prefix(&self, prefix: &str) -> Result<Vec<TimeEntry>> {
+        let conn = self.connection.lock().unwrap();
+
+        // Ids are hex and dashes, so the prefix can't contain LIKE wildcards
+        let prefix: String = prefix
+            .to_lowercase()
+            .chars()
+            .filter(|c| c.is_ascii_hexdigit() || *c == '-')
+            .collect();
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, project_id, project_name, task_description, start_time, end_time, 
+                   duration_seconds, tags, invoice_id, branch, commit_hashes,
+                   created_at, updated_at 
+            FROM time_entries WHERE id LIKE ?1 || '%'
+            ORDER BY start_time DESC
+            "#,
+        )?;
+        let entry_iter = stmt.query_map(params![prefix], Self::time_entry_from_row)?;
+
+        let mut entries = Vec::new();
+        for entry in entry_iter {
+            entries.push(entry?);
+        }
+
+        Ok(entries)
+    }
+
     async fn get_active_time_entry(&self) -> Result<Option<TimeEntry>> {
         let conn = self.connection.lock().unwrap();
 
         let mut stmt = conn.prepare(
             r#"
             SELECT id, project_id, project_name, task_description, start_time, end_time, 
-                   duration_seconds, tags, created_at, updated_at 
+                   duration_seconds, tags, invoice_id, branch, commit_hashes,
+                   created_at, updated_at 
             FROM time_entries WHERE end_time IS NULL
             ORDER BY start_time DESC LIMIT 1
             "#,
@@ -424,7 +811,8 @@ impl Repository for SqliteRepository {
         let mut stmt = conn.prepare(
             r#"
             SELECT id, project_id, project_name, task_description, start_time, end_time, 
-                   duration_seconds, tags, created_at, updated_at 
+                   duration_seconds, tags, invoice_id, branch, commit_hashes,
+                   created_at, updated_at 
             FROM time_entries WHERE project_id = ?1
             ORDER BY start_time DESC
             "#,
@@ -450,7 +838,8 @@ impl Repository for SqliteRepository {
         let mut stmt = conn.prepare(
             r#"
             SELECT id, project_id, project_name, task_description, start_time, end_time, 
-                   duration_seconds, tags, created_at, updated_at 
+                   duration_seconds, tags, invoice_id, branch, commit_hashes,
+                   created_at, updated_at 
             FROM time_entries 
             WHERE start_time >= ?1 AND start_time <= ?2
             ORDER BY start_time ASC
@@ -472,6 +861,18 @@ impl Repository for SqliteRepository {
     async fn update_time_entry(&self, entry: &TimeEntry) -> Result<()> {
         let conn = self.connection.lock().unwrap();
 
+        // Invoiced entries are locked so billed time can't change afterwards
+        let invoice_id: Option<String> = conn
+            .query_row(
+                "SELECT invoice_id FROM time_entries WHERE id = ?1",
+                params![entry.id.to_string()],
+                |row| row.get(0),
+            )
+            .unwrap_or(None);
+        if invoice_id.is_some() {
+            return Err(TimeSpanError::TimeEntryLocked(entry.id.to_string()));
+        }
+
         let tags_json = if entry.tags.is_empty() {
             None
         } else {
@@ -485,7 +886,8 @@ impl Repository for SqliteRepository {
             r#"
             UPDATE time_entries 
             SET project_id = ?2, project_name = ?3, task_description = ?4, start_time = ?5, 
-                end_time = ?6, duration_seconds = ?7, tags = ?8, updated_at = ?9
+                end_time = ?6, duration_seconds = ?7, tags = ?8, branch = ?9, commit_hashes = ?10,
+                updated_at = ?11
             WHERE id = ?1
             "#,
             params![
@@ -497,6 +899,8 @@ impl Repository for SqliteRepository {
                 end_time,
                 duration_seconds,
                 tags_json,
+                entry.branch,
+                commit_hashes_json(entry),
                 entry.updated_at.to_rfc3339(),
             ],
         )?;
@@ -528,8 +932,8 @@ impl Repository for SqliteRepository {
         conn.execute(
             r#"
             INSERT INTO active_timer 
-            (id, project_id, project_name, task_description, start_time, tags) 
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            (id, project_id, project_name, task_description, start_time, tags, branch, repository) 
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
             "#,
             params![
                 timer.id.to_string(),
@@ -538,6 +942,8 @@ impl Repository for SqliteRepository {
                 timer.task_description,
                 timer.start_time.to_rfc3339(),
                 tags_json,
+                timer.branch,
+                timer.repository,
             ],
         )?;
 
@@ -548,7 +954,7 @@ impl Repository for SqliteRepository {
         let conn = self.connection.lock().unwrap();
 
         let mut stmt = conn.prepare(
-            "SELECT id, project_id, project_name, task_description, start_time, tags FROM active_timer"
+            "SELECT id, project_id, project_name, task_description, start_time, tags, branch, repository FROM active_timer"
         )?;
         let mut rows = stmt.query_map([], Self::timer_from_row)?;
 
@@ -565,8 +971,455 @@ impl Repository for SqliteRepository {
         Ok(())
     }
 
+    async fn create_invoice(&self, invoice: &Invoice) -> Result<()> {
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let line_items = serde_json::to_string(&invoice.line_items).unwrap();
+
+        tx.execute(
+            r#"
+            INSERT INTO invoices
+            (id, number, client, period_start, period_end, grouping, rounding_increment_minutes,
+             currency, line_items, total_duration_seconds, total_billed_seconds, total_amount_cents, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+            "#,
+            params![
+                invoice.id.to_string(),
+                invoice.number,
+                invoice.client,
+                invoice.period.start.to_rfc3339(),
+                invoice.period.end.to_rfc3339(),
+                invoice.grouping.as_str(),
+                invoice.rounding_increment_minutes,
+                invoice.currency,
+                line_items,
+                invoice.total_duration.num_seconds(),
+                invoice.total_billed_duration.num_seconds(),
+                invoice.total_amount_cents,
+                invoice.created_at.to_rfc3339(),
+            ],
+        )?;
+
+        for entry_id in invoice.entry_ids() {
+            let updated = tx.execute(
+                "UPDATE time_entries SET invoice_id = ?2 WHERE id = ?1 AND invoice_id IS NULL",
+                params![entry_id.to_string(), invoice.id.to_string()],
+            )?;
+
+            // Dropping the transaction rolls back the invoice insert
+            if updated == 0 {
+                return Err(TimeSpanError::TimeEntryLocked(entry_id.to_string()));
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    async fn get_invoice_by_number(&self, number: &str) -> Result<Option<Invoice>> {
+        let conn = self.connection.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, number, client, period_start, period_end, grouping, rounding_increment_minutes,
+                   currency, line_items, total_duration_seconds, total_billed_seconds,
+                   total_amount_cents, created_at
+            FROM invoices WHERE number = ?1
+            "#,
+        )?;
+        let mut rows = stmt.query_map(params![number], Self::invoice_from_row)?;
+
+        if let Some(row) = rows.next() {
+            Ok(Some(row?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn list_invoices(&self) -> Result<Vec<Invoice>> {
+        let conn = self.connection.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, number, client, period_start, period_end, grouping, rounding_increment_minutes,
+                   currency, line_items, total_duration_seconds, total_billed_seconds,
+                   total_amount_cents, created_at
+            FROM invoices
+            ORDER BY created_at ASC
+            "#,
+        )?;
+        let invoice_iter = stmt.query_map([], Self::invoice_from_row)?;
+
+        let mut invoices = Vec::new();
+        for invoice in invoice_iter {
+            invoices.push(invoice?);
+        }
+
+        Ok(invoices)
+    }
+
+    async fn count_invoices_numbered(&self, prefix: &str) -> Result<usize> {
+        let conn = self.connection.lock().unwrap();
+
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM invoices WHERE substr(number, 1, length(?1)) = ?1",
+            params![prefix],
+            |row| row.get(0),
+        )?;
+
+        Ok(count as usize)
+    }
+
+    async fn import_git_time_entry(
+        &self,
+        entry: &TimeEntry,
+        git_entry: &GitTimeEntry,
+    ) -> Result<bool> {
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let already_imported: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM git_time_entries WHERE commit_hash = ?1",
+            params![git_entry.commit_hash],
+            |row| row.get(0),
+        )?;
+        if already_imported > 0 {
+            return Ok(false);
+        }
+
+        let tags_json = if entry.tags.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&entry.tags).unwrap())
+        };
+
+        tx.execute(
+            r#"
+            INSERT INTO time_entries
+            (id, project_id, project_name, task_description, start_time, end_time, duration_seconds, tags, branch,
+             commit_hashes, created_at, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+            "#,
+            params![
+                entry.id.to_string(),
+                entry.project_id.to_string(),
+                entry.project_name,
+                entry.task_description,
+                entry.start_time.to_rfc3339(),
+                entry.end_time.map(|dt| dt.to_rfc3339()),
+                entry.duration.map(|d| d.num_seconds()),
+                tags_json,
+                entry.branch,
+                commit_hashes_json(entry),
+                entry.created_at.to_rfc3339(),
+                entry.updated_at.to_rfc3339(),
+            ],
+        )?;
+
+        tx.execute(
+            r#"
+            INSERT INTO git_time_entries
+            (id, commit_hash, repository_path, committed_at, project_id, project_name,
+             time_entry_id, estimated_seconds, actual_seconds, confidence_score, commit_type,
+             created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+            "#,
+            params![
+                git_entry.id.to_string(),
+                git_entry.commit_hash,
+                git_entry.repository_path.to_string_lossy(),
+                git_entry.committed_at.to_rfc3339(),
+                git_entry.project_id.to_string(),
+                git_entry.project_name,
+                entry.id.to_string(),
+                git_entry.estimated_time.num_seconds(),
+                git_entry.actual_time.map(|d| d.num_seconds()),
+                git_entry.confidence_score as f64,
+                git_entry.commit_type.as_str(),
+                git_entry.created_at.to_rfc3339(),
+            ],
+        )?;
+
+        tx.commit()?;
+        Ok(true)
+    }
+
+    async fn get_git_time_entry_by_commit(
+        &self,
+        commit_hash: &str,
+    ) -> Result<Option<GitTimeEntry>> {
+        let conn = self.connection.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, commit_hash, repository_path, committed_at, project_id, project_name,
+                   time_entry_id, estimated_seconds, actual_seconds, confidence_score, commit_type,
+                   created_at
+            FROM git_time_entries WHERE commit_hash = ?1
+            "#,
+        )?;
+        let mut rows = stmt.query_map(params![commit_hash], Self::git_time_entry_from_row)?;
+
+        if let Some(row) = rows.next() {
+            Ok(Some(row?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn list_git_time_entries(&self) -> Result<Vec<GitTimeEntry>> {
+        let conn = self.connection.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT id, commit_hash, repository_path, committed_at, project_id, project_name,
+                   time_entry_id, estimated_seconds, actual_seconds, confidence_score, commit_type,
+                   created_at
+            FROM git_time_entries
+            ORDER BY committed_at ASC
+            "#,
+        )?;
+        let entry_iter = stmt.query_map([], Self::git_time_entry_from_row)?;
+
+        let mut entries = Vec::new();
+        for entry in entry_iter {
+            entries.push(entry?);
+        }
+
+        Ok(entries)
+    }
+
+    async fn set_git_actual_time(
+        &self,
+        commit_hash: &str,
+        actual_time: chrono::Duration,
+    ) -> Result<()> {
+        let conn = self.connection.lock().unwrap();
+
+        conn.execute(
+            "UPDATE git_time_entries SET actual_seconds = ?2 WHERE commit_hash = ?1",
+            params![commit_hash, actual_time.num_seconds()],
+        )?;
+
+        Ok(())
+    }
+
+    async fn save_git_calibration(&self, calibration: &GitCalibration) -> Result<()> {
+        let conn = self.connection.lock().unwrap();
+
+        conn.execute(
+            r#"
+            INSERT OR REPLACE INTO git_calibrations
+            (repository_path, commit_type_multipliers, file_type_multipliers, sample_count,
+             mean_error_before_seconds, mean_error_after_seconds, calibrated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            "#,
+            params![
+                calibration.repository_path.to_string_lossy(),
+                serde_json::to_string(&calibration.commit_type_multipliers).unwrap(),
+                serde_json::to_string(&calibration.file_type_multipliers).unwrap(),
+                calibration.sample_count as i64,
+                calibration.mean_error_before.num_seconds(),
+                calibration.mean_error_after.num_seconds(),
+                calibration.calibrated_at.to_rfc3339(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    async fn get_git_calibration(&self, repository_path: &Path) -> Result<Option<GitCalibration>> {
+        let conn = self.connection.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT repository_path, commit_type_multipliers, file_type_multipliers, sample_count,
+                   mean_error_before_seconds, mean_error_after_seconds, calibrated_at
+            FROM git_calibrations WHERE repository_path = ?1
+            "#,
+        )?;
+        let mut rows = stmt.query_map(
+            params![repository_path.to_string_lossy()],
+            Self::git_calibration_from_row,
+        )?;
+
+        if let Some(row) = rows.next() {
+            Ok(Some(row?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn save_entry_references(&self, references: &[EntryReference]) -> Result<()> {
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        for reference in references {
+            tx.execute(
+                r#"
+                INSERT OR IGNORE INTO entry_references (time_entry_id, reference, source)
+                VALUES (?1, ?2, ?3)
+                "#,
+                params![
+                    reference.time_entry_id.to_string(),
+                    reference.reference,
+                    reference.source.as_str(),
+                ],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    async fn replace_entry_references(
+        &self,
+        time_entry_id: Uuid,
+        source: ReferenceSource,
+        references: &[EntryReference],
+    ) -> Result<()> {
+        let mut conn = self.connection.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        tx.execute(
+            "DELETE FROM entry_references WHERE time_entry_id = ?1 AND source = ?2",
+            params![time_entry_id.to_string(), source.as_str()],
+        )?;
+        for reference in references {
+            tx.execute(
+                r#"
+                INSERT OR IGNORE INTO entry_references (time_entry_id, reference, source)
+                VALUES (?1, ?2, ?3)
+                "#,
+                params![
+                    reference.time_entry_id.to_string(),
+                    reference.reference,
+                    reference.source.as_str(),
+                ],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    async fn list_entry_references(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<EntryReference>> {
+        let conn = self.connection.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT r.time_entry_id, r.reference, r.source
+            FROM entry_references r
+            JOIN time_entries t ON t.id = r.time_entry_id
+            WHERE t.start_time >= ?1 AND t.start_time <= ?2
+            ORDER BY t.start_time ASC, r.reference ASC
+            "#,
+        )?;
+        let reference_iter =
+            stmt.query_map(params![start.to_rfc3339(), end.to_rfc3339()], |row| {
+                Ok(EntryReference {
+                    time_entry_id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
+                    reference: row.get(1)?,
+                    source: row
+                        .get::<_, String>(2)?
+                        .parse()
+                        .unwrap_or(ReferenceSource::Task),
+                })
+            })?;
+
+        let mut references = Vec::new();
+        for reference in reference_iter {
+            references.push(reference?);
+        }
+
+        Ok(references)
+    }
+
+    async fn save_activity_pulse(&self, pulse: &ActivityPulse) -> Result<()> {
+        let conn = self.connection.lock().unwrap();
+
+        conn.execute(
+            r#"
+            INSERT INTO activity_pulses
+            (id, project_id, project_name, repository_path, sampled_at, changed_files, lines_changed, fingerprint)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            "#,
+            params![
+                pulse.id.to_string(),
+                pulse.project_id.to_string(),
+                pulse.project_name,
+                pulse.repository_path.to_string_lossy(),
+                pulse.sampled_at.to_rfc3339(),
+                pulse.changed_files,
+                pulse.lines_changed,
+                pulse.fingerprint,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    async fn latest_activity_pulse(&self, repository_path: &Path) -> Result<Option<ActivityPulse>> {
+        let conn = self.connection.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT * FROM activity_pulses
+            WHERE repository_path = ?1
+            ORDER BY sampled_at DESC
+            LIMIT 1
+            "#,
+        )?;
+        let mut rows = stmt.query_map(
+            params![repository_path.to_string_lossy()],
+            Self::activity_pulse_from_row,
+        )?;
+
+        match rows.next() {
+            Some(pulse) => Ok(Some(pulse?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn list_activity_pulses(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<ActivityPulse>> {
+        let conn = self.connection.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT * FROM activity_pulses
+            WHERE sampled_at >= ?1 AND sampled_at <= ?2
+            ORDER BY sampled_at ASC
+            "#,
+        )?;
+        let pulse_iter = stmt.query_map(
+            params![start.to_rfc3339(), end.to_rfc3339()],
+            Self::activity_pulse_from_row,
+        )?;
+
+        let mut pulses = Vec::new();
+        for pulse in pulse_iter {
+            pulses.push(pulse?);
+        }
+
+        Ok(pulses)
+    }
+
     async fn clear_all(&self) -> Result<()> {
         let conn = self.connection.lock().unwrap();
+        conn.execute("DELETE FROM activity_pulses", [])?;
+        conn.execute("DELETE FROM entry_references", [])?;
+        conn.execute("DELETE FROM git_calibrations", [])?;
+        conn.execute("DELETE FROM git_time_entries", [])?;
+        conn.execute("DELETE FROM invoices", [])?;
         conn.execute("DELETE FROM time_entries", [])?;
         conn.execute("DELETE FROM projects", [])?;
         conn.execute("DELETE FROM active_timer", [])?;
@@ -574,6 +1427,14 @@ impl Repository for SqliteRepository {
     }
 }
 
+fn commit_hashes_json(entry: &TimeEntry) -> Option<String> {
+    if entry.commit_hashes.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(&entry.commit_hashes).unwrap())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -643,6 +1504,26 @@ mod tests {
         assert_eq!(retrieved.updated_at, project.updated_at);
     }
 
+    #[tokio::test]
+    async fn test_project_rate_and_rounding_roundtrip() {
+        use crate::models::{RoundingMode, RoundingRule, RoundingScope};
+
+        let repo = setup_repo().await;
+        let mut project = Project::new("Test Project".to_string(), None);
+        project.set_hourly_rate(Some(12_000));
+        project.set_rounding_rule(Some(RoundingRule::new(
+            RoundingMode::Up,
+            RoundingScope::Daily,
+            6,
+        )));
+
+        repo.create_project(&project).await.unwrap();
+
+        let retrieved = repo.get_project_by_id(project.id).await.unwrap().unwrap();
+        assert_eq!(retrieved.hourly_rate_cents, Some(12_000));
+        assert_eq!(retrieved.rounding_rule, project.rounding_rule);
+    }
+
     #[tokio::test]
     async fn test_delete_project_without_entries() {
         let repo = setup_repo().await;
@@ -793,6 +1674,8 @@ mod tests {
             Utc::now(),
         );
         timer.add_tag("development".to_string());
+        timer.branch = Some("main".to_string());
+        timer.repository = Some("/work/app".to_string());
 
         // No active timer initially
         let active = repo.get_active_timer().await.unwrap();
@@ -807,6 +1690,8 @@ mod tests {
         assert_eq!(active.project_name, "Test Project");
         assert_eq!(active.task_description, Some("Test task".to_string()));
         assert_eq!(active.tags, vec!["development"]);
+        assert_eq!(active.branch.as_deref(), Some("main"));
+        assert_eq!(active.repository.as_deref(), Some("/work/app"));
 
         // Clear timer
         repo.clear_active_timer().await.unwrap();
@@ -840,4 +1725,60 @@ mod tests {
             2
         );
     }
+
+    #[tokio::test]
+    async fn test_import_git_time_entry_is_idempotent() {
+        let repo = setup_repo().await;
+        let project = Project::new("Test Project".to_string(), None);
+        repo.create_project(&project).await.unwrap();
+
+        let commit = crate::models::GitCommit::new(
+            "abc123".to_string(),
+            "feat: thing".to_string(),
+            "Test Author".to_string(),
+            "test@example.com".to_string(),
+            Utc.with_ymd_and_hms(2024, 1, 1, 10, 0, 0).unwrap(),
+            std::path::PathBuf::from("/repo"),
+        );
+        let git_entry = GitTimeEntry::new(
+            &commit,
+            project.id,
+            project.name.clone(),
+            chrono::Duration::minutes(30),
+            0.7,
+        );
+
+        let mut first = TimeEntry::new(project.id, project.name.clone(), None, commit.timestamp);
+        first
+            .stop(commit.timestamp + chrono::Duration::minutes(30))
+            .unwrap();
+        assert!(repo
+            .import_git_time_entry(&first, &git_entry)
+            .await
+            .unwrap());
+
+        let second = TimeEntry::new(project.id, project.name.clone(), None, commit.timestamp);
+        let mut duplicate = git_entry.clone();
+        duplicate.id = Uuid::new_v4();
+        assert!(!repo
+            .import_git_time_entry(&second, &duplicate)
+            .await
+            .unwrap());
+
+        assert_eq!(
+            repo.count_time_entries_for_project(project.id)
+                .await
+                .unwrap(),
+            1
+        );
+        let stored = repo
+            .get_git_time_entry_by_commit("abc123")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.time_entry_id, Some(first.id));
+        assert_eq!(stored.estimated_time, chrono::Duration::minutes(30));
+        assert_eq!(stored.repository_path, std::path::PathBuf::from("/repo"));
+        assert_eq!(stored.commit_type, CommitType::Feature);
+    }
 }
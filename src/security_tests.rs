@@ -12,7 +12,7 @@
 #[allow(clippy::module_inception)]
 mod security_tests {
     use crate::repository::{Repository, SqliteRepository};
-    use crate::services::{GitService, ProjectService};
+    use crate::services::{CommitQuery, GitService, ProjectService};
     use std::path::PathBuf;
     use std::sync::Arc;
     use tempfile::TempDir;
@@ -177,7 +177,13 @@ mod security_tests {
             // 3. Validate path permissions
 
             let result = git_service
-                .get_commits(&dangerous_path, None, Some(1))
+                .get_commits(
+                    &dangerous_path,
+                    &CommitQuery {
+                        limit: Some(1),
+                        ..CommitQuery::default()
+                    },
+                )
                 .await;
 
             // Should either fail gracefully or succeed with empty results
@@ -238,7 +244,7 @@ mod security_tests {
                             // At this point, the raw input contains the dangerous patterns
                             // but our application validation will reject it when processed
                             // We just verify clap parsed it as a literal string
-                            assert!(start_args.project.is_ascii());
+                            assert!(start_args.project.is_some_and(|project| project.is_ascii()));
                         }
                         Commands::Project {
                             command: ProjectCommands::Create { name, .. },
@@ -1,34 +1,96 @@
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use crate::config::DiscoveryConfig;
+use crate::manifest::{ProjectManifest, MANIFEST_FILE};
 use crate::models::Project;
 use crate::repository::Repository;
+use crate::services::git_service::open_repository;
 use crate::services::ProjectService;
 use crate::{Result, TimeSpanError};
 
 pub struct ClientDiscoveryService {
     project_service: ProjectService,
     repository: Arc<dyn Repository>,
+    config: DiscoveryConfig,
 }
 
+/// Exclude patterns read from this file in the base directory, one glob per
+/// line; blank lines and lines starting with `#` are ignored
+pub const IGNORE_FILE: &str = ".timespanignore";
+
 #[derive(Debug, Clone)]
 pub struct DiscoveryOptions {
-    pub base_path: PathBuf,
+    /// Directories to scan; project names are relative to their root
+    pub roots: Vec<PathBuf>,
+    /// Globs matched against each directory's name and its path relative to
+    /// the base; excluded directories aren't descended into
     pub exclude_patterns: Vec<String>,
+    /// When not empty, only project directories matching one of these globs
+    /// are kept
+    pub include_patterns: Vec<String>,
+    /// How many levels below the base project directories sit: 1 for
+    /// `<base>/<project>`, 2 for `<base>/<client>/<project>`
+    pub depth: usize,
     pub project_prefix: Option<String>,
     pub dry_run: bool,
+    /// Archive client projects under the base whose directory is gone
+    pub prune: bool,
 }
 
+/// What one discovery run found and changed. It's a report for the run and
+/// isn't saved: moves and archiving are recorded on the projects themselves.
 #[derive(Debug)]
 pub struct DiscoveryResult {
     pub discovered_directories: Vec<ClientDirectory>,
     pub created_projects: Vec<Project>,
     pub updated_projects: Vec<Project>,
+    /// Projects whose directory was found at a new location
+    pub moved_projects: Vec<MovedProject>,
+    /// Client projects under the base whose directory no longer exists and
+    /// wasn't found elsewhere
+    pub orphaned_projects: Vec<Project>,
+    /// Orphans archived because of `prune`
+    pub archived_projects: Vec<Project>,
     pub skipped_directories: Vec<String>,
     pub errors: Vec<String>,
 }
 
+/// How a project was matched to a directory; only some of these can reveal
+/// a move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveEvidence {
+    /// The directory name still gives the project's name
+    Name,
+    /// The project's stored directory is this one, so it hasn't moved
+    Directory,
+    /// The directory's `origin` URL matches the one stored for the project
+    GitRemote,
+    /// The directory's `.timespan.toml` declares the project
+    Manifest,
+}
+
+impl MoveEvidence {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MoveEvidence::Name => "name",
+            MoveEvidence::Directory => "directory",
+            MoveEvidence::GitRemote => "git remote",
+            MoveEvidence::Manifest => "manifest",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MovedProject {
+    /// The project with its new directory
+    pub project: Project,
+    pub previous_path: PathBuf,
+    pub evidence: MoveEvidence,
+}
+
 #[derive(Debug, Clone)]
 pub struct ClientDirectory {
     pub name: String,
@@ -36,69 +98,76 @@ pub struct ClientDirectory {
     pub is_git_repo: bool,
     pub last_modified: Option<std::time::SystemTime>,
     pub suggested_description: Option<String>,
+    /// The directory's own `.timespan.toml`, which names the project
+    pub manifest: Option<ProjectManifest>,
+    /// URL of the repository's `origin` remote
+    pub remote_url: Option<String>,
 }
 
 impl Default for DiscoveryOptions {
     fn default() -> Self {
+        Self::from_config(&DiscoveryConfig::default())
+    }
+}
+
+impl DiscoveryOptions {
+    /// Options for scanning the configured roots
+    pub fn from_config(config: &DiscoveryConfig) -> Self {
         Self {
-            base_path: PathBuf::from("/Users/user/workspace/Clients"),
-            exclude_patterns: vec![
-                ".DS_Store".to_string(),
-                ".git".to_string(),
-                ".github".to_string(),
-                ".*".to_string(), // Exclude all hidden directories/files
-                "*.pdf".to_string(),
-                "*.mp4".to_string(),
-                "*.zip".to_string(),
-                "*.whisper".to_string(),
-                "*.html".to_string(),
-                "*.mht".to_string(),
-                "*.pages".to_string(),
-                "*.md".to_string(),
-                // Common IDE/editor directories
-                ".vscode".to_string(),
-                ".idea".to_string(),
-                ".claude".to_string(),
-                ".cursor".to_string(),
-                ".vscode-insiders".to_string(),
-                ".atom".to_string(),
-                ".sublime-text".to_string(),
-                ".vim".to_string(),
-                ".emacs.d".to_string(),
-            ],
-            project_prefix: Some("[CLIENT]".to_string()),
+            roots: config.roots.clone(),
+            exclude_patterns: config.exclude.clone(),
+            include_patterns: Vec::new(),
+            depth: 1,
+            project_prefix: config.prefix.clone(),
             dry_run: false,
+            prune: false,
         }
     }
 }
 
 impl ClientDiscoveryService {
     pub fn new(repository: Arc<dyn Repository>) -> Self {
+        Self::with_config(repository, DiscoveryConfig::default())
+    }
+
+    pub fn with_config(repository: Arc<dyn Repository>, config: DiscoveryConfig) -> Self {
         let project_service = ProjectService::new(repository.clone());
         Self {
             project_service,
             repository,
+            config,
         }
     }
 
+    pub fn config(&self) -> &DiscoveryConfig {
+        &self.config
+    }
+
     pub async fn discover_clients(&self, options: &DiscoveryOptions) -> Result<DiscoveryResult> {
         let mut result = DiscoveryResult {
             discovered_directories: Vec::new(),
             created_projects: Vec::new(),
             updated_projects: Vec::new(),
+            moved_projects: Vec::new(),
+            orphaned_projects: Vec::new(),
+            archived_projects: Vec::new(),
             skipped_directories: Vec::new(),
             errors: Vec::new(),
         };
 
         // Scan the base directory
-        let directories =
-            self.scan_client_directories(&options.base_path, &options.exclude_patterns)?;
+        let directories = self.scan_client_directories(options, &mut result.skipped_directories)?;
         result.discovered_directories = directories.clone();
 
+        // Projects as they were before this run; directories found again
+        // below claim their orphans as moves
+        let projects = self.project_service.list_projects().await?;
+        let mut orphans = find_orphaned_projects(&projects, &options.roots);
+
         // Process each directory
         for dir in directories {
             match self
-                .process_client_directory(&dir, options, &mut result)
+                .process_client_directory(&dir, options, &projects, &mut orphans, &mut result)
                 .await
             {
                 Ok(_) => {}
@@ -110,76 +179,154 @@ impl ClientDiscoveryService {
             }
         }
 
+        if options.prune && !options.dry_run {
+            for orphan in &orphans {
+                let mut archived = orphan.clone();
+                archived.archived_at = Some(chrono::Utc::now());
+                archived.updated_at = chrono::Utc::now();
+                // One project that can't be archived doesn't stop the rest
+                match self.repository.update_project(&archived).await {
+                    Ok(()) => result.archived_projects.push(archived),
+                    Err(e) => result
+                        .errors
+                        .push(format!("Error archiving {}: {}", orphan.name, e)),
+                }
+            }
+        }
+        result.orphaned_projects = orphans;
+
         Ok(result)
     }
 
+    /// Project directories under the roots. Missing roots and directories
+    /// that can't be used, e.g. because their manifest is invalid or another
+    /// root already has a directory of that name, are added to `skipped`.
+    /// Fails only when none of the roots exist.
     fn scan_client_directories(
         &self,
-        base_path: &Path,
-        exclude_patterns: &[String],
+        options: &DiscoveryOptions,
+        skipped: &mut Vec<String>,
     ) -> Result<Vec<ClientDirectory>> {
-        let mut directories = Vec::new();
+        if options.roots.is_empty() {
+            return Err(TimeSpanError::InvalidInput(
+                "No directories to scan".to_string(),
+            ));
+        }
+        if options.depth == 0 {
+            return Err(TimeSpanError::InvalidInput(
+                "Discovery depth must be at least 1".to_string(),
+            ));
+        }
 
-        if !base_path.exists() {
-            return Err(TimeSpanError::InvalidDuration(format!(
-                "Base path does not exist: {}",
-                base_path.display()
+        let mut directories: Vec<ClientDirectory> = Vec::new();
+        let mut scanned_roots = 0;
+        for root in &options.roots {
+            if !root.is_dir() {
+                skipped.push(format!("{} (directory does not exist)", root.display()));
+                continue;
+            }
+            scanned_roots += 1;
+
+            for dir in self.scan_root(root, options, skipped)? {
+                // Both would become the same project; the earlier root wins
+                match directories.iter().find(|found| found.name == dir.name) {
+                    Some(found) => skipped.push(format!(
+                        "{} (same name as {})",
+                        dir.path.display(),
+                        found.path.display()
+                    )),
+                    None => directories.push(dir),
+                }
+            }
+        }
+        if scanned_roots == 0 {
+            return Err(TimeSpanError::InvalidInput(format!(
+                "None of the directories to scan exist: {}",
+                options
+                    .roots
+                    .iter()
+                    .map(|root| root.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
             )));
         }
 
-        let entries = fs::read_dir(base_path).map_err(TimeSpanError::Io)?;
+        // Sort by name for consistent ordering
+        directories.sort_by(|a, b| a.name.cmp(&b.name));
 
-        for entry in entries {
-            let entry = entry.map_err(TimeSpanError::Io)?;
+        Ok(directories)
+    }
+
+    fn scan_root(
+        &self,
+        base_path: &Path,
+        options: &DiscoveryOptions,
+        skipped: &mut Vec<String>,
+    ) -> Result<Vec<ClientDirectory>> {
+        let mut directories = Vec::new();
+
+        let mut exclude_patterns = options.exclude_patterns.clone();
+        exclude_patterns.extend(read_ignore_file(base_path)?);
+        let exclude = glob_set(&exclude_patterns)?;
+        let include = if options.include_patterns.is_empty() {
+            None
+        } else {
+            Some(glob_set(&options.include_patterns)?)
+        };
+
+        // No min_depth: entries it skips never reach filter_entry
+        let walker = walkdir::WalkDir::new(base_path)
+            .max_depth(options.depth)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| {
+                // Prune excluded directories at every level, not just the last
+                entry.depth() == 0 || !matches_directory(&exclude, base_path, entry.path())
+            });
+
+        for entry in walker {
+            // An unreadable directory is skipped; the rest of the walk goes on
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    let name = e
+                        .path()
+                        .and_then(|path| relative_name(base_path, path))
+                        .filter(|name| !name.is_empty())
+                        .unwrap_or_else(|| base_path.display().to_string());
+                    skipped.push(format!("{} (unreadable: {})", name, e));
+                    continue;
+                }
+            };
             let path = entry.path();
 
             // Skip files, only process directories
-            if !path.is_dir() {
+            if entry.depth() != options.depth || !path.is_dir() {
                 continue;
             }
 
-            let name = match path.file_name().and_then(|n| n.to_str()) {
-                Some(name) => name.to_string(),
-                None => continue,
-            };
-
-            // Skip excluded patterns
-            if self.should_exclude(&name, exclude_patterns) {
+            if include
+                .as_ref()
+                .is_some_and(|include| !matches_directory(include, base_path, path))
+            {
                 continue;
             }
 
-            let client_dir = self.analyze_directory(&name, &path)?;
-            directories.push(client_dir);
-        }
-
-        // Sort by name for consistent ordering
-        directories.sort_by(|a, b| a.name.cmp(&b.name));
-
-        Ok(directories)
-    }
+            // Nested projects are named by their path, e.g. `Acme/Website`
+            let Some(name) = relative_name(base_path, path) else {
+                continue;
+            };
 
-    fn should_exclude(&self, name: &str, exclude_patterns: &[String]) -> bool {
-        for pattern in exclude_patterns {
-            if pattern == ".*" {
-                // Exclude all hidden files/directories (starting with .)
-                if name.starts_with('.') {
-                    return true;
-                }
-            } else if pattern.contains('*') {
-                // Simple wildcard matching
-                if pattern.starts_with('*') && name.ends_with(&pattern[1..]) {
-                    return true;
-                }
-                if pattern.ends_with('*') && name.starts_with(&pattern[..pattern.len() - 1]) {
-                    return true;
-                }
-            } else if name == pattern {
-                return true;
+            match self.analyze_directory(&name, path) {
+                Ok(client_dir) => directories.push(client_dir),
+                Err(e) => skipped.push(format!("{} (invalid manifest: {})", name, e)),
             }
         }
-        false
+
+        Ok(directories)
     }
 
+    /// Fails only when the directory's manifest can't be loaded
     fn analyze_directory(&self, name: &str, path: &Path) -> Result<ClientDirectory> {
         let is_git_repo = path.join(".git").exists();
 
@@ -187,7 +334,19 @@ impl ClientDiscoveryService {
             .ok()
             .and_then(|meta| meta.modified().ok());
 
-        let suggested_description = self.generate_description(name, path, is_git_repo);
+        let manifest_path = path.join(MANIFEST_FILE);
+        let manifest = if manifest_path.is_file() {
+            Some(ProjectManifest::load(&manifest_path)?)
+        } else {
+            None
+        };
+
+        let suggested_description = match manifest.as_ref().and_then(|m| m.description.clone()) {
+            Some(description) => Some(description),
+            None => self.config.description(name, path, is_git_repo),
+        };
+
+        let remote_url = if is_git_repo { origin_url(path) } else { None };
 
         Ok(ClientDirectory {
             name: name.to_string(),
@@ -195,86 +354,119 @@ impl ClientDiscoveryService {
             is_git_repo,
             last_modified,
             suggested_description,
+            manifest,
+            remote_url,
         })
     }
 
-    fn generate_description(&self, name: &str, path: &Path, is_git_repo: bool) -> Option<String> {
-        let mut parts = Vec::new();
-
-        // Add client type hint based on name patterns
-        if name.starts_with("NNL_") {
-            parts.push("Example Corp internal project");
-        } else if name.contains("Release") {
-            parts.push("Product release work");
-        } else {
-            parts.push("Client project");
-        }
-
-        // Add git info
-        if is_git_repo {
-            parts.push("(Git repository)");
-        }
-
-        // Add path info
-        let location = format!("Location: {}", path.display());
-        parts.push(&location);
-
-        Some(parts.join(" "))
-    }
-
     async fn process_client_directory(
         &self,
         dir: &ClientDirectory,
         options: &DiscoveryOptions,
+        projects: &[Project],
+        orphans: &mut Vec<Project>,
         result: &mut DiscoveryResult,
     ) -> Result<()> {
-        let project_name = match &options.project_prefix {
-            Some(prefix) => format!("{} {}", prefix, dir.name),
-            None => dir.name.clone(),
+        let (project_name, evidence) = match (&dir.manifest, &options.project_prefix) {
+            // A manifest names the project itself, so the prefix doesn't apply
+            (Some(manifest), _) => (manifest.project_name(), MoveEvidence::Manifest),
+            (None, Some(prefix)) => (format!("{} {}", prefix, dir.name), MoveEvidence::Name),
+            (None, None) => (dir.name.clone(), MoveEvidence::Name),
         };
 
-        // Check if project already exists
-        match self.project_service.get_project(&project_name).await {
-            Ok(Some(existing_project)) => {
-                // Project exists - potentially update it
-                if !options.dry_run {
-                    // Update directory path if it has changed
-                    if existing_project.directory_path.as_deref()
-                        != Some(dir.path.to_str().unwrap_or_default())
-                    {
-                        let mut updated_project = existing_project.clone();
-                        updated_project.directory_path =
-                            Some(dir.path.to_string_lossy().to_string());
-                        updated_project.is_client_project = true;
-                        updated_project.updated_at = chrono::Utc::now();
-
-                        self.repository.update_project(&updated_project).await?;
-                        result.updated_projects.push(updated_project);
-                    } else {
-                        result
-                            .skipped_directories
-                            .push(format!("{} (already exists)", project_name));
-                    }
+        // Check if project already exists, is already kept in this directory
+        // under another name (e.g. after a move), or is an orphan that moved here
+        let mut existing = self
+            .project_service
+            .get_project(&project_name)
+            .await?
+            .map(|project| (project, evidence));
+        if existing.is_none() && dir.manifest.is_none() {
+            existing = projects
+                .iter()
+                .find(|project| project.directory_path.as_deref().map(Path::new) == Some(&dir.path))
+                .map(|project| (project.clone(), MoveEvidence::Directory))
+                .or_else(|| {
+                    take_remote_match(orphans, dir)
+                        .map(|project| (project, MoveEvidence::GitRemote))
+                });
+        }
+        if let Some((project, _)) = &existing {
+            orphans.retain(|orphan| orphan.id != project.id);
+        }
+        let directory_path = dir.path.to_string_lossy().to_string();
+
+        if options.dry_run {
+            if let Some((mut project, evidence)) = existing {
+                if let Some(previous_path) = previous_location(&project, &dir.path) {
+                    project.directory_path = Some(directory_path);
+                    result.moved_projects.push(MovedProject {
+                        project,
+                        previous_path,
+                        evidence,
+                    });
                 }
             }
-            Ok(None) => {
-                // Project doesn't exist - create it
-                if !options.dry_run {
-                    let new_project = Project::new_client_project(
-                        project_name,
-                        dir.suggested_description.clone(),
-                        dir.path.to_string_lossy().to_string(),
-                    );
-
-                    self.repository.create_project(&new_project).await?;
-                    result.created_projects.push(new_project);
-                }
+            return Ok(());
+        }
+
+        let mut project = match (&dir.manifest, &existing) {
+            (Some(manifest), _) => {
+                self.project_service
+                    .ensure_manifest_project(manifest)
+                    .await?
             }
-            Err(e) => {
-                return Err(e);
+            (None, Some((project, _))) => project.clone(),
+            (None, None) => {
+                // Project doesn't exist - create it
+                let mut new_project = Project::new_client_project(
+                    project_name.clone(),
+                    dir.suggested_description.clone(),
+                    directory_path.clone(),
+                );
+                new_project.git_remote_url = dir.remote_url.clone();
+
+                self.repository.create_project(&new_project).await?;
+                new_project
             }
+        };
+
+        let saved = project.clone();
+        project.directory_path = Some(directory_path);
+        project.is_client_project = true;
+        project.archived_at = None;
+        if dir.remote_url.is_some() {
+            project.git_remote_url = dir.remote_url.clone();
+        }
+        if project != saved {
+            project.updated_at = chrono::Utc::now();
+            self.repository.update_project(&project).await?;
         }
 
+        let Some((existing, evidence)) = existing else {
+            result.created_projects.push(project);
+            return Ok(());
+        };
+        if let Some(previous_path) = previous_location(&existing, &dir.path) {
+            result.moved_projects.push(MovedProject {
+                project,
+                previous_path,
+                evidence,
+            });
+            return Ok(());
+        }
+
+        // Recording the remote URL alone isn't worth reporting
+        let mut compared = project.clone();
+        compared.git_remote_url = existing.git_remote_url.clone();
+        compared.updated_at = existing.updated_at;
+        if compared != existing {
+            result.updated_projects.push(project);
+        } else {
+            result
+                .skipped_directories
+                .push(format!("{} (already exists)", project_name));
+        }
         Ok(())
     }
 
@@ -282,14 +474,109 @@ impl ClientDiscoveryService {
         let all_projects = self.project_service.list_projects().await?;
         Ok(all_projects
             .into_iter()
-            .filter(|p| p.is_client_project)
+            .filter(|p| p.is_client_project && !p.is_archived())
             .collect())
     }
 }
 
+/// Unarchived client projects whose directory was under one of `roots` but
+/// no longer exists
+fn find_orphaned_projects(projects: &[Project], roots: &[PathBuf]) -> Vec<Project> {
+    let mut bases = roots.to_vec();
+    bases.extend(roots.iter().filter_map(|root| fs::canonicalize(root).ok()));
+    projects
+        .iter()
+        .filter(|project| project.is_client_project && !project.is_archived())
+        .filter(|project| {
+            project.directory_path.as_deref().is_some_and(|directory| {
+                let directory = Path::new(directory);
+                bases.iter().any(|base| directory.starts_with(base)) && !directory.exists()
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+/// The project's stored directory if it's gone and `path` is a new one
+fn previous_location(project: &Project, path: &Path) -> Option<PathBuf> {
+    let previous = Path::new(project.directory_path.as_deref()?);
+    (previous != path && !previous.exists()).then(|| previous.to_path_buf())
+}
+
+/// Remove and return the orphan whose stored remote URL is the directory's
+fn take_remote_match(orphans: &mut Vec<Project>, dir: &ClientDirectory) -> Option<Project> {
+    let url = dir.remote_url.as_deref()?;
+    let index = orphans
+        .iter()
+        .position(|orphan| orphan.git_remote_url.as_deref() == Some(url))?;
+    Some(orphans.remove(index))
+}
+
+fn origin_url(path: &Path) -> Option<String> {
+    let repo = open_repository(path).ok()?;
+    let remote = repo.find_remote("origin").ok()?;
+    remote.url().map(str::to_string)
+}
+
+/// Compile globs where `*` stays within one path component and `**` spans
+/// any number of them
+fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| {
+                TimeSpanError::InvalidInput(format!("Invalid pattern '{}': {}", pattern, e.kind()))
+            })?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| TimeSpanError::InvalidInput(format!("Invalid patterns: {}", e)))
+}
+
+/// Whether a directory's name or its path relative to `base_path` matches
+fn matches_directory(globs: &GlobSet, base_path: &Path, path: &Path) -> bool {
+    let name_matches = path
+        .file_name()
+        .is_some_and(|name| globs.is_match(Path::new(name)));
+    name_matches
+        || path
+            .strip_prefix(base_path)
+            .is_ok_and(|relative| globs.is_match(relative))
+}
+
+/// `path` relative to `base_path` with `/` separators
+fn relative_name(base_path: &Path, path: &Path) -> Option<String> {
+    let components: Option<Vec<&str>> = path
+        .strip_prefix(base_path)
+        .ok()?
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect();
+    Some(components?.join("/"))
+}
+
+/// Patterns from the base directory's `.timespanignore`, if there is one
+fn read_ignore_file(base_path: &Path) -> Result<Vec<String>> {
+    let path = base_path.join(IGNORE_FILE);
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path).map_err(TimeSpanError::Io)?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.trim_end_matches('/').to_string())
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::UserConfig;
     use crate::repository::SqliteRepository;
 
     async fn setup_service() -> ClientDiscoveryService {
@@ -304,41 +591,381 @@ mod tests {
         // This test verifies the service can be instantiated successfully
     }
 
+    /// Whether `relative` (a path under the base) matches any of `patterns`
+    fn excluded(patterns: &[&str], relative: &str) -> bool {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        let base = Path::new("/base");
+        matches_directory(&glob_set(&patterns).unwrap(), base, &base.join(relative))
+    }
+
+    #[test]
+    fn test_exclude_patterns() {
+        let exclude_patterns = ["*.pdf", ".DS_Store"];
+
+        assert!(excluded(&exclude_patterns, "document.pdf"));
+        assert!(excluded(&exclude_patterns, ".DS_Store"));
+        assert!(!excluded(&exclude_patterns, "ValidClient"));
+    }
+
+    #[test]
+    fn test_hidden_directory_exclusion() {
+        let exclude_patterns = [".*"];
+
+        // Test that hidden directories are excluded
+        assert!(excluded(&exclude_patterns, ".claude"));
+        assert!(excluded(&exclude_patterns, ".cursor"));
+        assert!(excluded(&exclude_patterns, ".vscode"));
+        assert!(excluded(&exclude_patterns, ".git"));
+        assert!(excluded(&exclude_patterns, ".github"));
+        assert!(excluded(&exclude_patterns, ".idea"));
+
+        // Test that non-hidden directories are not excluded
+        assert!(!excluded(&exclude_patterns, "ValidClient"));
+        assert!(!excluded(&exclude_patterns, "MyProject"));
+    }
+
+    #[test]
+    fn test_glob_patterns_match_names_and_paths() {
+        assert!(excluded(&["Archive-[0-9]*"], "Archive-2021"));
+        assert!(excluded(&["*-{old,bak}"], "Acme/site-old"));
+        assert!(excluded(&["Acme/*"], "Acme/Website"));
+        assert!(!excluded(&["Acme/*"], "Globex/Website"));
+        // `*` doesn't cross directories, `**` does
+        assert!(!excluded(&["Acme/*"], "Acme/Website/docs"));
+        assert!(excluded(&["Acme/**/docs"], "Acme/Website/docs"));
+
+        assert!(glob_set(&["[unclosed".to_string()]).is_err());
+    }
+
     #[tokio::test]
-    async fn test_exclude_patterns() {
+    async fn test_discover_nested_projects_with_ignore_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for path in [
+            "Acme/Website",
+            "Acme/App",
+            "Acme/.cache",
+            "Globex/Portal",
+            "Old/Legacy",
+            "Notes",
+        ] {
+            fs::create_dir_all(dir.path().join(path)).unwrap();
+        }
+        fs::write(dir.path().join(IGNORE_FILE), "# retired clients\nOld/\n").unwrap();
+
         let service = setup_service().await;
-        let exclude_patterns = vec!["*.pdf".to_string(), ".DS_Store".to_string()];
+        let mut options = DiscoveryOptions {
+            roots: vec![dir.path().to_path_buf()],
+            exclude_patterns: DiscoveryOptions::default().exclude_patterns,
+            depth: 2,
+            dry_run: true,
+            ..DiscoveryOptions::default()
+        };
+        let names = |result: DiscoveryResult| {
+            result
+                .discovered_directories
+                .into_iter()
+                .map(|dir| dir.name)
+                .collect::<Vec<_>>()
+        };
+
+        let result = service.discover_clients(&options).await.unwrap();
+        assert_eq!(
+            names(result),
+            vec!["Acme/App", "Acme/Website", "Globex/Portal"]
+        );
+
+        options.include_patterns = vec!["Acme/*".to_string()];
+        options.exclude_patterns.push("App".to_string());
+        let result = service.discover_clients(&options).await.unwrap();
+        assert_eq!(names(result), vec!["Acme/Website"]);
 
-        assert!(service.should_exclude("document.pdf", &exclude_patterns));
-        assert!(service.should_exclude(".DS_Store", &exclude_patterns));
-        assert!(!service.should_exclude("ValidClient", &exclude_patterns));
+        options.depth = 1;
+        options.include_patterns.clear();
+        let result = service.discover_clients(&options).await.unwrap();
+        assert_eq!(names(result), vec!["Acme", "Globex", "Notes"]);
     }
 
     #[tokio::test]
-    async fn test_hidden_directory_exclusion() {
+    async fn test_discovery_reconciles_moved_and_removed_directories() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let base = dir.path();
+        for name in ["Website", "Blog", "Shop"] {
+            fs::create_dir_all(base.join(name)).unwrap();
+        }
+        git2::Repository::init(base.join("Website"))
+            .unwrap()
+            .remote("origin", "https://example.com/acme/website.git")
+            .unwrap();
+        fs::write(
+            base.join("Shop").join(MANIFEST_FILE),
+            "[project]\nname = \"Shop\"\n",
+        )
+        .unwrap();
+
         let service = setup_service().await;
-        let exclude_patterns = vec![".*".to_string()];
+        let mut options = DiscoveryOptions {
+            roots: vec![base.to_path_buf()],
+            ..DiscoveryOptions::default()
+        };
+        let result = service.discover_clients(&options).await.unwrap();
+        assert_eq!(result.created_projects.len(), 3);
+
+        fs::rename(base.join("Website"), base.join("Website-2024")).unwrap();
+        fs::rename(base.join("Shop"), base.join("Shop-v2")).unwrap();
+        fs::remove_dir(base.join("Blog")).unwrap();
+
+        let moves = |result: &DiscoveryResult| {
+            result
+                .moved_projects
+                .iter()
+                .map(|moved| (moved.project.name.clone(), moved.evidence))
+                .collect::<Vec<_>>()
+        };
+        let names = |projects: &[Project]| {
+            projects
+                .iter()
+                .map(|project| project.name.clone())
+                .collect::<Vec<_>>()
+        };
 
-        // Test that hidden directories are excluded
-        assert!(service.should_exclude(".claude", &exclude_patterns));
-        assert!(service.should_exclude(".cursor", &exclude_patterns));
-        assert!(service.should_exclude(".vscode", &exclude_patterns));
-        assert!(service.should_exclude(".git", &exclude_patterns));
-        assert!(service.should_exclude(".github", &exclude_patterns));
-        assert!(service.should_exclude(".idea", &exclude_patterns));
+        options.dry_run = true;
+        options.prune = true;
+        let result = service.discover_clients(&options).await.unwrap();
+        assert_eq!(
+            moves(&result),
+            vec![
+                ("Shop".to_string(), MoveEvidence::Manifest),
+                ("[CLIENT] Website".to_string(), MoveEvidence::GitRemote),
+            ]
+        );
+        assert_eq!(names(&result.orphaned_projects), vec!["[CLIENT] Blog"]);
+        assert!(result.archived_projects.is_empty());
+
+        options.dry_run = false;
+        let result = service.discover_clients(&options).await.unwrap();
+        assert_eq!(moves(&result).len(), 2);
+        assert!(result.created_projects.is_empty());
+        assert_eq!(names(&result.archived_projects), vec!["[CLIENT] Blog"]);
+
+        let website = service
+            .project_service
+            .get_project("[CLIENT] Website")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            website.directory_path,
+            Some(base.join("Website-2024").to_string_lossy().to_string())
+        );
+        assert_eq!(
+            names(&service.list_client_projects().await.unwrap()),
+            vec!["Shop", "[CLIENT] Website"]
+        );
 
-        // Test that non-hidden directories are not excluded
-        assert!(!service.should_exclude("ValidClient", &exclude_patterns));
-        assert!(!service.should_exclude("MyProject", &exclude_patterns));
+        // An archived project comes back when its directory does
+        fs::create_dir(base.join("Blog")).unwrap();
+        let result = service.discover_clients(&options).await.unwrap();
+        assert_eq!(names(&result.updated_projects), vec!["[CLIENT] Blog"]);
+        assert!(result.orphaned_projects.is_empty());
+        assert_eq!(service.list_client_projects().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_manifest_skips_directory() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for name in ["Broken", "Website"] {
+            fs::create_dir(dir.path().join(name)).unwrap();
+        }
+        fs::write(
+            dir.path().join("Broken").join(MANIFEST_FILE),
+            "[project\nname = \"Broken\"\n",
+        )
+        .unwrap();
+
+        let service = setup_service().await;
+        let options = DiscoveryOptions {
+            roots: vec![dir.path().to_path_buf()],
+            ..DiscoveryOptions::default()
+        };
+        let result = service.discover_clients(&options).await.unwrap();
+        assert_eq!(result.created_projects.len(), 1);
+        assert_eq!(result.created_projects[0].name, "[CLIENT] Website");
+        assert_eq!(result.skipped_directories.len(), 1);
+        assert!(result.skipped_directories[0].starts_with("Broken (invalid manifest: "));
+    }
+
+    #[tokio::test]
+    async fn test_prune_continues_past_failed_archive() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let base = dir.path().join("clients");
+        for name in ["Blog", "Docs"] {
+            fs::create_dir_all(base.join(name)).unwrap();
+        }
+        let db_path = dir.path().join("timespan.db");
+        let service =
+            ClientDiscoveryService::new(Arc::new(SqliteRepository::new(&db_path).unwrap()));
+        let mut options = DiscoveryOptions {
+            roots: vec![base.clone()],
+            ..DiscoveryOptions::default()
+        };
+        service.discover_clients(&options).await.unwrap();
+
+        fs::remove_dir(base.join("Blog")).unwrap();
+        fs::remove_dir(base.join("Docs")).unwrap();
+        rusqlite::Connection::open(&db_path)
+            .unwrap()
+            .execute(
+                "CREATE TRIGGER refuse_blog BEFORE UPDATE ON projects \
+                 WHEN OLD.name = '[CLIENT] Blog' BEGIN SELECT RAISE(ABORT, 'locked'); END",
+                [],
+            )
+            .unwrap();
+
+        options.prune = true;
+        let result = service.discover_clients(&options).await.unwrap();
+        let archived: Vec<&str> = result
+            .archived_projects
+            .iter()
+            .map(|project| project.name.as_str())
+            .collect();
+        assert_eq!(archived, vec!["[CLIENT] Docs"]);
+        assert_eq!(result.errors.len(), 1);
+        assert!(result.errors[0].starts_with("Error archiving [CLIENT] Blog: "));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unreadable_directory_is_skipped() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        for name in ["Locked/Site", "Open/Site"] {
+            fs::create_dir_all(dir.path().join(name)).unwrap();
+        }
+        let locked = dir.path().join("Locked");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        // Permissions don't stop root, so only check the skip when they apply
+        let unreadable = fs::read_dir(&locked).is_err();
+
+        let service = setup_service().await;
+        let options = DiscoveryOptions {
+            roots: vec![dir.path().to_path_buf()],
+            depth: 2,
+            ..DiscoveryOptions::default()
+        };
+        let result = service.discover_clients(&options).await;
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let result = result.unwrap();
+        assert!(result
+            .created_projects
+            .iter()
+            .any(|project| project.name.contains("Open/Site")));
+        if unreadable {
+            assert_eq!(result.skipped_directories.len(), 1);
+            assert!(result.skipped_directories[0].starts_with("Locked (unreadable: "));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_discover_configured_roots() {
+        let work = tempfile::TempDir::new().unwrap();
+        let agency = tempfile::TempDir::new().unwrap();
+        fs::create_dir(work.path().join("Acme")).unwrap();
+        fs::create_dir(agency.path().join("Globex-Release")).unwrap();
+
+        let config = UserConfig::parse(&format!(
+            "[discovery]\nroots = [{:?}, {:?}]\nprefix = \"\"\n\n\
+             [discovery.descriptions]\n\"*Release*\" = \"Release work for {{name}}\"\n",
+            work.path(),
+            agency.path()
+        ))
+        .unwrap();
+        let repo = Arc::new(SqliteRepository::in_memory().unwrap());
+        let service = ClientDiscoveryService::with_config(repo, config.discovery);
+
+        let options = DiscoveryOptions::from_config(service.config());
+        let result = service.discover_clients(&options).await.unwrap();
+        let created = result
+            .created_projects
+            .iter()
+            .map(|project| (project.name.as_str(), project.description.as_deref()))
+            .collect::<Vec<_>>();
+        let acme_description = format!(
+            "Client project Location: {}",
+            work.path().join("Acme").display()
+        );
+        assert_eq!(
+            created,
+            vec![
+                ("Acme", Some(acme_description.as_str())),
+                ("Globex-Release", Some("Release work for Globex-Release")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_discover_skips_missing_roots_and_repeated_names() {
+        let work = tempfile::TempDir::new().unwrap();
+        let agency = tempfile::TempDir::new().unwrap();
+        for root in [work.path(), agency.path()] {
+            fs::create_dir(root.join("Acme")).unwrap();
+        }
+        fs::create_dir(agency.path().join("Globex")).unwrap();
+        let missing = work.path().join("missing");
+
+        let service = setup_service().await;
+        let mut options = DiscoveryOptions {
+            roots: vec![
+                work.path().to_path_buf(),
+                missing.clone(),
+                agency.path().to_path_buf(),
+            ],
+            ..DiscoveryOptions::default()
+        };
+        let result = service.discover_clients(&options).await.unwrap();
+        let created = result
+            .created_projects
+            .iter()
+            .map(|project| (project.name.as_str(), project.directory_path.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            created,
+            vec![
+                (
+                    "[CLIENT] Acme",
+                    Some(work.path().join("Acme").to_string_lossy().to_string())
+                ),
+                (
+                    "[CLIENT] Globex",
+                    Some(agency.path().join("Globex").to_string_lossy().to_string())
+                ),
+            ]
+        );
+        assert_eq!(
+            result.skipped_directories,
+            vec![
+                format!("{} (directory does not exist)", missing.display()),
+                format!(
+                    "{} (same name as {})",
+                    agency.path().join("Acme").display(),
+                    work.path().join("Acme").display()
+                ),
+            ]
+        );
+
+        // Nothing to scan at all is an error
+        options.roots = vec![missing];
+        assert!(matches!(
+            service.discover_clients(&options).await,
+            Err(TimeSpanError::InvalidInput(_))
+        ));
     }
 
     #[test]
     fn test_default_options() {
         let options = DiscoveryOptions::default();
-        assert_eq!(
-            options.base_path,
-            PathBuf::from("/Users/user/workspace/Clients")
-        );
+        assert!(options.roots.is_empty());
         assert!(options.exclude_patterns.contains(&".DS_Store".to_string()));
         assert_eq!(options.project_prefix, Some("[CLIENT]".to_string()));
     }
@@ -0,0 +1,513 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{Result, TimeSpanError};
+
+const BLOCK_START: &str = "# >>> timespan >>>";
+const BLOCK_END: &str = "# <<< timespan <<<";
+/// Suffix for an existing non-shell hook that we moved aside and now call
+const CHAINED_SUFFIX: &str = ".pre-timespan";
+
+/// Which hook to install
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookKind {
+    /// Records each commit via `timespan git record-commit`
+    PostCommit,
+    /// Moves the running timer to the new branch via `timespan git sync-branch`
+    PostCheckout,
+}
+
+impl HookKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookKind::PostCommit => "post-commit",
+            HookKind::PostCheckout => "post-checkout",
+        }
+    }
+
+    /// The shell snippet that calls TimeSpan; failures are ignored so the
+    /// git command itself always succeeds
+    fn command(&self) -> &'static str {
+        match self {
+            HookKind::PostCommit => {
+                "if command -v timespan >/dev/null 2>&1; then\n\
+                 \x20   timespan git record-commit --repo \"$(git rev-parse --show-toplevel)\" --commit \"$(git rev-parse HEAD)\" >/dev/null 2>&1 || true\n\
+                 fi\n"
+            }
+            // The third argument is 1 for branch checkouts and 0 for file checkouts
+            HookKind::PostCheckout => {
+                "if [ \"$3\" = \"1\" ] && command -v timespan >/dev/null 2>&1; then\n\
+                 \x20   timespan git sync-branch --repo \"$(git rev-parse --show-toplevel)\" >/dev/null 2>&1 || true\n\
+                 fi\n"
+            }
+        }
+    }
+}
+
+/// Where a hook should be installed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookTarget {
+    /// `<repo>/.git/hooks` (or the repository's `core.hooksPath`)
+    Repository(PathBuf),
+    /// The directory named by the global `core.hooksPath`, which is set to a
+    /// TimeSpan-managed directory when it is not configured yet
+    Global,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookAction {
+    /// No hook existed; a new one was written
+    Created,
+    /// Our block was appended to an existing shell hook
+    Appended,
+    /// An existing hook we cannot append to was moved aside and is called first
+    Chained(PathBuf),
+    /// Our block was already present and has been refreshed
+    Updated,
+}
+
+#[derive(Debug, Clone)]
+pub struct HookInstallation {
+    pub hook_path: PathBuf,
+    pub action: HookAction,
+}
+
+/// Installs and removes the `post-commit` hook that records commits via
+/// `timespan git record-commit`, and the `post-checkout` hook that follows
+/// branch switches. The hooks never fail the git command.
+pub struct GitHookService {
+    global_hooks_dir: PathBuf,
+}
+
+impl Default for GitHookService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GitHookService {
+    pub fn new() -> Self {
+        let global_hooks_dir = dirs::data_dir()
+            .map(|dir| dir.join("timespan"))
+            .unwrap_or_else(|| {
+                std::env::var("HOME")
+                    .map(|home| PathBuf::from(home).join(".timespan"))
+                    .unwrap_or_else(|_| PathBuf::from(".timespan"))
+            })
+            .join("hooks");
+
+        Self { global_hooks_dir }
+    }
+
+    pub fn install(&self, target: &HookTarget, kind: HookKind) -> Result<HookInstallation> {
+        let hooks_dir = match target {
+            HookTarget::Repository(path) => Self::repository_hooks_dir(path)?,
+            HookTarget::Global => match Self::global_hooks_path()? {
+                Some(dir) => dir,
+                None => {
+                    Self::set_global_hooks_path(Some(&self.global_hooks_dir))?;
+                    self.global_hooks_dir.clone()
+                }
+            },
+        };
+        fs::create_dir_all(&hooks_dir)?;
+
+        let hook_path = hooks_dir.join(kind.name());
+        let chained_path = hooks_dir.join(format!("{}{}", kind.name(), CHAINED_SUFFIX));
+        let global = matches!(target, HookTarget::Global) && hooks_dir == self.global_hooks_dir;
+
+        let action = if !hook_path.exists() {
+            fs::write(
+                &hook_path,
+                format!("#!/bin/sh\n{}", hook_block(kind, global, false)),
+            )?;
+            HookAction::Created
+        } else {
+            let existing = fs::read_to_string(&hook_path)?;
+            if existing.contains(BLOCK_START) {
+                let chained = chained_path.exists();
+                let mut content = remove_block(&existing);
+                content.push_str(&hook_block(kind, global, chained));
+                fs::write(&hook_path, content)?;
+                HookAction::Updated
+            } else if is_shell_script(&existing) && falls_through(&existing) {
+                let mut content = existing;
+                if !content.ends_with('\n') {
+                    content.push('\n');
+                }
+                content.push_str(&hook_block(kind, global, false));
+                fs::write(&hook_path, content)?;
+                HookAction::Appended
+            } else {
+                fs::rename(&hook_path, &chained_path)?;
+                fs::write(
+                    &hook_path,
+                    format!("#!/bin/sh\n{}", hook_block(kind, global, true)),
+                )?;
+                HookAction::Chained(chained_path)
+            }
+        };
+
+        make_executable(&hook_path)?;
+
+        Ok(HookInstallation { hook_path, action })
+    }
+
+    /// Remove our hook block, restoring any hook we chained. Returns the hook
+    /// path when something was removed.
+    pub fn uninstall(&self, target: &HookTarget, kind: HookKind) -> Result<Option<PathBuf>> {
+        let hooks_dir = match target {
+            HookTarget::Repository(path) => Self::repository_hooks_dir(path)?,
+            HookTarget::Global => match Self::global_hooks_path()? {
+                Some(dir) => dir,
+                None => return Ok(None),
+            },
+        };
+
+        let hook_path = hooks_dir.join(kind.name());
+        let chained_path = hooks_dir.join(format!("{}{}", kind.name(), CHAINED_SUFFIX));
+
+        let existing = match fs::read_to_string(&hook_path) {
+            Ok(content) => content,
+            Err(_) => return Ok(None),
+        };
+        if !existing.contains(BLOCK_START) {
+            return Ok(None);
+        }
+
+        let remaining = remove_block(&existing);
+        if remaining.lines().all(|line| {
+            let line = line.trim();
+            line.is_empty() || line.starts_with("#!")
+        }) {
+            fs::remove_file(&hook_path)?;
+            if chained_path.exists() {
+                fs::rename(&chained_path, &hook_path)?;
+            }
+        } else {
+            fs::write(&hook_path, remaining)?;
+        }
+
+        // Our global hooks directory stays configured while any hook is left
+        let hooks_left = [HookKind::PostCommit, HookKind::PostCheckout]
+            .iter()
+            .any(|kind| hooks_dir.join(kind.name()).exists());
+        if matches!(target, HookTarget::Global) && hooks_dir == self.global_hooks_dir && !hooks_left
+        {
+            Self::set_global_hooks_path(None)?;
+        }
+
+        Ok(Some(hook_path))
+    }
+
+    pub fn is_installed(&self, target: &HookTarget, kind: HookKind) -> Result<bool> {
+        let hooks_dir = match target {
+            HookTarget::Repository(path) => Self::repository_hooks_dir(path)?,
+            HookTarget::Global => match Self::global_hooks_path()? {
+                Some(dir) => dir,
+                None => return Ok(false),
+            },
+        };
+
+        Ok(fs::read_to_string(hooks_dir.join(kind.name()))
+            .map(|content| content.contains(BLOCK_START))
+            .unwrap_or(false))
+    }
+
+    fn repository_hooks_dir(repo_path: &Path) -> Result<PathBuf> {
+        let repo = git2::Repository::open(repo_path).map_err(|e| {
+            TimeSpanError::InvalidInput(format!(
+                "Not a git repository: {} ({})",
+                repo_path.display(),
+                e.message()
+            ))
+        })?;
+
+        let configured = repo
+            .config()
+            .ok()
+            .and_then(|config| config.get_path("core.hooksPath").ok());
+
+        Ok(match configured {
+            Some(path) if path.is_absolute() => path,
+            Some(path) => repo.workdir().unwrap_or_else(|| repo.path()).join(path),
+            None => repo.path().join("hooks"),
+        })
+    }
+
+    fn global_hooks_path() -> Result<Option<PathBuf>> {
+        let config = git2::Config::open_default().map_err(git_config_error)?;
+        let global = config
+            .open_level(git2::ConfigLevel::Global)
+            .map_err(git_config_error);
+
+        Ok(match global {
+            Ok(global) => global.get_path("core.hooksPath").ok(),
+            Err(_) => None,
+        })
+    }
+
+    fn set_global_hooks_path(path: Option<&Path>) -> Result<()> {
+        let config_path = git2::Config::find_global().or_else(|_| {
+            dirs::home_dir()
+                .map(|home| home.join(".gitconfig"))
+                .ok_or_else(|| {
+                    TimeSpanError::InvalidInput(
+                        "Could not locate the global git config".to_string(),
+                    )
+                })
+        })?;
+        let mut config = git2::Config::open(&config_path).map_err(git_config_error)?;
+
+        match path {
+            Some(path) => config
+                .set_str("core.hooksPath", &path.to_string_lossy())
+                .map_err(git_config_error),
+            None => match config.remove("core.hooksPath") {
+                Ok(()) => Ok(()),
+                Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(()),
+                Err(e) => Err(git_config_error(e)),
+            },
+        }
+    }
+}
+
+fn git_config_error(e: git2::Error) -> TimeSpanError {
+    TimeSpanError::Io(std::io::Error::other(format!(
+        "Failed to update git config: {}",
+        e.message()
+    )))
+}
+
+/// The marker-delimited block we own inside a hook script. A global hooks
+/// directory we manage replaces per-repository hooks, so its hook also runs
+/// the repository's own one.
+fn hook_block(kind: HookKind, global: bool, chained: bool) -> String {
+    let mut block = format!(
+        "{}\n# Installed by `timespan git setup`; remove with `timespan git setup --uninstall`\n",
+        BLOCK_START
+    );
+
+    if chained {
+        block.push_str(&format!(
+            "\"$(dirname \"$0\")/{}{}\" \"$@\"\n",
+            kind.name(),
+            CHAINED_SUFFIX
+        ));
+    }
+    if global {
+        block.push_str(&format!(
+            "repo_hook=\"$(git rev-parse --git-dir)/hooks/{}\"\n\
+             if [ -x \"$repo_hook\" ]; then \"$repo_hook\" \"$@\"; fi\n",
+            kind.name()
+        ));
+    }
+
+    block.push_str(kind.command());
+    block.push_str(BLOCK_END);
+    block.push('\n');
+    block
+}
+
+fn remove_block(content: &str) -> String {
+    let mut result = String::new();
+    let mut inside = false;
+
+    for line in content.lines() {
+        if line.trim() == BLOCK_START {
+            inside = true;
+        } else if line.trim() == BLOCK_END {
+            inside = false;
+        } else if !inside {
+            result.push_str(line);
+            result.push('\n');
+        }
+    }
+
+    result
+}
+
+fn is_shell_script(content: &str) -> bool {
+    let shebang = content.lines().next().unwrap_or("");
+    shebang.starts_with("#!")
+        && ["/sh", "/bash", "/zsh", "env sh", "env bash", "env zsh"]
+            .iter()
+            .any(|shell| shebang.contains(shell))
+}
+
+/// Whether a block appended to this script is reached. Scripts that may end
+/// early through `exit`, `exec` or `set -e` get chained instead.
+fn falls_through(content: &str) -> bool {
+    let shebang = content.lines().next().unwrap_or("");
+    if shebang.split_whitespace().skip(1).any(enables_errexit) {
+        return false;
+    }
+
+    content.lines().skip(1).all(|line| {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("exit") | Some("exec") => false,
+            Some("set") => !words.any(|word| word == "errexit" || enables_errexit(word)),
+            _ => true,
+        }
+    })
+}
+
+fn enables_errexit(flag: &str) -> bool {
+    flag.starts_with('-') && !flag.starts_with("--") && flag.contains('e')
+}
+
+fn make_executable(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut permissions = fs::metadata(path)?.permissions();
+        permissions.set_mode(0o755);
+        fs::set_permissions(path, permissions)?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn init_repo() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        git2::Repository::init(dir.path()).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_install_and_uninstall_fresh_hook() {
+        let repo = init_repo();
+        let service = GitHookService::new();
+        let target = HookTarget::Repository(repo.path().to_path_buf());
+
+        let installation = service.install(&target, HookKind::PostCommit).unwrap();
+        assert_eq!(installation.action, HookAction::Created);
+        let content = fs::read_to_string(&installation.hook_path).unwrap();
+        assert!(content.starts_with("#!/bin/sh\n"));
+        assert!(content.contains("timespan git record-commit"));
+        assert!(content.contains("|| true"));
+        assert!(service.is_installed(&target, HookKind::PostCommit).unwrap());
+
+        // Installing twice refreshes the block instead of duplicating it
+        let again = service.install(&target, HookKind::PostCommit).unwrap();
+        assert_eq!(again.action, HookAction::Updated);
+        let content = fs::read_to_string(&again.hook_path).unwrap();
+        assert_eq!(content.matches(BLOCK_START).count(), 1);
+
+        assert!(service
+            .uninstall(&target, HookKind::PostCommit)
+            .unwrap()
+            .is_some());
+        assert!(!installation.hook_path.exists());
+        assert!(!service.is_installed(&target, HookKind::PostCommit).unwrap());
+    }
+
+    #[test]
+    fn test_post_checkout_hook_is_separate() {
+        let repo = init_repo();
+        let service = GitHookService::new();
+        let target = HookTarget::Repository(repo.path().to_path_buf());
+
+        service.install(&target, HookKind::PostCommit).unwrap();
+        let installation = service.install(&target, HookKind::PostCheckout).unwrap();
+        assert_eq!(installation.action, HookAction::Created);
+        assert!(installation.hook_path.ends_with("post-checkout"));
+        let content = fs::read_to_string(&installation.hook_path).unwrap();
+        assert!(content.contains("timespan git sync-branch"));
+        assert!(content.contains("[ \"$3\" = \"1\" ]"));
+
+        service.uninstall(&target, HookKind::PostCheckout).unwrap();
+        assert!(!service
+            .is_installed(&target, HookKind::PostCheckout)
+            .unwrap());
+        assert!(service.is_installed(&target, HookKind::PostCommit).unwrap());
+    }
+
+    #[test]
+    fn test_chains_existing_hooks() {
+        let repo = init_repo();
+        let service = GitHookService::new();
+        let target = HookTarget::Repository(repo.path().to_path_buf());
+        let hooks_dir = repo.path().join(".git").join("hooks");
+        fs::create_dir_all(&hooks_dir).unwrap();
+        let hook_path = hooks_dir.join(HookKind::PostCommit.name());
+
+        // Shell hooks keep their content and get our block appended
+        fs::write(&hook_path, "#!/bin/bash\necho existing\n").unwrap();
+        assert_eq!(
+            service
+                .install(&target, HookKind::PostCommit)
+                .unwrap()
+                .action,
+            HookAction::Appended
+        );
+        service.uninstall(&target, HookKind::PostCommit).unwrap();
+        assert_eq!(
+            fs::read_to_string(&hook_path).unwrap(),
+            "#!/bin/bash\necho existing\n"
+        );
+
+        // Other interpreters are moved aside, called first, and restored
+        fs::write(&hook_path, "#!/usr/bin/env python3\nprint('hi')\n").unwrap();
+        let installation = service.install(&target, HookKind::PostCommit).unwrap();
+        assert!(matches!(installation.action, HookAction::Chained(_)));
+        assert!(fs::read_to_string(&hook_path)
+            .unwrap()
+            .contains("post-commit.pre-timespan"));
+
+        service.uninstall(&target, HookKind::PostCommit).unwrap();
+        assert_eq!(
+            fs::read_to_string(&hook_path).unwrap(),
+            "#!/usr/bin/env python3\nprint('hi')\n"
+        );
+        assert!(!hooks_dir.join("post-commit.pre-timespan").exists());
+    }
+
+    #[test]
+    fn test_chains_shell_hooks_that_end_early() {
+        let repo = init_repo();
+        let service = GitHookService::new();
+        let target = HookTarget::Repository(repo.path().to_path_buf());
+        let hooks_dir = repo.path().join(".git").join("hooks");
+        fs::create_dir_all(&hooks_dir).unwrap();
+        let hook_path = hooks_dir.join(HookKind::PostCommit.name());
+
+        for existing in [
+            "#!/bin/sh
+echo existing
+exit 0
+",
+            "#!/bin/sh
+exec other-hook \"$@\"
+",
+            "#!/bin/bash
+set -euo pipefail
+echo existing
+",
+            "#!/bin/sh -e
+echo existing
+",
+        ] {
+            fs::write(&hook_path, existing).unwrap();
+            let installation = service.install(&target, HookKind::PostCommit).unwrap();
+            assert!(
+                matches!(installation.action, HookAction::Chained(_)),
+                "{:?}",
+                existing
+            );
+            let content = fs::read_to_string(&hook_path).unwrap();
+            assert!(content.contains("post-commit.pre-timespan"));
+            assert!(content.contains("timespan git record-commit"));
+
+            service.uninstall(&target, HookKind::PostCommit).unwrap();
+            assert_eq!(fs::read_to_string(&hook_path).unwrap(), existing);
+        }
+    }
+}
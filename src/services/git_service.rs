@@ -1,38 +1,267 @@
 use chrono::{DateTime, Duration, TimeZone, Utc};
 use git2::{Commit, Repository};
 use std::collections::HashMap;
-use std::path::Path;
-
-use crate::models::{CommitAnalysis, CommitType, GitCommit, GitTimeEntry, Project};
+use std::path::{Path, PathBuf};
+
+use crate::config::{BranchConfig, EstimationSettings, GitConfig, HistoryConfig};
+use crate::manifest::ProjectManifest;
+use crate::models::{
+    file_extension, ActivityPulse, CommitActivityReport, CommitAnalysis, CommitType, DateRange,
+    EntryReference, EstimationStrategy, GitCalibration, GitCommit, GitTimeEntry, MergePolicy,
+    Project, ReferenceSource, RepositoryActivity, TimeEntry, TimeSource,
+};
 use crate::repository::Repository as TimeSpanRepository;
 use crate::{Result, TimeSpanError};
 
 pub struct GitService {
     repository: std::sync::Arc<dyn TimeSpanRepository>,
+    config: GitConfig,
+}
+
+/// Tuning for the session estimator
+#[derive(Debug, Clone, Copy)]
+pub struct SessionSettings {
+    /// Commits further apart than this start a new session
+    pub max_gap: Duration,
+    /// Time credited to the first commit of a session
+    pub lead_in: Duration,
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        Self {
+            max_gap: Duration::hours(2),
+            lead_in: Duration::minutes(30),
+        }
+    }
+}
+
+/// Session-based estimate for one commit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionEstimate {
+    pub duration: Duration,
+    pub confidence: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GitImportOptions {
+    pub strategy: EstimationStrategy,
+    pub session: SessionSettings,
+    pub dry_run: bool,
+}
+
+impl Default for GitImportOptions {
+    fn default() -> Self {
+        Self {
+            strategy: EstimationStrategy::Heuristic,
+            session: SessionSettings::default(),
+            dry_run: false,
+        }
+    }
+}
+
+/// Whose commits to read from a repository
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum AuthorFilter {
+    /// The repository's `user.email` and its identity aliases
+    #[default]
+    CurrentUser,
+    /// An email (with its identity aliases) or an author name
+    Author(String),
+    All,
+}
+
+/// Resolved author filter: a commit matches on any of the emails or names
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorMatcher {
+    /// Lowercased; the first one is the main email
+    pub emails: Vec<String>,
+    pub names: Vec<String>,
+}
+
+impl AuthorMatcher {
+    pub fn matches(&self, commit: &GitCommit) -> bool {
+        self.emails
+            .iter()
+            .any(|email| commit.author_email.eq_ignore_ascii_case(email))
+            || self
+                .names
+                .iter()
+                .any(|name| commit.author.eq_ignore_ascii_case(name))
+    }
+
+    /// Short description for output, e.g. `jane@work.example (+1 alias)`
+    pub fn describe(&self) -> String {
+        let main = self
+            .emails
+            .first()
+            .or_else(|| self.names.first())
+            .cloned()
+            .unwrap_or_default();
+        match self.emails.len().saturating_sub(1) {
+            0 => main,
+            1 => format!("{} (+1 alias)", main),
+            aliases => format!("{} (+{} aliases)", main, aliases),
+        }
+    }
+}
+
+/// Which commits `get_commits` reads
+#[derive(Debug, Clone, Default)]
+pub struct CommitQuery {
+    pub since: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+    /// `None` reads every author
+    pub authors: Option<AuthorMatcher>,
+    /// Refs, revisions or globs to walk; `None` uses `[history] refs` from
+    /// the config (all local branches by default)
+    pub refs: Option<Vec<String>>,
+    /// `None` uses `[history] merges` from the config
+    pub merges: Option<MergePolicy>,
+}
+
+/// Directories `find_repositories` never descends into
+const SCAN_SKIPPED_DIRS: [&str; 5] = ["node_modules", "target", "vendor", "build", "dist"];
+
+/// How far past `since` a time-sorted walk continues before stopping
+const SINCE_SLACK_HOURS: i64 = 24;
+
+/// Commits of a type (or touching an extension) needed before calibration
+/// fits a multiplier for it
+const MIN_CALIBRATION_SAMPLES: usize = 2;
+/// How long after a tracked interval ends a commit still counts towards it
+const CALIBRATION_GRACE_MINUTES: i64 = 10;
+
+/// Outcome of importing a batch of commits
+#[derive(Debug, Default)]
+pub struct GitImportResult {
+    /// Commits that were (or, for a dry run, would be) imported
+    pub imported: Vec<GitTimeEntry>,
+    /// Hashes of commits that had already been imported
+    pub skipped: Vec<String>,
+}
+
+/// Notes ref that `annotate_commits` writes to
+pub const NOTES_REF: &str = "refs/notes/timespan";
+
+/// Time attributed to one commit
+#[derive(Debug, Clone)]
+pub struct CommitTime {
+    pub commit: GitCommit,
+    pub duration: Duration,
+    pub source: TimeSource,
+    pub project_name: Option<String>,
+}
+
+impl CommitTime {
+    /// Text of the commit's note under `NOTES_REF`
+    pub fn note(&self) -> String {
+        let mut note = format!(
+            "Time: {}h {}m ({})\n",
+            self.duration.num_hours(),
+            self.duration.num_minutes() % 60,
+            self.source.as_str()
+        );
+        if let Some(project) = &self.project_name {
+            note.push_str(&format!("Project: {}\n", project));
+        }
+        note
+    }
+}
+
+/// Outcome of annotating commits with notes
+#[derive(Debug, Default)]
+pub struct GitAnnotateResult {
+    /// Commits whose note was added or changed
+    pub written: Vec<CommitTime>,
+    /// Commits whose note was already up to date
+    pub unchanged: Vec<CommitTime>,
 }
 
 impl GitService {
     pub fn new(repository: std::sync::Arc<dyn TimeSpanRepository>) -> Self {
-        Self { repository }
+        Self::with_config(repository, GitConfig::default())
+    }
+
+    /// Use estimation settings from a loaded `git-config.toml`
+    pub fn with_config(
+        repository: std::sync::Arc<dyn TimeSpanRepository>,
+        config: GitConfig,
+    ) -> Self {
+        Self { repository, config }
+    }
+
+    /// Which refs are read and how merges are treated by default
+    pub fn history(&self) -> &HistoryConfig {
+        &self.config.history
+    }
+
+    /// Whether and how the running timer follows the checked-out branch
+    pub fn branches(&self) -> &BranchConfig {
+        &self.config.branches
+    }
+
+    /// Branch checked out in the repository containing `path`; `None`
+    /// outside a repository, on a detached HEAD or before the first commit
+    pub fn current_branch(&self, path: &Path) -> Option<String> {
+        let repo = Repository::discover(path).ok()?;
+        let head = repo.head().ok()?;
+        if !head.is_branch() {
+            return None;
+        }
+        head.shorthand().map(str::to_string)
+    }
+
+    /// Working directory of the repository containing `path`; `None` outside
+    /// a repository or in a bare one
+    pub fn repository_root(&self, path: &Path) -> Option<PathBuf> {
+        let repo = Repository::discover(path).ok()?;
+        repo.workdir().map(canonical_repo_path)
     }
 
-    /// Get commits from a local git repository
+    /// Work out whose commits an `AuthorFilter` selects in a repository.
+    /// `None` means every author, which is also the fallback when the
+    /// repository has no `user.email` configured.
+    pub fn resolve_authors(
+        &self,
+        repo_path: &Path,
+        filter: &AuthorFilter,
+    ) -> Result<Option<AuthorMatcher>> {
+        match filter {
+            AuthorFilter::All => Ok(None),
+            AuthorFilter::Author(author) if author.contains('@') => Ok(Some(AuthorMatcher {
+                emails: self.config.identity_emails(author),
+                names: Vec::new(),
+            })),
+            AuthorFilter::Author(author) => Ok(Some(AuthorMatcher {
+                emails: Vec::new(),
+                names: vec![author.trim().to_string()],
+            })),
+            AuthorFilter::CurrentUser => {
+                let git_repo = open_repository(repo_path)?;
+                let email = git_repo
+                    .config()
+                    .and_then(|config| config.get_string("user.email"))
+                    .ok()
+                    .filter(|email| !email.trim().is_empty());
+
+                Ok(email.map(|email| AuthorMatcher {
+                    emails: self.config.identity_emails(&email),
+                    names: Vec::new(),
+                }))
+            }
+        }
+    }
+
+    /// Get commits from a local git repository, newest first. Every ref in
+    /// the query is walked in commit-time order and each commit is returned
+    /// once, however many branches contain it.
     pub async fn get_commits(
         &self,
         repo_path: &Path,
-        since: Option<DateTime<Utc>>,
-        limit: Option<usize>,
+        query: &CommitQuery,
     ) -> Result<Vec<GitCommit>> {
-        let git_repo = Repository::open(repo_path).map_err(|e| {
-            TimeSpanError::Io(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                format!(
-                    "Failed to open git repository at {}: {}",
-                    repo_path.display(),
-                    e
-                ),
-            ))
-        })?;
+        let git_repo = open_repository(repo_path)?;
 
         let mut revwalk = git_repo.revwalk().map_err(|e| {
             TimeSpanError::Io(std::io::Error::other(format!(
@@ -40,18 +269,32 @@ impl GitService {
                 e
             )))
         })?;
-
-        revwalk.push_head().map_err(|e| {
-            TimeSpanError::Io(std::io::Error::other(format!("Failed to push HEAD: {}", e)))
+        revwalk.set_sorting(git2::Sort::TIME).map_err(|e| {
+            TimeSpanError::Io(std::io::Error::other(format!(
+                "Failed to sort revwalk: {}",
+                e
+            )))
         })?;
 
+        let refs = query.refs.as_ref().unwrap_or(&self.config.history.refs);
+        push_refs(&git_repo, &mut revwalk, refs)?;
+
+        let merges = query.merges.unwrap_or(self.config.history.merges);
+        // Commit times aren't strictly ordered (rebases, clock skew), so keep
+        // walking a little past `since` before giving up
+        let stop_before = query
+            .since
+            .map(|since| since - Duration::hours(SINCE_SLACK_HOURS));
+
+        let mut seen = std::collections::HashSet::new();
         let mut commits = Vec::new();
 
-        for (count, oid) in revwalk.enumerate() {
-            if let Some(max_count) = limit {
-                if count >= max_count {
-                    break;
-                }
+        for oid in revwalk {
+            if query
+                .limit
+                .is_some_and(|max_count| commits.len() >= max_count)
+            {
+                break;
             }
 
             let oid = oid.map_err(|e| {
@@ -60,6 +303,9 @@ impl GitService {
                     e
                 )))
             })?;
+            if !seen.insert(oid) {
+                continue;
+            }
 
             let commit_obj = git_repo.find_commit(oid).map_err(|e| {
                 TimeSpanError::Io(std::io::Error::other(format!(
@@ -68,36 +314,29 @@ impl GitService {
                 )))
             })?;
 
-            let commit_time = Utc
-                .timestamp_opt(commit_obj.time().seconds(), 0)
-                .single()
-                .ok_or_else(|| {
-                    TimeSpanError::InvalidDuration("Invalid commit timestamp".to_string())
-                })?;
+            if commit_obj.parent_count() > 1 && merges == MergePolicy::Skip {
+                continue;
+            }
 
-            // Filter by date if specified
-            if let Some(since_date) = since {
-                if commit_time < since_date {
-                    break;
-                }
+            let commit_time = commit_obj.time().seconds();
+            if stop_before.is_some_and(|stop| commit_time < stop.timestamp()) {
+                break;
+            }
+            if query
+                .since
+                .is_some_and(|since| commit_time < since.timestamp())
+            {
+                continue;
             }
 
-            let mut git_commit = GitCommit::new(
-                oid.to_string(),
-                commit_obj.message().unwrap_or("").to_string(),
-                commit_obj.author().name().unwrap_or("Unknown").to_string(),
-                commit_obj.author().email().unwrap_or("").to_string(),
-                commit_time,
-                repo_path.to_path_buf(),
-            );
+            let git_commit = self.commit_from_git(&git_repo, &commit_obj, repo_path)?;
 
-            // Get file changes for this commit
-            if let Ok((files, insertions, deletions)) =
-                self.get_commit_stats(&git_repo, &commit_obj)
+            if query
+                .authors
+                .as_ref()
+                .is_some_and(|authors| !authors.matches(&git_commit))
             {
-                git_commit.files_changed = files;
-                git_commit.insertions = insertions;
-                git_commit.deletions = deletions;
+                continue;
             }
 
             commits.push(git_commit);
@@ -106,15 +345,133 @@ impl GitService {
         Ok(commits)
     }
 
-    /// Get statistics for a specific commit (files changed, insertions, deletions)
+    /// Find every git repository under `root`, including nested
+    /// repositories, submodules and worktrees (whose `.git` is a file).
+    /// Hidden directories and dependency/build folders are not descended
+    /// into.
+    pub fn find_repositories(&self, root: &Path, max_depth: usize) -> Result<Vec<PathBuf>> {
+        if !root.is_dir() {
+            return Err(TimeSpanError::InvalidInput(format!(
+                "Not a directory: {}",
+                root.display()
+            )));
+        }
+
+        let mut repositories = Vec::new();
+        let mut walker = walkdir::WalkDir::new(root)
+            .max_depth(max_depth)
+            .sort_by_file_name()
+            .into_iter();
+
+        while let Some(entry) = walker.next() {
+            // Unreadable directories are skipped rather than failing the scan
+            let Ok(entry) = entry else {
+                continue;
+            };
+            if !entry.file_type().is_dir() {
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy();
+            if entry.depth() > 0 && (name.starts_with('.') || SCAN_SKIPPED_DIRS.contains(&&*name)) {
+                walker.skip_current_dir();
+                continue;
+            }
+
+            if entry.path().join(".git").exists() {
+                repositories.push(entry.path().to_path_buf());
+            }
+        }
+
+        Ok(repositories)
+    }
+
+    /// Your commits (by the repository's `user.email`) made between `start`
+    /// and `end`, oldest first
+    pub async fn commits_between(
+        &self,
+        repo_path: &Path,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<GitCommit>> {
+        // Commit times have whole-second precision
+        let start = DateTime::from_timestamp(start.timestamp(), 0).unwrap_or(start);
+        let query = CommitQuery {
+            since: Some(start),
+            authors: self.resolve_authors(repo_path, &AuthorFilter::CurrentUser)?,
+            ..Default::default()
+        };
+
+        let mut commits: Vec<GitCommit> = self
+            .get_commits(repo_path, &query)
+            .await?
+            .into_iter()
+            .filter(|commit| commit.timestamp >= start && commit.timestamp <= end)
+            .collect();
+        commits.sort_by_key(|commit| commit.timestamp);
+        Ok(commits)
+    }
+
+    /// Load a single commit by hash or any revision git understands
+    pub async fn get_commit(&self, repo_path: &Path, revision: &str) -> Result<GitCommit> {
+        let git_repo = open_repository(repo_path)?;
+
+        let commit_obj = git_repo
+            .revparse_single(revision)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|e| {
+                TimeSpanError::InvalidInput(format!("Unknown commit {}: {}", revision, e))
+            })?;
+
+        self.commit_from_git(&git_repo, &commit_obj, repo_path)
+    }
+
+    fn commit_from_git(
+        &self,
+        git_repo: &Repository,
+        commit_obj: &Commit,
+        repo_path: &Path,
+    ) -> Result<GitCommit> {
+        let commit_time = Utc
+            .timestamp_opt(commit_obj.time().seconds(), 0)
+            .single()
+            .ok_or_else(|| {
+                TimeSpanError::InvalidDuration("Invalid commit timestamp".to_string())
+            })?;
+
+        let mut git_commit = GitCommit::new(
+            commit_obj.id().to_string(),
+            commit_obj.message().unwrap_or("").to_string(),
+            commit_obj.author().name().unwrap_or("Unknown").to_string(),
+            commit_obj.author().email().unwrap_or("").to_string(),
+            commit_time,
+            repo_path.to_path_buf(),
+        );
+        git_commit.is_merge = commit_obj.parent_count() > 1;
+
+        // Get file changes for this commit
+        if let Ok((files, insertions, deletions)) = self.get_commit_stats(git_repo, commit_obj) {
+            git_commit.files_changed = files;
+            git_commit.insertions = insertions;
+            git_commit.deletions = deletions;
+        }
+
+        Ok(git_commit)
+    }
+
+    /// Get statistics for a specific commit (files changed, insertions,
+    /// deletions) against its first parent. Renamed files count once with
+    /// only their edited lines, binary files count as changed without
+    /// lines, and merges report nothing since their changes belong to the
+    /// merged commits.
     fn get_commit_stats(
         &self,
         repo: &Repository,
         commit: &Commit,
     ) -> std::result::Result<(Vec<String>, u32, u32), git2::Error> {
-        let mut files_changed = Vec::new();
-        let mut total_insertions = 0u32;
-        let mut total_deletions = 0u32;
+        if commit.parent_count() > 1 {
+            return Ok((Vec::new(), 0, 0));
+        }
 
         let tree = commit.tree()?;
         let parent_tree = if commit.parent_count() > 0 {
@@ -124,51 +481,75 @@ impl GitService {
         };
 
         let mut diff_options = git2::DiffOptions::new();
-        let diff =
+        let mut diff =
             repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut diff_options))?;
 
-        diff.foreach(
-            &mut |delta, _progress| {
-                if let Some(path) = delta.new_file().path() {
-                    files_changed.push(path.to_string_lossy().to_string());
-                }
-                true
-            },
-            None,
-            None,
-            Some(&mut |_delta, _hunk, line| {
-                match line.origin() {
-                    '+' => total_insertions += 1,
-                    '-' => total_deletions += 1,
-                    _ => {}
+        let mut find_options = git2::DiffFindOptions::new();
+        find_options.renames(true);
+        diff.find_similar(Some(&mut find_options))?;
+
+        let mut files_changed: Vec<String> = Vec::new();
+        for delta in diff.deltas() {
+            let path = delta.new_file().path().or_else(|| delta.old_file().path());
+            if let Some(path) = path {
+                let path = path.to_string_lossy().to_string();
+                if !files_changed.contains(&path) {
+                    files_changed.push(path);
                 }
-                true
-            }),
-        )?;
+            }
+        }
 
-        Ok((files_changed, total_insertions, total_deletions))
+        // Binary files have no hunks, so they add no lines here
+        let stats = diff.stats()?;
+
+        Ok((
+            files_changed,
+            stats.insertions() as u32,
+            stats.deletions() as u32,
+        ))
     }
 
     /// Analyze a commit and estimate time spent
     pub async fn analyze_commit(&self, commit: &GitCommit) -> Result<CommitAnalysis> {
+        let settings = self.config.settings_for(&commit.repository_path);
         let commit_type = commit.detect_commit_type();
-        let complexity_score = self.calculate_complexity_score(commit);
-        let file_type_weights = self.get_file_type_weights(&commit.files_changed);
-        let estimated_duration = self.estimate_commit_time(commit, &commit_type, complexity_score);
+        let complexity_score = self.calculate_complexity_score(commit, &settings);
+        let file_type_weights = self.get_file_type_weights(commit, &settings);
+        let calibration = self
+            .repository
+            .get_git_calibration(&canonical_repo_path(&commit.repository_path))
+            .await?;
+        // Merges only tie together work that was estimated on its own commits
+        let estimated_duration = if commit.is_merge {
+            Duration::zero()
+        } else {
+            self.estimate_commit_time(
+                commit,
+                &settings,
+                &commit_type,
+                complexity_score,
+                calibration.as_ref(),
+            )
+        };
 
         Ok(CommitAnalysis {
             commit: commit.clone(),
             complexity_score,
             file_type_weights,
             commit_type,
+            scope: commit.scope(),
             estimated_duration,
         })
     }
 
     /// Calculate complexity score based on changes
-    fn calculate_complexity_score(&self, commit: &GitCommit) -> f32 {
+    fn calculate_complexity_score(&self, commit: &GitCommit, settings: &EstimationSettings) -> f32 {
         let total_changes = commit.total_changes() as f32;
-        let file_count = commit.files_changed.len() as f32;
+        let file_count = commit
+            .files_changed
+            .iter()
+            .filter(|file| !settings.is_ignored(file))
+            .count() as f32;
 
         // Base complexity on lines changed and files touched
         let lines_score = (total_changes / 100.0).min(3.0); // Cap at 3.0 for very large commits
@@ -177,21 +558,16 @@ impl GitService {
         (lines_score + files_score) / 2.0
     }
 
-    /// Get weights for different file types
-    fn get_file_type_weights(&self, files: &[String]) -> HashMap<String, f32> {
+    /// Get configured weights for the changed files, summed per extension
+    fn get_file_type_weights(
+        &self,
+        commit: &GitCommit,
+        settings: &EstimationSettings,
+    ) -> HashMap<String, f32> {
         let mut weights = HashMap::new();
 
-        for file in files {
-            let weight = match Path::new(file).extension().and_then(|ext| ext.to_str()) {
-                Some("rs") => 1.5,                             // Rust files are complex
-                Some("py") => 1.3,                             // Python files
-                Some("js") | Some("ts") => 1.2,                // JavaScript/TypeScript
-                Some("java") | Some("cpp") | Some("c") => 1.4, // Compiled languages
-                Some("md") | Some("txt") => 0.5,               // Documentation
-                Some("json") | Some("toml") | Some("yaml") | Some("yml") => 0.3, // Config files
-                Some("html") | Some("css") => 0.7,             // Frontend markup/styling
-                _ => 1.0,                                      // Default weight
-            };
+        for file in &commit.files_changed {
+            let weight = settings.file_weight(file) as f32;
 
             let ext = Path::new(file)
                 .extension()
@@ -205,22 +581,22 @@ impl GitService {
         weights
     }
 
-    /// Estimate time spent on a commit
+    /// Estimate time spent on a commit, scaled by the repository's
+    /// calibration when there is one
     fn estimate_commit_time(
         &self,
         commit: &GitCommit,
+        settings: &EstimationSettings,
         commit_type: &CommitType,
         complexity_score: f32,
+        calibration: Option<&GitCalibration>,
     ) -> Duration {
-        let mut base_time = match commit_type {
-            CommitType::Feature => Duration::minutes(45), // Features take longer
-            CommitType::BugFix => Duration::minutes(60),  // Bug fixes can be tricky
-            CommitType::Refactor => Duration::minutes(30), // Refactoring
-            CommitType::Documentation => Duration::minutes(15), // Documentation is usually quick
-            CommitType::Test => Duration::minutes(25),    // Writing tests
-            CommitType::Chore => Duration::minutes(10),   // Chores are usually quick
-            CommitType::Other => Duration::minutes(20),   // Default
-        };
+        let mut base_time = Duration::minutes(settings.base_minutes(commit_type));
+
+        if let Some(calibration) = calibration {
+            let multiplier = calibration.commit_type_multiplier(commit_type);
+            base_time = Duration::seconds((base_time.num_seconds() as f32 * multiplier) as i64);
+        }
 
         // Adjust based on complexity
         let complexity_multiplier = 1.0 + (complexity_score * 0.5);
@@ -231,64 +607,84 @@ impl GitService {
         let changes_factor = (commit.total_changes() as f32 / 50.0).min(3.0); // Cap the multiplier
         base_time += Duration::minutes((changes_factor * 10.0) as i64);
 
-        // Apply file type weights
+        // Extra time per changed file, skipping ignored files
         let file_weight_bonus = commit
             .files_changed
             .iter()
-            .map(|file| {
-                match Path::new(file).extension().and_then(|ext| ext.to_str()) {
-                    Some("rs") => 5, // Extra time for Rust
-                    Some("js") | Some("ts") => 3,
-                    Some("md") => 1,
-                    _ => 0,
-                }
-            })
+            .filter(|file| !settings.is_ignored(file))
+            .map(|file| settings.file_minutes(file))
             .sum::<i64>();
 
         base_time += Duration::minutes(file_weight_bonus);
 
+        if let Some(calibration) = calibration {
+            let factor = calibration.file_type_factor(&commit.files_changed);
+            base_time = Duration::seconds((base_time.num_seconds() as f32 * factor) as i64);
+        }
+
         // Cap the maximum time per commit
-        base_time.min(Duration::hours(4))
+        base_time.min(settings.max_per_commit())
     }
 
-    /// Detect project from repository path
+    /// Detect project from repository path: an existing project named after
+    /// the repository, else the one a `.timespan.toml` declares, else the
+    /// directory name
     pub async fn detect_project(&self, repo_path: &Path) -> Result<Option<String>> {
-        // Try to find project based on directory name or remote URL
-        let dir_name = repo_path
+        if let Some((project, _)) = self.repository_project(repo_path).await? {
+            return Ok(Some(project.name));
+        }
+
+        // A .timespan.toml names the project explicitly
+        if let Some(manifest) = ProjectManifest::find(repo_path)? {
+            return Ok(Some(manifest.project_name()));
+        }
+
+        // Return directory name as fallback
+        Ok(repo_path
             .file_name()
             .and_then(|name| name.to_str())
-            .map(|name| name.to_string());
+            .map(|name| name.to_string()))
+    }
+
+    /// Existing unarchived project named after the git repository containing
+    /// `path`, by its directory name (optionally as a `[CLIENT]` project) or
+    /// the name in its `origin` remote URL. Also returns the repository root.
+    pub async fn repository_project(&self, path: &Path) -> Result<Option<(Project, PathBuf)>> {
+        let Ok(git_repo) = Repository::discover(path) else {
+            return Ok(None);
+        };
+        // Collecting the components drops the trailing slash git2 leaves
+        let Some(root) = git_repo
+            .workdir()
+            .map(|dir| dir.components().collect::<PathBuf>())
+        else {
+            return Ok(None);
+        };
 
         // First, try to match by directory name
-        if let Some(name) = &dir_name {
-            // Check if we already have a project with this name
-            if let Ok(Some(_)) = self.repository.get_project_by_name(name).await {
-                return Ok(Some(name.clone()));
-            }
+        let mut candidates = Vec::new();
+        if let Some(name) = root.file_name().and_then(|name| name.to_str()) {
+            candidates.push(name.to_string());
+            candidates.push(format!("[CLIENT] {}", name));
+        }
 
-            // Check for client project pattern
-            let client_name = format!("[CLIENT] {}", name);
-            if let Ok(Some(_)) = self.repository.get_project_by_name(&client_name).await {
-                return Ok(Some(client_name));
+        // Then by the repository name in the remote URL
+        if let Ok(remote) = git_repo.find_remote("origin") {
+            if let Some(repo_name) = remote
+                .url()
+                .and_then(|url| self.extract_repo_name_from_url(url))
+            {
+                candidates.push(repo_name);
             }
         }
 
-        // Try to get remote URL from git repo
-        if let Ok(git_repo) = Repository::open(repo_path) {
-            if let Ok(remote) = git_repo.find_remote("origin") {
-                if let Some(url) = remote.url() {
-                    // Extract repo name from URL
-                    if let Some(repo_name) = self.extract_repo_name_from_url(url) {
-                        if let Ok(Some(_)) = self.repository.get_project_by_name(&repo_name).await {
-                            return Ok(Some(repo_name));
-                        }
-                    }
-                }
+        for name in candidates {
+            let project = self.repository.get_project_by_name(&name).await?;
+            if let Some(project) = project.filter(|project| !project.is_archived()) {
+                return Ok(Some((project, root)));
             }
         }
-
-        // Return directory name as fallback
-        Ok(dir_name)
+        Ok(None)
     }
 
     /// Extract repository name from git URL
@@ -307,7 +703,7 @@ impl GitService {
         let confidence_score = self.calculate_confidence_score(analysis);
 
         let git_time_entry = GitTimeEntry::new(
-            analysis.commit.hash.clone(),
+            &analysis.commit,
             project.id,
             project.name.clone(),
             analysis.estimated_duration,
@@ -317,6 +713,511 @@ impl GitService {
         Ok(git_time_entry)
     }
 
+    /// Group each author's commits into work sessions. Within a session the
+    /// time since the author's previous commit counts as work; the first
+    /// commit of a session gets `lead_in`. Confidence is shared by every
+    /// commit of a session and grows with its length and regularity.
+    pub fn estimate_sessions(
+        &self,
+        commits: &[GitCommit],
+        settings: &SessionSettings,
+    ) -> HashMap<String, SessionEstimate> {
+        let mut estimates = HashMap::new();
+        let mut by_author: HashMap<String, Vec<&GitCommit>> = HashMap::new();
+        for commit in commits {
+            // Merges neither take time nor extend a session
+            if commit.is_merge {
+                estimates.insert(
+                    commit.hash.clone(),
+                    SessionEstimate {
+                        duration: Duration::zero(),
+                        confidence: 1.0,
+                    },
+                );
+                continue;
+            }
+
+            let author = if commit.author_email.is_empty() {
+                commit.author.to_lowercase()
+            } else {
+                commit.author_email.to_lowercase()
+            };
+            by_author.entry(author).or_default().push(commit);
+        }
+
+        for mut author_commits in by_author.into_values() {
+            author_commits.sort_by_key(|commit| commit.timestamp);
+
+            let mut sessions: Vec<Vec<(&GitCommit, Duration)>> = Vec::new();
+            let mut previous: Option<DateTime<Utc>> = None;
+            for commit in author_commits {
+                let gap = previous.map(|prev| commit.timestamp - prev);
+                match gap {
+                    Some(gap) if gap <= settings.max_gap => {
+                        sessions.last_mut().unwrap().push((commit, gap));
+                    }
+                    _ => sessions.push(vec![(commit, settings.lead_in)]),
+                }
+                previous = Some(commit.timestamp);
+            }
+
+            for session in sessions {
+                let confidence = session_confidence(&session[1..]);
+                for (commit, duration) in session {
+                    estimates.insert(
+                        commit.hash.clone(),
+                        SessionEstimate {
+                            duration,
+                            confidence,
+                        },
+                    );
+                }
+            }
+        }
+
+        estimates
+    }
+
+    /// Create a time entry for every commit that has not been imported yet.
+    /// Commits already recorded in `git_time_entries` are skipped, so
+    /// re-running an import never duplicates entries.
+    pub async fn import_commits(
+        &self,
+        commits: &[GitCommit],
+        project: &Project,
+        options: &GitImportOptions,
+    ) -> Result<GitImportResult> {
+        let mut result = GitImportResult::default();
+        let sessions = match options.strategy {
+            EstimationStrategy::Session => self.estimate_sessions(commits, &options.session),
+            EstimationStrategy::Heuristic => HashMap::new(),
+        };
+
+        for commit in commits {
+            if options.dry_run
+                && self
+                    .repository
+                    .get_git_time_entry_by_commit(&commit.hash)
+                    .await?
+                    .is_some()
+            {
+                result.skipped.push(commit.hash.clone());
+                continue;
+            }
+
+            let analysis = self.analyze_commit(commit).await?;
+            let mut git_time_entry = self.create_git_time_entry(&analysis, project).await?;
+
+            // Session time is the work leading up to the commit; heuristic
+            // estimates keep starting at the commit itself
+            let start_time = match sessions.get(&commit.hash) {
+                Some(estimate) => {
+                    git_time_entry.estimated_time = estimate.duration;
+                    git_time_entry.confidence_score = estimate.confidence;
+                    commit.timestamp - estimate.duration
+                }
+                None => commit.timestamp,
+            };
+
+            let mut time_entry = TimeEntry::new(
+                project.id,
+                project.name.clone(),
+                Some(format!(
+                    "Git: {}",
+                    commit.message.lines().next().unwrap_or("No message")
+                )),
+                start_time,
+            );
+            time_entry.stop(start_time + git_time_entry.estimated_time)?;
+            time_entry.add_tag("git-import".to_string());
+            time_entry.commit_hashes = vec![commit.hash.clone()];
+            time_entry.add_tag(format!(
+                "commit-{}",
+                commit.hash.chars().take(8).collect::<String>()
+            ));
+            git_time_entry.time_entry_id = Some(time_entry.id);
+
+            if !options.dry_run {
+                if !self
+                    .repository
+                    .import_git_time_entry(&time_entry, &git_time_entry)
+                    .await?
+                {
+                    result.skipped.push(commit.hash.clone());
+                    continue;
+                }
+
+                let references: Vec<EntryReference> = self
+                    .config
+                    .references
+                    .extract(&commit.message)
+                    .into_iter()
+                    .map(|reference| EntryReference {
+                        time_entry_id: time_entry.id,
+                        reference,
+                        source: ReferenceSource::Commit,
+                    })
+                    .collect();
+                self.repository.save_entry_references(&references).await?;
+            }
+
+            result.imported.push(git_time_entry);
+        }
+
+        Ok(result)
+    }
+
+    /// Fit per-repository multipliers from imported commits that happened
+    /// while a timer was running. Sets `actual_time` on the matched commits
+    /// and stores the calibration unless `dry_run` is set.
+    pub async fn calibrate(&self, repo_path: &Path, dry_run: bool) -> Result<GitCalibration> {
+        let repo_key = canonical_repo_path(repo_path);
+        let imported: Vec<GitTimeEntry> = self
+            .repository
+            .list_git_time_entries()
+            .await?
+            .into_iter()
+            .filter(|entry| canonical_repo_path(&entry.repository_path) == repo_key)
+            .collect();
+
+        let (Some(first), Some(last)) = (
+            imported.iter().map(|e| e.committed_at).min(),
+            imported.iter().map(|e| e.committed_at).max(),
+        ) else {
+            return Err(TimeSpanError::InvalidInput(format!(
+                "No imported commits for {}; run 'timespan git import' first",
+                repo_path.display()
+            )));
+        };
+
+        // Only hand-tracked time counts as ground truth
+        let imported_entry_ids: std::collections::HashSet<uuid::Uuid> =
+            imported.iter().filter_map(|e| e.time_entry_id).collect();
+        let tracked: Vec<TimeEntry> = self
+            .repository
+            .list_time_entries_by_date_range(first - Duration::days(1), last + Duration::days(1))
+            .await?
+            .into_iter()
+            .filter(|entry| {
+                entry.end_time.is_some()
+                    && !imported_entry_ids.contains(&entry.id)
+                    && !entry.tags.iter().any(|tag| tag == "git-import")
+            })
+            .collect();
+
+        let commit_times: Vec<(String, DateTime<Utc>)> = imported
+            .iter()
+            .map(|e| (e.commit_hash.clone(), e.committed_at))
+            .collect();
+        let actuals = attribute_tracked_time(
+            &commit_times,
+            &tracked,
+            Duration::minutes(CALIBRATION_GRACE_MINUTES),
+        );
+
+        let settings = self.config.settings_for(repo_path);
+        let mut samples = Vec::new();
+        for (hash, actual) in &actuals {
+            // Commits that were rewritten away can't be re-analyzed
+            if let Ok(commit) = self.get_commit(repo_path, hash).await {
+                let commit_type = commit.detect_commit_type();
+                let complexity = self.calculate_complexity_score(&commit, &settings);
+                samples.push((commit, commit_type, complexity, *actual));
+            }
+        }
+
+        if samples.is_empty() {
+            return Err(TimeSpanError::InvalidInput(
+                "No imported commits overlap tracked time entries".to_string(),
+            ));
+        }
+
+        let estimate_all = |calibration: Option<&GitCalibration>| -> Vec<Duration> {
+            samples
+                .iter()
+                .map(|(commit, commit_type, complexity, _)| {
+                    self.estimate_commit_time(
+                        commit,
+                        &settings,
+                        commit_type,
+                        *complexity,
+                        calibration,
+                    )
+                })
+                .collect()
+        };
+
+        let mut calibration = GitCalibration {
+            repository_path: repo_key,
+            commit_type_multipliers: HashMap::new(),
+            file_type_multipliers: HashMap::new(),
+            sample_count: samples.len(),
+            mean_error_before: Duration::zero(),
+            mean_error_after: Duration::zero(),
+            calibrated_at: Utc::now(),
+        };
+
+        let before = estimate_all(None);
+        calibration.commit_type_multipliers = fit_multipliers(samples.iter().zip(&before).map(
+            |((_, commit_type, _, actual), estimate)| {
+                (vec![commit_type.as_str().to_string()], *actual, *estimate)
+            },
+        ));
+
+        let with_types = estimate_all(Some(&calibration));
+        calibration.file_type_multipliers = fit_multipliers(samples.iter().zip(&with_types).map(
+            |((commit, _, _, actual), estimate)| {
+                let mut extensions: Vec<String> = commit
+                    .files_changed
+                    .iter()
+                    .filter_map(|file| file_extension(file))
+                    .collect();
+                extensions.sort();
+                extensions.dedup();
+                (extensions, *actual, *estimate)
+            },
+        ));
+
+        let after = estimate_all(Some(&calibration));
+        let actual: Vec<Duration> = samples.iter().map(|sample| sample.3).collect();
+        calibration.mean_error_before = mean_absolute_error(&before, &actual);
+        calibration.mean_error_after = mean_absolute_error(&after, &actual);
+
+        if !dry_run {
+            for (commit, _, _, actual) in &samples {
+                self.repository
+                    .set_git_actual_time(&commit.hash, *actual)
+                    .await?;
+            }
+            self.repository.save_git_calibration(&calibration).await?;
+        }
+
+        Ok(calibration)
+    }
+
+    /// Time for each commit: time entries linked to it (split evenly when an
+    /// entry links several commits), else its calibrated actual time, else
+    /// the estimate made when it was imported, else a fresh estimate
+    pub async fn commit_times(&self, commits: &[GitCommit]) -> Result<Vec<CommitTime>> {
+        let Some(earliest) = commits.iter().map(|commit| commit.timestamp).min() else {
+            return Ok(Vec::new());
+        };
+        let hashes: std::collections::HashSet<&str> =
+            commits.iter().map(|commit| commit.hash.as_str()).collect();
+
+        let imported: HashMap<String, GitTimeEntry> = self
+            .repository
+            .list_git_time_entries()
+            .await?
+            .into_iter()
+            .filter(|entry| hashes.contains(entry.commit_hash.as_str()))
+            .map(|entry| (entry.commit_hash.clone(), entry))
+            .collect();
+        let imported_entry_ids: std::collections::HashSet<uuid::Uuid> = imported
+            .values()
+            .filter_map(|entry| entry.time_entry_id)
+            .collect();
+
+        // Entries are linked when they stop, so look back a day for timers
+        // started before the earliest commit
+        let mut tracked: HashMap<&str, (Duration, String)> = HashMap::new();
+        let entries = self
+            .repository
+            .list_time_entries_by_date_range(earliest - Duration::days(1), Utc::now())
+            .await?;
+        for entry in &entries {
+            let Some(duration) = entry.duration else {
+                continue;
+            };
+            if entry.commit_hashes.is_empty()
+                || imported_entry_ids.contains(&entry.id)
+                || entry.tags.iter().any(|tag| tag == "git-import")
+            {
+                continue;
+            }
+            let share = duration / entry.commit_hashes.len() as i32;
+            for hash in &entry.commit_hashes {
+                if let Some(hash) = hashes.get(hash.as_str()) {
+                    let (time, _) = tracked
+                        .entry(hash)
+                        .or_insert((Duration::zero(), entry.project_name.clone()));
+                    *time += share;
+                }
+            }
+        }
+
+        let mut times = Vec::with_capacity(commits.len());
+        for commit in commits {
+            let imported = imported.get(&commit.hash);
+            let project_name = imported.map(|entry| entry.project_name.clone());
+            let (duration, source, project_name) =
+                match (tracked.get(commit.hash.as_str()), imported) {
+                    (Some((duration, project)), _) => {
+                        (*duration, TimeSource::Tracked, Some(project.clone()))
+                    }
+                    (None, Some(entry)) => match entry.actual_time {
+                        Some(actual) => (actual, TimeSource::Tracked, project_name),
+                        None => (entry.estimated_time, TimeSource::Estimated, project_name),
+                    },
+                    (None, None) => (
+                        self.analyze_commit(commit).await?.estimated_duration,
+                        TimeSource::Estimated,
+                        None,
+                    ),
+                };
+            times.push(CommitTime {
+                commit: commit.clone(),
+                duration,
+                source,
+                project_name,
+            });
+        }
+        Ok(times)
+    }
+
+    /// Write each commit's time to a note under `NOTES_REF`, replacing notes
+    /// from earlier runs. Notes that already match are left alone, so
+    /// re-running doesn't add commits to the notes history.
+    pub async fn annotate_commits(
+        &self,
+        repo_path: &Path,
+        commits: &[GitCommit],
+    ) -> Result<GitAnnotateResult> {
+        let git_repo = open_repository(repo_path)?;
+        let signature = notes_signature(&git_repo)?;
+        let mut result = GitAnnotateResult::default();
+
+        for time in self.commit_times(commits).await? {
+            let oid = git2::Oid::from_str(&time.commit.hash).map_err(notes_error)?;
+            let note = time.note();
+            let current = git_repo
+                .find_note(Some(NOTES_REF), oid)
+                .ok()
+                .and_then(|existing| existing.message().map(str::to_string));
+            if current.as_deref() == Some(note.as_str()) {
+                result.unchanged.push(time);
+                continue;
+            }
+
+            git_repo
+                .note(&signature, &signature, Some(NOTES_REF), oid, &note, true)
+                .map_err(notes_error)?;
+            result.written.push(time);
+        }
+
+        Ok(result)
+    }
+
+    /// Delete every note under `NOTES_REF`, returning how many were removed
+    pub fn remove_notes(&self, repo_path: &Path) -> Result<usize> {
+        let git_repo = open_repository(repo_path)?;
+        let annotated: Vec<git2::Oid> = match git_repo.notes(Some(NOTES_REF)) {
+            Ok(notes) => notes
+                .map(|note| note.map(|(_, annotated)| annotated))
+                .collect::<std::result::Result<_, _>>()
+                .map_err(notes_error)?,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(0),
+            Err(e) => return Err(notes_error(e)),
+        };
+
+        let signature = notes_signature(&git_repo)?;
+        for oid in &annotated {
+            git_repo
+                .note_delete(*oid, Some(NOTES_REF), &signature, &signature)
+                .map_err(notes_error)?;
+        }
+        Ok(annotated.len())
+    }
+
+    /// Sample the working tree of the project's directory and record a pulse
+    /// when it differs from the last recorded sample (edits, staging and new
+    /// commits all count). Returns `None` when nothing changed or the project
+    /// has no git repository to watch.
+    pub async fn sample_activity(&self, project: &Project) -> Result<Option<ActivityPulse>> {
+        let Some(directory) = &project.directory_path else {
+            return Ok(None);
+        };
+        let Ok(git_repo) = open_repository(Path::new(directory)) else {
+            return Ok(None);
+        };
+        let repository_path = canonical_repo_path(Path::new(directory));
+
+        let head = git_repo
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok());
+        let head_tree = head.as_ref().and_then(|commit| commit.tree().ok());
+        let mut options = git2::DiffOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
+        let diff = git_repo
+            .diff_tree_to_workdir_with_index(head_tree.as_ref(), Some(&mut options))
+            .map_err(sampling_error)?;
+        let stats = diff.stats().map_err(sampling_error)?;
+        let fingerprint = format!(
+            "{}:{}",
+            head.map(|commit| commit.id().to_string())
+                .unwrap_or_default(),
+            diff.patchid(None).map_err(sampling_error)?
+        );
+
+        let latest = self
+            .repository
+            .latest_activity_pulse(&repository_path)
+            .await?;
+        if latest.is_some_and(|pulse| pulse.fingerprint == fingerprint) {
+            return Ok(None);
+        }
+
+        let pulse = ActivityPulse {
+            id: uuid::Uuid::new_v4(),
+            project_id: project.id,
+            project_name: project.name.clone(),
+            repository_path,
+            sampled_at: Utc::now(),
+            changed_files: stats.files_changed() as u32,
+            lines_changed: (stats.insertions() + stats.deletions()) as u32,
+            fingerprint,
+        };
+        self.repository.save_activity_pulse(&pulse).await?;
+        Ok(Some(pulse))
+    }
+
+    /// Commit velocity over `date_range`, built from imported commits
+    pub async fn commit_activity(&self, date_range: DateRange) -> Result<CommitActivityReport> {
+        let entries = self.repository.list_git_time_entries().await?;
+        Ok(CommitActivityReport::new(&entries, date_range))
+    }
+
+    /// The `top` most active repositories by estimated time, optionally
+    /// limited to commits within `date_range`
+    pub async fn repository_activity(
+        &self,
+        date_range: Option<&DateRange>,
+        top: usize,
+    ) -> Result<Vec<RepositoryActivity>> {
+        let entries = self.repository.list_git_time_entries().await?;
+        let in_range: Vec<&GitTimeEntry> = entries
+            .iter()
+            .filter(|entry| match date_range {
+                Some(range) => entry.committed_at >= range.start && entry.committed_at <= range.end,
+                None => true,
+            })
+            .collect();
+
+        let mut repositories = RepositoryActivity::from_entries(&in_range);
+        repositories.truncate(top);
+        Ok(repositories)
+    }
+
+    pub fn export_activity_json<T: serde::Serialize>(&self, activity: &T) -> Result<String> {
+        serde_json::to_string_pretty(activity).map_err(|e| {
+            TimeSpanError::InvalidDuration(format!("Failed to serialize activity: {}", e))
+        })
+    }
+
     /// Calculate confidence score for time estimation
     fn calculate_confidence_score(&self, analysis: &CommitAnalysis) -> f32 {
         let mut score: f32 = 0.5; // Base confidence
@@ -351,14 +1252,204 @@ impl GitService {
         let current_dir = std::env::current_dir().map_err(TimeSpanError::Io)?;
 
         let since = Utc::now() - Duration::days(days as i64);
-        self.get_commits(&current_dir, Some(since), Some(50)).await
+        let query = CommitQuery {
+            since: Some(since),
+            limit: Some(50),
+            authors: self.resolve_authors(&current_dir, &AuthorFilter::CurrentUser)?,
+            ..CommitQuery::default()
+        };
+        self.get_commits(&current_dir, &query).await
+    }
+}
+
+pub(crate) fn open_repository(repo_path: &Path) -> Result<Repository> {
+    Repository::open(repo_path).map_err(|e| {
+        TimeSpanError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!(
+                "Failed to open git repository at {}: {}",
+                repo_path.display(),
+                e
+            ),
+        ))
+    })
+}
+
+/// The repository's configured identity, or a TimeSpan one when
+/// `user.name`/`user.email` aren't set
+fn notes_signature(git_repo: &Repository) -> Result<git2::Signature<'static>> {
+    git_repo
+        .signature()
+        .or_else(|_| git2::Signature::now("TimeSpan", "timespan@localhost"))
+        .map_err(notes_error)
+}
+
+fn sampling_error(e: git2::Error) -> TimeSpanError {
+    TimeSpanError::Io(std::io::Error::other(format!(
+        "Failed to sample working tree: {}",
+        e
+    )))
+}
+
+fn notes_error(e: git2::Error) -> TimeSpanError {
+    TimeSpanError::Io(std::io::Error::other(format!(
+        "Failed to update {}: {}",
+        NOTES_REF, e
+    )))
+}
+
+/// Start the walk from every ref matching `refs`. Globs may match nothing;
+/// plain names must resolve. A detached HEAD is always included, and HEAD is
+/// the fallback when nothing matched.
+fn push_refs(git_repo: &Repository, revwalk: &mut git2::Revwalk, refs: &[String]) -> Result<()> {
+    let walk_error = |e: git2::Error| {
+        TimeSpanError::Io(std::io::Error::other(format!(
+            "Failed to walk history: {}",
+            e
+        )))
+    };
+    let mut pushed = 0;
+
+    for pattern in refs {
+        if pattern.contains(['*', '?', '[']) {
+            let glob = if pattern.starts_with("refs/") {
+                pattern.clone()
+            } else {
+                format!("refs/{}", pattern)
+            };
+            let matching = git_repo
+                .references_glob(&glob)
+                .map(|references| references.count())
+                .unwrap_or(0);
+            if matching > 0 {
+                revwalk.push_glob(&glob).map_err(walk_error)?;
+                pushed += matching;
+            }
+        } else {
+            let commit = git_repo
+                .revparse_single(pattern)
+                .and_then(|object| object.peel_to_commit())
+                .map_err(|e| {
+                    TimeSpanError::InvalidInput(format!("Unknown ref {}: {}", pattern, e))
+                })?;
+            revwalk.push(commit.id()).map_err(walk_error)?;
+            pushed += 1;
+        }
     }
+
+    if pushed == 0 || git_repo.head_detached().unwrap_or(false) {
+        revwalk.push_head().map_err(|e| {
+            TimeSpanError::Io(std::io::Error::other(format!("Failed to push HEAD: {}", e)))
+        })?;
+    }
+
+    Ok(())
+}
+
+fn canonical_repo_path(path: &Path) -> std::path::PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Split each tracked interval among the commits made during it: a commit is
+/// credited with the tracked time since the previous commit in the same
+/// interval (or since the interval started). Commits made shortly after an
+/// interval ends still count towards it.
+fn attribute_tracked_time(
+    commits: &[(String, DateTime<Utc>)],
+    tracked: &[TimeEntry],
+    grace: Duration,
+) -> HashMap<String, Duration> {
+    let mut commits: Vec<&(String, DateTime<Utc>)> = commits.iter().collect();
+    commits.sort_by_key(|(_, time)| *time);
+
+    let mut actuals = HashMap::new();
+    for entry in tracked {
+        let Some(end) = entry.end_time else {
+            continue;
+        };
+
+        let mut previous = entry.start_time;
+        for (hash, time) in &commits {
+            if *time < entry.start_time || *time > end + grace || actuals.contains_key(hash) {
+                continue;
+            }
+            let segment_end = (*time).min(end);
+            if segment_end > previous {
+                actuals.insert(hash.clone(), segment_end - previous);
+                previous = segment_end;
+            }
+        }
+    }
+
+    actuals
+}
+
+/// Ratio of actual to estimated time per key, over samples that carry the
+/// key. Keys with too few samples are left out; ratios are clamped so one
+/// odd interval can't make estimates absurd.
+fn fit_multipliers(
+    samples: impl Iterator<Item = (Vec<String>, Duration, Duration)>,
+) -> HashMap<String, f32> {
+    let mut sums: HashMap<String, (i64, i64, usize)> = HashMap::new();
+    for (keys, actual, estimate) in samples {
+        for key in keys {
+            let sum = sums.entry(key).or_insert((0, 0, 0));
+            sum.0 += actual.num_seconds();
+            sum.1 += estimate.num_seconds();
+            sum.2 += 1;
+        }
+    }
+
+    sums.into_iter()
+        .filter(|(_, (_, estimated, count))| *count >= MIN_CALIBRATION_SAMPLES && *estimated > 0)
+        .map(|(key, (actual, estimated, _))| {
+            (key, (actual as f32 / estimated as f32).clamp(0.25, 4.0))
+        })
+        .collect()
+}
+
+fn mean_absolute_error(estimates: &[Duration], actuals: &[Duration]) -> Duration {
+    if estimates.is_empty() {
+        return Duration::zero();
+    }
+
+    let total: i64 = estimates
+        .iter()
+        .zip(actuals)
+        .map(|(estimate, actual)| (*estimate - *actual).num_seconds().abs())
+        .sum();
+    Duration::seconds(total / estimates.len() as i64)
+}
+
+/// Confidence for a session from the gaps between its commits: a lone
+/// commit is a guess, while several evenly spaced commits are a strong signal
+fn session_confidence(gaps: &[(&GitCommit, Duration)]) -> f32 {
+    if gaps.is_empty() {
+        return 0.3;
+    }
+
+    let minutes: Vec<f32> = gaps
+        .iter()
+        .map(|(_, gap)| gap.num_seconds() as f32 / 60.0)
+        .collect();
+    let mean = minutes.iter().sum::<f32>() / minutes.len() as f32;
+    let variance = minutes.iter().map(|m| (m - mean).powi(2)).sum::<f32>() / minutes.len() as f32;
+    let variation = if mean > 0.0 {
+        variance.sqrt() / mean
+    } else {
+        1.0
+    };
+
+    let regularity = 1.0 / (1.0 + variation);
+    let size = (minutes.len() as f32 / 4.0).min(1.0);
+    (0.3 + 0.6 * regularity * size).clamp(0.1, 0.9)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::repository::SqliteRepository;
+    use crate::services::test_git::{commit_changes, commit_file};
     use std::path::PathBuf;
     use std::sync::Arc;
 
@@ -398,7 +1489,10 @@ mod tests {
         commit.deletions = 25;
         commit.files_changed = vec!["file1.rs".to_string(), "file2.rs".to_string()];
 
-        let score = git_service.calculate_complexity_score(&commit);
+        let score = git_service.calculate_complexity_score(
+            &commit,
+            &git_service.config.settings_for(&commit.repository_path),
+        );
         assert!(score > 0.0);
         assert!(score < 5.0); // Should be reasonable
     }
@@ -416,8 +1510,600 @@ mod tests {
             PathBuf::from("/test"),
         );
 
-        let duration = git_service.estimate_commit_time(&commit, &CommitType::Feature, 1.0);
+        let duration = git_service.estimate_commit_time(
+            &commit,
+            &git_service.config.settings_for(&commit.repository_path),
+            &CommitType::Feature,
+            1.0,
+            None,
+        );
         assert!(duration > Duration::minutes(30));
         assert!(duration < Duration::hours(5));
     }
+
+    #[tokio::test]
+    async fn test_estimate_commit_time_uses_config() {
+        let config = GitConfig::parse(
+            r#"
+            [file_weights]
+            "src/generated/**" = 0
+
+            [repos."/test".estimation.base_minutes]
+            feature = 30
+            "#,
+        )
+        .unwrap();
+        let repo = Arc::new(SqliteRepository::in_memory().unwrap());
+        let git_service = GitService::with_config(repo, config);
+
+        let mut commit = GitCommit::new(
+            "abc123".to_string(),
+            "feat: add new feature".to_string(),
+            "Test Author".to_string(),
+            "test@example.com".to_string(),
+            Utc::now(),
+            PathBuf::from("/test"),
+        );
+        commit.files_changed = vec![
+            "src/generated/schema.rs".to_string(),
+            "src/lib.rs".to_string(),
+        ];
+
+        // Repository base of 30 minutes plus 5 for the one non-generated .rs file
+        let duration = git_service.estimate_commit_time(
+            &commit,
+            &git_service.config.settings_for(&commit.repository_path),
+            &CommitType::Feature,
+            0.0,
+            None,
+        );
+        assert_eq!(duration, Duration::minutes(35));
+
+        // Other repositories keep the default base; the global rule still applies
+        commit.repository_path = PathBuf::from("/elsewhere");
+        let duration = git_service.estimate_commit_time(
+            &commit,
+            &git_service.config.settings_for(&commit.repository_path),
+            &CommitType::Feature,
+            0.0,
+            None,
+        );
+        assert_eq!(duration, Duration::minutes(50));
+    }
+
+    fn test_commit(hash: &str, minutes_ago: i64) -> GitCommit {
+        let mut commit = GitCommit::new(
+            hash.to_string(),
+            "fix: handle empty input".to_string(),
+            "Test Author".to_string(),
+            "test@example.com".to_string(),
+            Utc::now() - Duration::minutes(minutes_ago),
+            PathBuf::from("/test"),
+        );
+        commit.insertions = 20;
+        commit.files_changed = vec!["src/lib.rs".to_string()];
+        commit
+    }
+
+    #[tokio::test]
+    async fn test_import_commits_skips_already_imported() {
+        let repo = Arc::new(SqliteRepository::in_memory().unwrap());
+        let git_service = GitService::new(repo.clone());
+        let project = Project::new("Test".to_string(), None);
+        repo.create_project(&project).await.unwrap();
+
+        let commits = vec![test_commit("aaa111", 120), test_commit("bbb222", 60)];
+
+        let preview = git_service
+            .import_commits(
+                &commits,
+                &project,
+                &GitImportOptions {
+                    dry_run: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(preview.imported.len(), 2);
+        assert_eq!(
+            repo.count_time_entries_for_project(project.id)
+                .await
+                .unwrap(),
+            0
+        );
+
+        let first = git_service
+            .import_commits(&commits[..1], &project, &GitImportOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(first.imported.len(), 1);
+
+        let second = git_service
+            .import_commits(&commits, &project, &GitImportOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(second.imported.len(), 1);
+        assert_eq!(second.skipped, vec!["aaa111".to_string()]);
+        assert_eq!(
+            repo.count_time_entries_for_project(project.id)
+                .await
+                .unwrap(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_estimate_sessions() {
+        let git_service = setup_git_service().await;
+        let base = Utc::now() - Duration::days(1);
+        let at = |hash: &str, email: &str, minutes: i64| {
+            let mut commit = test_commit(hash, 0);
+            commit.author_email = email.to_string();
+            commit.timestamp = base + Duration::minutes(minutes);
+            commit
+        };
+
+        let commits = vec![
+            at("a1", "alice@example.com", 0),
+            at("a2", "alice@example.com", 40),
+            at("b1", "bob@example.com", 50),
+            at("a3", "alice@example.com", 80),
+            // More than two hours later: a new session
+            at("a4", "alice@example.com", 300),
+        ];
+        let settings = SessionSettings {
+            max_gap: Duration::hours(2),
+            lead_in: Duration::minutes(20),
+        };
+
+        let estimates = git_service.estimate_sessions(&commits, &settings);
+
+        assert_eq!(estimates["a1"].duration, Duration::minutes(20));
+        assert_eq!(estimates["a2"].duration, Duration::minutes(40));
+        assert_eq!(estimates["a3"].duration, Duration::minutes(40));
+        assert_eq!(estimates["a4"].duration, Duration::minutes(20));
+        // Other authors' commits don't split or extend a session
+        assert_eq!(estimates["b1"].duration, Duration::minutes(20));
+
+        // A regular three-commit session beats a lone commit
+        assert!(estimates["a2"].confidence > estimates["a4"].confidence);
+        assert_eq!(estimates["a1"].confidence, estimates["a3"].confidence);
+    }
+
+    #[test]
+    fn test_attribute_tracked_time() {
+        let base = Utc::now() - Duration::days(1);
+        let project_id = uuid::Uuid::new_v4();
+        let mut tracked = TimeEntry::new(project_id, "Test".to_string(), None, base);
+        tracked.stop(base + Duration::minutes(90)).unwrap();
+
+        let commits = vec![
+            ("a".to_string(), base + Duration::minutes(30)),
+            ("b".to_string(), base + Duration::minutes(80)),
+            // Shortly after the timer stopped: gets the remaining 10 minutes
+            ("c".to_string(), base + Duration::minutes(95)),
+            // Well after: not matched
+            ("d".to_string(), base + Duration::minutes(200)),
+        ];
+
+        let actuals = attribute_tracked_time(&commits, &[tracked], Duration::minutes(10));
+
+        assert_eq!(actuals["a"], Duration::minutes(30));
+        assert_eq!(actuals["b"], Duration::minutes(50));
+        assert_eq!(actuals["c"], Duration::minutes(10));
+        assert!(!actuals.contains_key("d"));
+    }
+
+    #[tokio::test]
+    async fn test_get_commits_walks_branches_and_merges() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let git_repo = git2::Repository::init(dir.path()).unwrap();
+        let author = ("Dev", "dev@example.com");
+        let base = Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap();
+
+        let first = commit_file(&git_repo, author, base, "feat: first");
+        let main_ref = git_repo.head().unwrap().name().unwrap().to_string();
+
+        // A topic branch committed to after main moves on
+        git_repo
+            .branch("topic", &git_repo.find_commit(first).unwrap(), false)
+            .unwrap();
+        commit_file(
+            &git_repo,
+            author,
+            base + Duration::minutes(10),
+            "feat: main",
+        );
+        git_repo.set_head("refs/heads/topic").unwrap();
+        let topic = commit_file(
+            &git_repo,
+            author,
+            base + Duration::minutes(20),
+            "feat: topic",
+        );
+        git_repo.set_head(&main_ref).unwrap();
+
+        let messages = |commits: &[GitCommit]| {
+            commits
+                .iter()
+                .map(|commit| commit.message.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let git_service = GitService::new(Arc::new(SqliteRepository::in_memory().unwrap()));
+        let commits = git_service
+            .get_commits(dir.path(), &CommitQuery::default())
+            .await
+            .unwrap();
+        assert_eq!(messages(&commits), "feat: topic, feat: main, feat: first");
+
+        // Merge topic into main
+        let head = git_repo.head().unwrap().peel_to_commit().unwrap();
+        let topic_commit = git_repo.find_commit(topic).unwrap();
+        let time = git2::Time::new((base + Duration::minutes(30)).timestamp(), 0);
+        let signature = git2::Signature::new(author.0, author.1, &time).unwrap();
+        git_repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                "Merge branch 'topic'",
+                &topic_commit.tree().unwrap(),
+                &[&head, &topic_commit],
+            )
+            .unwrap();
+
+        // Skipped by default; each commit appears once although both
+        // branches contain it
+        let commits = git_service
+            .get_commits(dir.path(), &CommitQuery::default())
+            .await
+            .unwrap();
+        assert_eq!(messages(&commits), "feat: topic, feat: main, feat: first");
+
+        let commits = git_service
+            .get_commits(
+                dir.path(),
+                &CommitQuery {
+                    merges: Some(MergePolicy::Zero),
+                    ..CommitQuery::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(commits.len(), 4);
+        assert!(commits[0].is_merge);
+        assert_eq!(commits[0].total_changes(), 0);
+        let analysis = git_service.analyze_commit(&commits[0]).await.unwrap();
+        assert_eq!(analysis.estimated_duration, Duration::zero());
+
+        let commits = git_service
+            .get_commits(
+                dir.path(),
+                &CommitQuery {
+                    since: Some(base + Duration::minutes(15)),
+                    refs: Some(vec!["topic".to_string()]),
+                    ..CommitQuery::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(messages(&commits), "feat: topic");
+    }
+
+    #[tokio::test]
+    async fn test_find_repositories() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        git2::Repository::init(root.join("app")).unwrap();
+        git2::Repository::init(root.join("app").join("vendor").join("lib")).unwrap();
+        git2::Repository::init(root.join("app").join("plugins").join("extra")).unwrap();
+        git2::Repository::init(root.join("clients").join("acme")).unwrap();
+        git2::Repository::init(root.join(".cache").join("hidden")).unwrap();
+        std::fs::create_dir_all(root.join("notes")).unwrap();
+        // Worktrees and submodules have a `.git` file instead of a directory
+        std::fs::create_dir_all(root.join("app-wt")).unwrap();
+        std::fs::write(root.join("app-wt").join(".git"), "gitdir: ../app/.git\n").unwrap();
+
+        let git_service = setup_git_service().await;
+        let found: Vec<String> = git_service
+            .find_repositories(root, 5)
+            .unwrap()
+            .iter()
+            .map(|path| {
+                path.strip_prefix(root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect();
+
+        assert_eq!(
+            found,
+            vec!["app", "app/plugins/extra", "app-wt", "clients/acme"]
+        );
+        assert_eq!(git_service.find_repositories(root, 1).unwrap().len(), 2);
+        assert!(git_service
+            .find_repositories(&root.join("missing"), 5)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_commit_stats_handle_renames_and_binary_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let git_repo = git2::Repository::init(dir.path()).unwrap();
+        let author = ("Dev", "dev@example.com");
+        let base = Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap();
+
+        let original: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+        commit_changes(
+            &git_repo,
+            author,
+            base,
+            "docs: add notes",
+            &[("notes.txt", Some(original.as_bytes()))],
+        );
+
+        let edited = original.replace("line 20", "line twenty");
+        commit_changes(
+            &git_repo,
+            author,
+            base + Duration::minutes(5),
+            "docs: move notes",
+            &[
+                ("notes.txt", None),
+                ("guide.txt", Some(edited.as_bytes())),
+                ("logo.bin", Some(&[0, 159, 146, 150, 0, 1, 2, 0])),
+            ],
+        );
+
+        let git_service = GitService::new(Arc::new(SqliteRepository::in_memory().unwrap()));
+        let commit = git_service.get_commit(dir.path(), "HEAD").await.unwrap();
+
+        assert_eq!(commit.files_changed, vec!["guide.txt", "logo.bin"]);
+        assert_eq!((commit.insertions, commit.deletions), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_get_commits_filters_by_author() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let git_repo = git2::Repository::init(dir.path()).unwrap();
+        git_repo
+            .config()
+            .unwrap()
+            .set_str("user.email", "Jane@Work.example")
+            .unwrap();
+        let base = Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap();
+        commit_file(&git_repo, ("Jane", "jane@work.example"), base, "feat: a");
+        commit_file(
+            &git_repo,
+            ("Sam", "sam@work.example"),
+            base + Duration::minutes(5),
+            "fix: b",
+        );
+        commit_file(
+            &git_repo,
+            ("Jane", "jane@home.example"),
+            base + Duration::minutes(9),
+            "docs: c",
+        );
+
+        let config =
+            GitConfig::parse("[identities]\n\"jane@work.example\" = [\"jane@home.example\"]\n")
+                .unwrap();
+        let git_service =
+            GitService::with_config(Arc::new(SqliteRepository::in_memory().unwrap()), config);
+
+        let messages = |commits: Vec<GitCommit>| {
+            commits
+                .into_iter()
+                .map(|commit| commit.message)
+                .collect::<Vec<_>>()
+        };
+
+        let mine = git_service
+            .resolve_authors(dir.path(), &AuthorFilter::CurrentUser)
+            .unwrap();
+        let commits = git_service
+            .get_commits(
+                dir.path(),
+                &CommitQuery {
+                    authors: mine,
+                    ..CommitQuery::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(messages(commits), vec!["docs: c", "feat: a"]);
+
+        let sam = git_service
+            .resolve_authors(dir.path(), &AuthorFilter::Author("sam".to_string()))
+            .unwrap();
+        let commits = git_service
+            .get_commits(
+                dir.path(),
+                &CommitQuery {
+                    limit: Some(1),
+                    authors: sam,
+                    ..CommitQuery::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(messages(commits), vec!["fix: b"]);
+
+        assert!(git_service
+            .resolve_authors(dir.path(), &AuthorFilter::All)
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_calibrate_against_tracked_time() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let git_repo = git2::Repository::init(dir.path()).unwrap();
+        let base = Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap();
+
+        for i in 1..=4 {
+            commit_file(
+                &git_repo,
+                ("Dev", "dev@example.com"),
+                base + Duration::minutes(15 * i),
+                &format!("feat: step {}", i),
+            );
+        }
+
+        let repo = Arc::new(SqliteRepository::in_memory().unwrap());
+        let git_service = GitService::new(repo.clone());
+        let project = Project::new("Test".to_string(), None);
+        repo.create_project(&project).await.unwrap();
+
+        let commits = git_service
+            .get_commits(dir.path(), &CommitQuery::default())
+            .await
+            .unwrap();
+        assert_eq!(commits.len(), 4);
+        git_service
+            .import_commits(&commits, &project, &GitImportOptions::default())
+            .await
+            .unwrap();
+
+        // One hour of real work covering all four commits
+        let mut tracked = TimeEntry::new(project.id, project.name.clone(), None, base);
+        tracked.stop(base + Duration::minutes(60)).unwrap();
+        repo.create_time_entry(&tracked).await.unwrap();
+
+        let calibration = git_service.calibrate(dir.path(), false).await.unwrap();
+
+        assert_eq!(calibration.sample_count, 4);
+        assert!(calibration.commit_type_multipliers["feature"] < 1.0);
+        assert!(calibration.mean_error_after < calibration.mean_error_before);
+
+        let stored = repo
+            .get_git_time_entry_by_commit(&commits[0].hash)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.actual_time, Some(Duration::minutes(15)));
+
+        // Later estimates use the stored calibration
+        let analysis = git_service.analyze_commit(&commits[0]).await.unwrap();
+        assert!(analysis.estimated_duration < stored.estimated_time);
+    }
+
+    #[tokio::test]
+    async fn test_annotate_commits_with_notes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let git_repo = git2::Repository::init(dir.path()).unwrap();
+        let base = Utc::now() - Duration::hours(3);
+        let tracked_oid = commit_file(&git_repo, ("Dev", "dev@example.com"), base, "feat: a");
+        let estimated_oid = commit_file(
+            &git_repo,
+            ("Dev", "dev@example.com"),
+            base + Duration::minutes(30),
+            "fix: b",
+        );
+
+        let repo = Arc::new(SqliteRepository::in_memory().unwrap());
+        let git_service = GitService::new(repo.clone());
+        let project = Project::new("App".to_string(), None);
+        repo.create_project(&project).await.unwrap();
+
+        let mut entry = TimeEntry::new(
+            project.id,
+            project.name.clone(),
+            None,
+            base - Duration::minutes(90),
+        );
+        entry.stop(base).unwrap();
+        entry.commit_hashes = vec![tracked_oid.to_string()];
+        repo.create_time_entry(&entry).await.unwrap();
+
+        let commits = git_service
+            .get_commits(dir.path(), &CommitQuery::default())
+            .await
+            .unwrap();
+        let result = git_service
+            .annotate_commits(dir.path(), &commits)
+            .await
+            .unwrap();
+        assert_eq!(result.written.len(), 2);
+
+        let note = |oid: git2::Oid| {
+            git_repo
+                .find_note(Some(NOTES_REF), oid)
+                .ok()
+                .and_then(|note| note.message().map(str::to_string))
+        };
+        assert_eq!(
+            note(tracked_oid).as_deref(),
+            Some("Time: 1h 30m (tracked)\nProject: App\n")
+        );
+        assert!(note(estimated_oid).unwrap().ends_with("(estimated)\n"));
+
+        // Re-running leaves matching notes alone
+        let rerun = git_service
+            .annotate_commits(dir.path(), &commits)
+            .await
+            .unwrap();
+        assert!(rerun.written.is_empty());
+        assert_eq!(rerun.unchanged.len(), 2);
+
+        assert_eq!(git_service.remove_notes(dir.path()).unwrap(), 2);
+        assert!(note(tracked_oid).is_none());
+        assert_eq!(git_service.remove_notes(dir.path()).unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sample_activity_records_changes_only() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let git_repo = git2::Repository::init(dir.path()).unwrap();
+        commit_file(&git_repo, ("Dev", "dev@example.com"), Utc::now(), "feat: a");
+
+        let repo = Arc::new(SqliteRepository::in_memory().unwrap());
+        let git_service = GitService::new(repo.clone());
+        let mut project = Project::new("App".to_string(), None);
+        assert!(git_service
+            .sample_activity(&project)
+            .await
+            .unwrap()
+            .is_none());
+        project.directory_path = Some(dir.path().to_string_lossy().to_string());
+        repo.create_project(&project).await.unwrap();
+
+        let baseline = git_service
+            .sample_activity(&project)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((baseline.changed_files, baseline.lines_changed), (0, 0));
+        assert!(git_service
+            .sample_activity(&project)
+            .await
+            .unwrap()
+            .is_none());
+
+        std::fs::write(dir.path().join("lib.rs"), "// edited\n// twice\n").unwrap();
+        std::fs::write(dir.path().join("new.rs"), "fn main() {}\n").unwrap();
+        let edited = git_service
+            .sample_activity(&project)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(edited.changed_files, 2);
+        assert_eq!(edited.lines_changed, 4);
+        assert!(git_service
+            .sample_activity(&project)
+            .await
+            .unwrap()
+            .is_none());
+
+        let pulses = repo
+            .list_activity_pulses(Utc::now() - Duration::hours(1), Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(pulses.len(), 2);
+    }
 }
@@ -0,0 +1,480 @@
+use chrono::{DateTime, Datelike, Duration, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::models::{
+    format_hours_minutes, DateRange, Invoice, InvoiceGrouping, InvoiceLineItem, Project,
+};
+use crate::repository::Repository;
+use crate::{Result, TimeSpanError};
+
+pub struct InvoiceService {
+    repository: Arc<dyn Repository>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InvoiceOptions {
+    pub client: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub grouping: InvoiceGrouping,
+    pub rounding_increment_minutes: i64,
+    /// Rate used for projects that don't define their own hourly rate
+    pub default_rate_cents: Option<i64>,
+    pub currency: String,
+    pub dry_run: bool,
+}
+
+impl InvoiceService {
+    pub fn new(repository: Arc<dyn Repository>) -> Self {
+        Self { repository }
+    }
+
+    /// Build an invoice from the client's uninvoiced entries and, unless this
+    /// is a dry run, store it and lock the billed entries
+    pub async fn create_invoice(&self, options: &InvoiceOptions) -> Result<Invoice> {
+        let projects: HashMap<Uuid, Project> = self
+            .repository
+            .list_projects()
+            .await?
+            .into_iter()
+            .filter(|p| p.billable && p.matches_client(&options.client))
+            .map(|p| (p.id, p))
+            .collect();
+
+        if projects.is_empty() {
+            return Err(TimeSpanError::ProjectNotFound(options.client.clone()));
+        }
+
+        let entries: Vec<_> = self
+            .repository
+            .list_time_entries_by_date_range(options.start, options.end)
+            .await?
+            .into_iter()
+            .filter(|e| projects.contains_key(&e.project_id) && !e.is_running() && !e.is_invoiced())
+            .collect();
+
+        if entries.is_empty() {
+            return Err(TimeSpanError::InvalidInput(format!(
+                "No uninvoiced time entries for client '{}' in the selected period",
+                options.client
+            )));
+        }
+
+        // Group entries into line items, keyed by project and description
+        let mut groups: BTreeMap<(String, String), (Uuid, Vec<Uuid>, Duration)> = BTreeMap::new();
+        for entry in &entries {
+            let description = match options.grouping {
+                InvoiceGrouping::Project => entry.project_name.clone(),
+                InvoiceGrouping::Task => entry
+                    .task_description
+                    .clone()
+                    .unwrap_or_else(|| "General work".to_string()),
+            };
+
+            let group = groups
+                .entry((entry.project_name.clone(), description))
+                .or_insert_with(|| (entry.project_id, Vec::new(), Duration::zero()));
+            group.1.push(entry.id);
+            group.2 += entry.duration.unwrap_or_else(Duration::zero);
+        }
+
+        let line_items = groups
+            .into_iter()
+            .map(
+                |((project_name, description), (project_id, entry_ids, duration))| {
+                    let rate = projects
+                        .get(&project_id)
+                        .and_then(|p| p.hourly_rate_cents)
+                        .or(options.default_rate_cents);
+
+                    InvoiceLineItem::new(
+                        project_name,
+                        description,
+                        entry_ids,
+                        duration,
+                        options.rounding_increment_minutes,
+                        rate,
+                    )
+                },
+            )
+            .collect();
+
+        let number = self.next_invoice_number(options.end).await?;
+        let invoice = Invoice::new(
+            number,
+            options.client.clone(),
+            DateRange {
+                start: options.start,
+                end: options.end,
+            },
+            options.grouping,
+            options.rounding_increment_minutes,
+            options.currency.clone(),
+            line_items,
+        );
+
+        if !options.dry_run {
+            self.repository.create_invoice(&invoice).await?;
+        }
+
+        Ok(invoice)
+    }
+
+    pub async fn get_invoice(&self, number: &str) -> Result<Option<Invoice>> {
+        self.repository.get_invoice_by_number(number).await
+    }
+
+    pub async fn list_invoices(&self) -> Result<Vec<Invoice>> {
+        self.repository.list_invoices().await
+    }
+
+    /// Invoice numbers are sequential per year, e.g. `INV-2024-0007`
+    async fn next_invoice_number(&self, date: DateTime<Utc>) -> Result<String> {
+        let prefix = format!("INV-{}-", date.year());
+        let mut sequence = self.repository.count_invoices_numbered(&prefix).await? + 1;
+
+        loop {
+            let number = format!("{}{:04}", prefix, sequence);
+            if self
+                .repository
+                .get_invoice_by_number(&number)
+                .await?
+                .is_none()
+            {
+                return Ok(number);
+            }
+            sequence += 1;
+        }
+    }
+
+    pub fn render_markdown(&self, invoice: &Invoice) -> String {
+        let mut out = String::new();
+
+        out.push_str(&format!("# Invoice {}\n\n", invoice.number));
+        out.push_str(&format!("**Client:** {}  \n", invoice.client));
+        out.push_str(&format!(
+            "**Period:** {} – {}  \n",
+            invoice.period.start.format("%Y-%m-%d"),
+            invoice.period.end.format("%Y-%m-%d")
+        ));
+        out.push_str(&format!(
+            "**Issued:** {}\n\n",
+            invoice.created_at.format("%Y-%m-%d")
+        ));
+
+        out.push_str("| Project | Description | Tracked | Billed | Rate | Amount |\n");
+        out.push_str("|---|---|---:|---:|---:|---:|\n");
+        for item in &invoice.line_items {
+            out.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} |\n",
+                escape_markdown(&item.project_name),
+                escape_markdown(&item.description),
+                format_hours_minutes(item.duration),
+                format_hours_minutes(item.billed_duration),
+                format_rate(item.hourly_rate_cents, &invoice.currency),
+                format_amount(item.amount_cents, &invoice.currency),
+            ));
+        }
+
+        out.push_str(&format!(
+            "| **Total** | | {} | {} | | **{}** |\n",
+            format_hours_minutes(invoice.total_duration),
+            format_hours_minutes(invoice.total_billed_duration),
+            format_amount(invoice.total_amount_cents, &invoice.currency),
+        ));
+
+        if invoice.rounding_increment_minutes > 1 {
+            out.push_str(&format!(
+                "\nBilled time is rounded up to {}-minute increments per line item.\n",
+                invoice.rounding_increment_minutes
+            ));
+        }
+
+        out
+    }
+
+    pub fn render_html(&self, invoice: &Invoice) -> String {
+        let mut rows = String::new();
+        for item in &invoice.line_items {
+            rows.push_str(&format!(
+                "      <tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&item.project_name),
+                escape_html(&item.description),
+                format_hours_minutes(item.duration),
+                format_hours_minutes(item.billed_duration),
+                escape_html(&format_rate(item.hourly_rate_cents, &invoice.currency)),
+                escape_html(&format_amount(item.amount_cents, &invoice.currency)),
+            ));
+        }
+
+        format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>Invoice {number}</title>
+</head>
+<body>
+  <h1>Invoice {number}</h1>
+  <p><strong>Client:</strong> {client}<br>
+  <strong>Period:</strong> {start} – {end}<br>
+  <strong>Issued:</strong> {issued}</p>
+  <table>
+    <thead>
+      <tr><th>Project</th><th>Description</th><th>Tracked</th><th>Billed</th><th>Rate</th><th>Amount</th></tr>
+    </thead>
+    <tbody>
+{rows}    </tbody>
+    <tfoot>
+      <tr><th colspan="2">Total</th><th>{total}</th><th>{billed}</th><th></th><th>{amount}</th></tr>
+    </tfoot>
+  </table>
+</body>
+</html>
+"#,
+            number = escape_html(&invoice.number),
+            client = escape_html(&invoice.client),
+            start = invoice.period.start.format("%Y-%m-%d"),
+            end = invoice.period.end.format("%Y-%m-%d"),
+            issued = invoice.created_at.format("%Y-%m-%d"),
+            rows = rows,
+            total = format_hours_minutes(invoice.total_duration),
+            billed = format_hours_minutes(invoice.total_billed_duration),
+            amount = escape_html(&format_amount(
+                invoice.total_amount_cents,
+                &invoice.currency
+            )),
+        )
+    }
+
+    pub fn export_invoice_json(&self, invoice: &Invoice) -> Result<String> {
+        serde_json::to_string_pretty(invoice)
+            .map_err(|e| TimeSpanError::InvalidInput(format!("Failed to serialize invoice: {}", e)))
+    }
+}
+
+fn format_amount(cents: i64, currency: &str) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.unsigned_abs();
+    format!("{}{}.{:02} {}", sign, cents / 100, cents % 100, currency)
+}
+
+fn format_rate(rate_cents: Option<i64>, currency: &str) -> String {
+    match rate_cents {
+        Some(rate) => format!("{}/h", format_amount(rate, currency)),
+        None => "-".to_string(),
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TimeEntry;
+    use crate::repository::SqliteRepository;
+    use chrono::TimeZone;
+
+    async fn setup() -> (Arc<SqliteRepository>, InvoiceService) {
+        let repo = Arc::new(SqliteRepository::in_memory().unwrap());
+        (repo.clone(), InvoiceService::new(repo))
+    }
+
+    async fn add_entry(
+        repo: &SqliteRepository,
+        project: &Project,
+        task: Option<&str>,
+        hour: u32,
+        minutes: i64,
+    ) -> TimeEntry {
+        let start = Utc.with_ymd_and_hms(2024, 1, 10, hour, 0, 0).unwrap();
+        let mut entry = TimeEntry::new(
+            project.id,
+            project.name.clone(),
+            task.map(|t| t.to_string()),
+            start,
+        );
+        entry.stop(start + Duration::minutes(minutes)).unwrap();
+        repo.create_time_entry(&entry).await.unwrap();
+        entry
+    }
+
+    fn january(client: &str, grouping: InvoiceGrouping) -> InvoiceOptions {
+        InvoiceOptions {
+            client: client.to_string(),
+            start: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            end: Utc.with_ymd_and_hms(2024, 1, 31, 23, 59, 59).unwrap(),
+            grouping,
+            rounding_increment_minutes: 15,
+            default_rate_cents: Some(5_000),
+            currency: "USD".to_string(),
+            dry_run: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_invoice_groups_by_task_and_locks_entries() {
+        let (repo, service) = setup().await;
+        let mut project = Project::new("[CLIENT] Acme".to_string(), None);
+        project.set_hourly_rate(Some(10_000));
+        repo.create_project(&project).await.unwrap();
+
+        let entry = add_entry(&repo, &project, Some("Login"), 9, 50).await;
+        add_entry(&repo, &project, Some("Login"), 11, 20).await;
+        add_entry(&repo, &project, Some("Reports"), 14, 10).await;
+
+        let invoice = service
+            .create_invoice(&january("Acme", InvoiceGrouping::Task))
+            .await
+            .unwrap();
+
+        assert_eq!(invoice.number, "INV-2024-0001");
+        assert_eq!(invoice.line_items.len(), 2);
+        assert_eq!(invoice.total_duration, Duration::minutes(80));
+        // 70m -> 75m and 10m -> 15m at the project's own rate
+        assert_eq!(invoice.total_billed_duration, Duration::minutes(90));
+        assert_eq!(invoice.total_amount_cents, 15_000);
+
+        let stored = repo.get_time_entry_by_id(entry.id).await.unwrap().unwrap();
+        assert_eq!(stored.invoice_id, Some(invoice.id));
+
+        // Locked entries can't be edited
+        let result = repo.update_time_entry(&stored).await;
+        assert!(matches!(result, Err(TimeSpanError::TimeEntryLocked(_))));
+
+        // And they can't be billed twice
+        let result = service
+            .create_invoice(&january("Acme", InvoiceGrouping::Task))
+            .await;
+        assert!(matches!(result, Err(TimeSpanError::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_does_not_lock_entries() {
+        let (repo, service) = setup().await;
+        let project = Project::new("Acme".to_string(), None);
+        repo.create_project(&project).await.unwrap();
+        let entry = add_entry(&repo, &project, None, 9, 30).await;
+
+        let mut options = january("Acme", InvoiceGrouping::Project);
+        options.dry_run = true;
+        let invoice = service.create_invoice(&options).await.unwrap();
+
+        assert_eq!(invoice.total_amount_cents, 2_500);
+        assert!(service.list_invoices().await.unwrap().is_empty());
+        let stored = repo.get_time_entry_by_id(entry.id).await.unwrap().unwrap();
+        assert!(!stored.is_invoiced());
+    }
+
+    #[tokio::test]
+    async fn test_non_billable_projects_are_not_invoiced() {
+        let (repo, service) = setup().await;
+        let website = Project::new("[CLIENT] Acme/Website".to_string(), None);
+        let mut support = Project::new("[CLIENT] Acme/Support".to_string(), None);
+        support.billable = false;
+        for project in [&website, &support] {
+            repo.create_project(project).await.unwrap();
+        }
+        add_entry(&repo, &website, None, 9, 60).await;
+        let unbilled = add_entry(&repo, &support, None, 11, 30).await;
+
+        let invoice = service
+            .create_invoice(&january("Acme", InvoiceGrouping::Project))
+            .await
+            .unwrap();
+
+        assert_eq!(invoice.line_items.len(), 1);
+        assert_eq!(invoice.total_duration, Duration::minutes(60));
+        let stored = repo
+            .get_time_entry_by_id(unbilled.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!stored.is_invoiced());
+    }
+
+    #[tokio::test]
+    async fn test_invoice_numbers_restart_each_year() {
+        let (repo, service) = setup().await;
+        for client in ["Acme", "Globex"] {
+            let project = Project::new(client.to_string(), None);
+            repo.create_project(&project).await.unwrap();
+            add_entry(&repo, &project, None, 9, 30).await;
+        }
+        let acme = repo.get_project_by_name("Acme").await.unwrap().unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 1, 10, 9, 0, 0).unwrap();
+        let mut entry = TimeEntry::new(acme.id, acme.name.clone(), None, start);
+        entry.stop(start + Duration::minutes(30)).unwrap();
+        repo.create_time_entry(&entry).await.unwrap();
+
+        let mut numbers = Vec::new();
+        for client in ["Acme", "Globex"] {
+            let invoice = service
+                .create_invoice(&january(client, InvoiceGrouping::Project))
+                .await
+                .unwrap();
+            numbers.push(invoice.number);
+        }
+        let mut options = january("Acme", InvoiceGrouping::Project);
+        options.start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        options.end = Utc.with_ymd_and_hms(2025, 1, 31, 23, 59, 59).unwrap();
+        numbers.push(service.create_invoice(&options).await.unwrap().number);
+
+        assert_eq!(
+            numbers,
+            vec!["INV-2024-0001", "INV-2024-0002", "INV-2025-0001"]
+        );
+    }
+
+    #[test]
+    fn test_format_amount_keeps_sign_of_credits() {
+        assert_eq!(format_amount(12_345, "USD"), "123.45 USD");
+        assert_eq!(format_amount(5, "USD"), "0.05 USD");
+        assert_eq!(format_amount(-50, "USD"), "-0.50 USD");
+        assert_eq!(format_amount(-12_345, "EUR"), "-123.45 EUR");
+    }
+
+    #[tokio::test]
+    async fn test_unknown_client() {
+        let (_, service) = setup().await;
+
+        let result = service
+            .create_invoice(&january("Nobody", InvoiceGrouping::Project))
+            .await;
+        assert!(matches!(result, Err(TimeSpanError::ProjectNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_render_formats() {
+        let (repo, service) = setup().await;
+        let project = Project::new("Acme <Web>".to_string(), None);
+        repo.create_project(&project).await.unwrap();
+        add_entry(&repo, &project, None, 9, 60).await;
+
+        let invoice = service
+            .create_invoice(&january("Acme <Web>", InvoiceGrouping::Project))
+            .await
+            .unwrap();
+
+        let markdown = service.render_markdown(&invoice);
+        assert!(markdown.contains("# Invoice INV-2024-0001"));
+        assert!(markdown.contains("50.00 USD"));
+
+        let html = service.render_html(&invoice);
+        assert!(html.contains("Acme &lt;Web&gt;"));
+
+        let json = service.export_invoice_json(&invoice).unwrap();
+        assert!(json.contains("line_items"));
+    }
+}
@@ -1,17 +1,39 @@
 pub mod client_discovery;
+pub mod git_hooks;
 pub mod git_service;
+pub mod invoicing;
+#[cfg(test)]
+mod test_git;
 
 use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use crate::models::{Project, TimeEntry, TimeReport, Timer};
+use crate::config::{BranchConfig, ReferenceConfig};
+use crate::manifest::ProjectManifest;
+use crate::models::{
+    ActivityGap, BranchTimeSummary, DateRange, EntryDetails, EntryReference, GitCommit,
+    PeriodComparison, Project, ProjectMatch, ReferenceSource, ReportPeriod, RoundingRule,
+    TicketField, TicketSummary, TimeEntry, TimeReport, Timer, Timesheet,
+};
 use crate::repository::Repository;
 use crate::{Result, TimeSpanError};
 
 pub use client_discovery::{
-    ClientDirectory, ClientDiscoveryService, DiscoveryOptions, DiscoveryResult,
+    ClientDirectory, ClientDiscoveryService, DiscoveryOptions, DiscoveryResult, MoveEvidence,
+    MovedProject, IGNORE_FILE,
 };
-pub use git_service::GitService;
+pub use git_hooks::{GitHookService, HookAction, HookInstallation, HookKind, HookTarget};
+pub use git_service::{
+    AuthorFilter, AuthorMatcher, CommitQuery, CommitTime, GitAnnotateResult, GitImportOptions,
+    GitImportResult, GitService, SessionEstimate, SessionSettings, NOTES_REF,
+};
+pub use invoicing::{InvoiceOptions, InvoiceService};
+
+/// Activity pulses further apart than this belong to separate stretches
+const ACTIVITY_MAX_GAP_MINUTES: i64 = 15;
+/// Untracked stretches shorter than this aren't reported
+const ACTIVITY_MIN_GAP_MINUTES: i64 = 5;
 
 pub struct ProjectService {
     repository: Arc<dyn Repository>,
@@ -40,6 +62,99 @@ impl ProjectService {
         self.repository.list_projects().await
     }
 
+    /// Unarchived project whose `directory_path` contains `path`; the
+    /// deepest one wins when project directories are nested
+    pub async fn find_project_for_directory(&self, path: &Path) -> Result<Option<Project>> {
+        let path = canonical_path(path);
+
+        let mut best: Option<(usize, Project)> = None;
+        for project in self.repository.list_projects().await? {
+            if project.is_archived() {
+                continue;
+            }
+            let Some(directory) = project.directory_path.as_deref() else {
+                continue;
+            };
+            let directory = canonical_path(Path::new(directory));
+            if !path.starts_with(&directory) {
+                continue;
+            }
+
+            let depth = directory.components().count();
+            let deeper = match &best {
+                Some((best_depth, _)) => depth > *best_depth,
+                None => true,
+            };
+            if deeper {
+                best = Some((depth, project));
+            }
+        }
+
+        Ok(best.map(|(_, project)| project))
+    }
+
+    /// Project for work done in `path`, checked in order: a project whose
+    /// directory contains it, a project named after its git repository, then
+    /// the project a `.timespan.toml` declares (created on first use)
+    pub async fn resolve_directory_project(
+        &self,
+        path: &Path,
+        git_service: &GitService,
+    ) -> Result<Option<(Project, ProjectMatch)>> {
+        if let Some(project) = self.find_project_for_directory(path).await? {
+            let directory = PathBuf::from(project.directory_path.clone().unwrap_or_default());
+            return Ok(Some((project, ProjectMatch::Directory(directory))));
+        }
+
+        if let Some((project, root)) = git_service.repository_project(path).await? {
+            return Ok(Some((project, ProjectMatch::Repository(root))));
+        }
+
+        match ProjectManifest::find(path)? {
+            Some(manifest) => {
+                let project = self.ensure_manifest_project(&manifest).await?;
+                Ok(Some((project, ProjectMatch::Manifest(manifest.path))))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// The project a `.timespan.toml` declares, created on first use. The
+    /// manifest is the source of truth, so its directory, description,
+    /// billable flag and rate are copied onto an existing project.
+    pub async fn ensure_manifest_project(&self, manifest: &ProjectManifest) -> Result<Project> {
+        let name = manifest.project_name();
+        let directory = manifest.directory().to_string_lossy().to_string();
+
+        let Some(mut project) = self.repository.get_project_by_name(&name).await? else {
+            let mut project = Project::new(name, manifest.description.clone());
+            project.directory_path = Some(directory);
+            project.is_client_project = manifest.client.is_some();
+            project.billable = manifest.billable.unwrap_or(true);
+            project.hourly_rate_cents = manifest.hourly_rate_cents;
+            self.repository.create_project(&project).await?;
+            return Ok(project);
+        };
+
+        let before = project.clone();
+        project.directory_path = Some(directory);
+        project.is_client_project |= manifest.client.is_some();
+        if manifest.description.is_some() {
+            project.description = manifest.description.clone();
+        }
+        if let Some(billable) = manifest.billable {
+            project.billable = billable;
+        }
+        if manifest.hourly_rate_cents.is_some() {
+            project.hourly_rate_cents = manifest.hourly_rate_cents;
+        }
+        if project != before {
+            project.updated_at = Utc::now();
+            self.repository.update_project(&project).await?;
+        }
+        Ok(project)
+    }
+
     pub async fn update_project(&self, name: &str, new_description: Option<String>) -> Result<()> {
         let mut project = self
             .repository
@@ -51,6 +166,32 @@ impl ProjectService {
         self.repository.update_project(&project).await
     }
 
+    pub async fn set_hourly_rate(&self, name: &str, hourly_rate_cents: Option<i64>) -> Result<()> {
+        let mut project = self
+            .repository
+            .get_project_by_name(name)
+            .await?
+            .ok_or_else(|| TimeSpanError::ProjectNotFound(name.to_string()))?;
+
+        project.set_hourly_rate(hourly_rate_cents);
+        self.repository.update_project(&project).await
+    }
+
+    pub async fn set_rounding_rule(
+        &self,
+        name: &str,
+        rounding_rule: Option<RoundingRule>,
+    ) -> Result<()> {
+        let mut project = self
+            .repository
+            .get_project_by_name(name)
+            .await?
+            .ok_or_else(|| TimeSpanError::ProjectNotFound(name.to_string()))?;
+
+        project.set_rounding_rule(rounding_rule);
+        self.repository.update_project(&project).await
+    }
+
     pub async fn delete_project(&self, name: &str) -> Result<()> {
         let project = self
             .repository
@@ -62,19 +203,114 @@ impl ProjectService {
     }
 }
 
+/// Canonical form of `path`, resolving the deepest ancestor that exists so
+/// paths that haven't been created yet still compare correctly
+fn canonical_path(path: &Path) -> PathBuf {
+    if let Ok(canonical) = std::fs::canonicalize(path) {
+        return canonical;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => canonical_path(parent).join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
 pub struct TimeTrackingService {
     repository: Arc<dyn Repository>,
+    references: ReferenceConfig,
+}
+
+/// Result of moving the running timer to a new branch
+#[derive(Debug, Clone)]
+pub struct BranchSwitch {
+    /// Time tracked on the previous branch
+    pub stopped: TimeEntry,
+    pub timer: Timer,
+    /// Ticket id of the new branch
+    pub ticket: Option<String>,
+}
+
+/// Result of stopping the timer with its commits linked
+#[derive(Debug)]
+pub struct StoppedTimer {
+    pub entry: TimeEntry,
+    /// Why the commits couldn't be read; the entry is saved without them
+    pub link_error: Option<TimeSpanError>,
+}
+
+/// Result of indexing the references of existing time entries again
+#[derive(Debug, Default)]
+pub struct ReferenceReindex {
+    /// Time entries that were indexed
+    pub entries: usize,
+    /// Linked commits that are no longer in the repository, so their
+    /// messages weren't indexed
+    pub missing_commits: Vec<String>,
+}
+
+/// Replace the previous branch's ticket on `timer` with `ticket`. A task
+/// description the user typed is kept when the new branch has no ticket.
+fn label_with_ticket(
+    timer: &mut Timer,
+    previous: Option<String>,
+    ticket: Option<String>,
+    branches: &BranchConfig,
+) {
+    match branches.ticket_field {
+        TicketField::Task => {
+            if ticket.is_some() || timer.task_description == previous {
+                timer.task_description = ticket;
+            }
+        }
+        TicketField::Tag => {
+            if let Some(previous) = previous {
+                timer.tags.retain(|tag| *tag != previous);
+            }
+            if let Some(ticket) = ticket {
+                timer.add_tag(ticket);
+            }
+        }
+    }
 }
 
 impl TimeTrackingService {
     pub fn new(repository: Arc<dyn Repository>) -> Self {
-        Self { repository }
+        Self::with_references(repository, ReferenceConfig::default())
+    }
+
+    /// Track time, indexing task descriptions with the given reference patterns
+    pub fn with_references(repository: Arc<dyn Repository>, references: ReferenceConfig) -> Self {
+        Self {
+            repository,
+            references,
+        }
     }
 
     pub async fn start_timer(
         &self,
         project_name: &str,
         task_description: Option<&str>,
+    ) -> Result<Timer> {
+        self.start_timer_on_branch(
+            project_name,
+            task_description,
+            None,
+            None,
+            &BranchConfig::default(),
+        )
+        .await
+    }
+
+    /// Start a timer that records the `branch` checked out in `repository`.
+    /// When branch switching is enabled the branch's ticket id labels the
+    /// timer.
+    pub async fn start_timer_on_branch(
+        &self,
+        project_name: &str,
+        task_description: Option<&str>,
+        repository: Option<&Path>,
+        branch: Option<&str>,
+        branches: &BranchConfig,
     ) -> Result<Timer> {
         // Check if there's already an active timer
         if let Some(active) = self.repository.get_active_timer().await? {
@@ -88,12 +324,23 @@ impl TimeTrackingService {
             .await?
             .ok_or_else(|| TimeSpanError::ProjectNotFound(project_name.to_string()))?;
 
-        let timer = Timer::new(
+        let mut timer = Timer::new(
             project.id,
             project.name,
             task_description.map(|s| s.to_string()),
             Utc::now(),
         );
+        timer.repository = repository.map(|path| path.display().to_string());
+        if let Some(branch) = branch {
+            timer.branch = Some(branch.to_string());
+            if branches.enabled {
+                match branches.ticket_field {
+                    // A task given on the command line wins over the ticket
+                    TicketField::Task if task_description.is_some() => {}
+                    _ => label_with_ticket(&mut timer, None, branches.ticket(branch), branches),
+                }
+            }
+        }
 
         // Save the active timer
         self.repository.save_active_timer(&timer).await?;
@@ -102,6 +349,19 @@ impl TimeTrackingService {
     }
 
     pub async fn stop_timer(&self) -> Result<TimeEntry> {
+        Ok(self.stop_timer_linking(None).await?.entry)
+    }
+
+    /// Stop the timer and attach the commits made in the project's
+    /// repository while it ran. Projects without a directory get no commits.
+    /// The timer stops even when the commits can't be read, e.g. when the
+    /// directory isn't a repository or has no commits yet; `link_error`
+    /// says why.
+    pub async fn stop_timer_with_commits(&self, git_service: &GitService) -> Result<StoppedTimer> {
+        self.stop_timer_linking(Some(git_service)).await
+    }
+
+    async fn stop_timer_linking(&self, git_service: Option<&GitService>) -> Result<StoppedTimer> {
         let timer = self
             .repository
             .get_active_timer()
@@ -109,7 +369,109 @@ impl TimeTrackingService {
             .ok_or(TimeSpanError::NoActiveTimer)?;
 
         let end_time = Utc::now();
+        let mut commits = Vec::new();
+        let mut link_error = None;
+        if let Some(git_service) = git_service {
+            let directory = self
+                .repository
+                .get_project_by_id(timer.project_id)
+                .await?
+                .and_then(|project| project.directory_path);
+            if let Some(directory) = directory {
+                match git_service
+                    .commits_between(Path::new(&directory), timer.start_time, end_time)
+                    .await
+                {
+                    Ok(linked) => commits = linked,
+                    Err(e) => link_error = Some(e),
+                }
+            }
+        }
 
+        let entry = self.finish_timer(timer, end_time, &commits).await?;
+
+        // Clear the active timer
+        self.repository.clear_active_timer().await?;
+
+        Ok(StoppedTimer { entry, link_error })
+    }
+
+    /// Split the running timer when `branch` differs from the branch it was
+    /// started on: the time so far is saved as an entry and a new timer
+    /// starts on `branch`, labelled with its ticket id and moved to the
+    /// project its branch rule names. Only a checkout in the timer's own
+    /// `repository` counts. Does nothing without a running timer, for timers
+    /// that didn't record a branch and repository, or on a detached HEAD.
+    pub async fn switch_branch(
+        &self,
+        repository: Option<&Path>,
+        branch: Option<&str>,
+        branches: &BranchConfig,
+    ) -> Result<Option<BranchSwitch>> {
+        let (Some(timer), Some(branch)) = (self.repository.get_active_timer().await?, branch)
+        else {
+            return Ok(None);
+        };
+        let Some(previous_branch) = timer.branch.clone() else {
+            return Ok(None);
+        };
+        if previous_branch == branch {
+            return Ok(None);
+        }
+        let repository = repository.map(|path| path.display().to_string());
+        if timer.repository.is_none() || timer.repository != repository {
+            return Ok(None);
+        }
+
+        let project = match branches.project_for(branch) {
+            Some(name) => self
+                .repository
+                .get_project_by_name(name)
+                .await?
+                .ok_or_else(|| TimeSpanError::ProjectNotFound(name.to_string()))?,
+            None => self
+                .repository
+                .get_project_by_id(timer.project_id)
+                .await?
+                .ok_or_else(|| TimeSpanError::ProjectNotFound(timer.project_name.clone()))?,
+        };
+
+        let now = Utc::now();
+        let mut next = Timer::new(
+            project.id,
+            project.name,
+            timer.task_description.clone(),
+            now,
+        );
+        next.tags = timer.tags.clone();
+        next.branch = Some(branch.to_string());
+        next.repository = repository;
+        let ticket = branches.ticket(branch);
+        label_with_ticket(
+            &mut next,
+            branches.ticket(&previous_branch),
+            ticket.clone(),
+            branches,
+        );
+
+        let stopped = self.finish_timer(timer, now, &[]).await?;
+        self.repository.save_active_timer(&next).await?;
+
+        Ok(Some(BranchSwitch {
+            stopped,
+            timer: next,
+            ticket,
+        }))
+    }
+
+    /// Save the timer's time up to `end_time` as a time entry linked to
+    /// `commits`, indexing the references in its task and commit messages
+    async fn finish_timer(
+        &self,
+        timer: Timer,
+        end_time: DateTime<Utc>,
+        commits: &[GitCommit],
+    ) -> Result<TimeEntry> {
         // Create time entry from timer
         let mut time_entry = TimeEntry::new(
             timer.project_id,
@@ -117,6 +479,8 @@ impl TimeTrackingService {
             timer.task_description,
             timer.start_time,
         );
+        time_entry.branch = timer.branch;
+        time_entry.commit_hashes = commits.iter().map(|commit| commit.hash.clone()).collect();
 
         // Set tags from timer
         for tag in timer.tags {
@@ -129,12 +493,89 @@ impl TimeTrackingService {
         // Save the time entry
         self.repository.create_time_entry(&time_entry).await?;
 
-        // Clear the active timer
-        self.repository.clear_active_timer().await?;
+        let mut references = self.task_references(&time_entry);
+        for commit in commits {
+            references.extend(self.commit_references(&time_entry, commit));
+        }
+        self.repository.save_entry_references(&references).await?;
 
         Ok(time_entry)
     }
 
+    /// Save changes to a time entry and re-index the references in its task
+    pub async fn update_time_entry(&self, entry: &TimeEntry) -> Result<()> {
+        self.repository.update_time_entry(entry).await?;
+        self.repository
+            .replace_entry_references(
+                entry.id,
+                ReferenceSource::Task,
+                &self.task_references(entry),
+            )
+            .await
+    }
+
+    /// Index the references of every time entry again, e.g. for entries
+    /// tracked before references were indexed. Task references are
+    /// replaced; references in linked commit messages are added for the
+    /// commits that can still be read from the project's repository.
+    pub async fn reindex_references(&self, git_service: &GitService) -> Result<ReferenceReindex> {
+        let mut result = ReferenceReindex::default();
+        for project in self.repository.list_projects().await? {
+            for entry in self
+                .repository
+                .list_time_entries_by_project(project.id)
+                .await?
+            {
+                self.repository
+                    .replace_entry_references(
+                        entry.id,
+                        ReferenceSource::Task,
+                        &self.task_references(&entry),
+                    )
+                    .await?;
+
+                let details = self.entry_details(entry, git_service).await?;
+                let references: Vec<EntryReference> = details
+                    .commits
+                    .iter()
+                    .flat_map(|commit| self.commit_references(&details.entry, commit))
+                    .collect();
+                self.repository.save_entry_references(&references).await?;
+
+                result.entries += 1;
+                result.missing_commits.extend(details.missing_commits);
+            }
+        }
+        Ok(result)
+    }
+
+    fn task_references(&self, entry: &TimeEntry) -> Vec<EntryReference> {
+        let Some(task) = &entry.task_description else {
+            return Vec::new();
+        };
+        self.references
+            .extract(task)
+            .into_iter()
+            .map(|reference| EntryReference {
+                time_entry_id: entry.id,
+                reference,
+                source: ReferenceSource::Task,
+            })
+            .collect()
+    }
+
+    fn commit_references(&self, entry: &TimeEntry, commit: &GitCommit) -> Vec<EntryReference> {
+        self.references
+            .extract(&commit.message)
+            .into_iter()
+            .map(|reference| EntryReference {
+                time_entry_id: entry.id,
+                reference,
+                source: ReferenceSource::Commit,
+            })
+            .collect()
+    }
+
     pub async fn get_current_status(&self) -> Result<String> {
         match self.repository.get_active_timer().await? {
             Some(timer) => {
@@ -155,6 +596,89 @@ impl TimeTrackingService {
         }
     }
 
+    /// Look up a time entry by its id or a unique prefix of it
+    pub async fn find_entry(&self, id: &str) -> Result<TimeEntry> {
+        let id = id.trim();
+        if id.len() < 4 {
+            return Err(TimeSpanError::InvalidInput(
+                "Give at least 4 characters of the entry id".to_string(),
+            ));
+        }
+
+        let mut matches = self.repository.find_time_entries_by_id_prefix(id).await?;
+        match matches.len() {
+            0 => Err(TimeSpanError::InvalidInput(format!(
+                "No time entry with id {}",
+                id
+            ))),
+            1 => Ok(matches.remove(0)),
+            count => Err(TimeSpanError::InvalidInput(format!(
+                "{} time entries start with {}; give more of the id",
+                count, id
+            ))),
+        }
+    }
+
+    /// The entry with its linked commits read from the project's repository
+    pub async fn entry_details(
+        &self,
+        entry: TimeEntry,
+        git_service: &GitService,
+    ) -> Result<EntryDetails> {
+        let directory = self
+            .repository
+            .get_project_by_id(entry.project_id)
+            .await?
+            .and_then(|project| project.directory_path);
+
+        let mut commits = Vec::new();
+        let mut missing_commits = Vec::new();
+        for hash in &entry.commit_hashes {
+            let commit = match &directory {
+                Some(directory) => git_service
+                    .get_commit(Path::new(directory), hash)
+                    .await
+                    .ok(),
+                None => None,
+            };
+            match commit {
+                Some(commit) => commits.push(commit),
+                None => missing_commits.push(hash.clone()),
+            }
+        }
+
+        Ok(EntryDetails {
+            entry,
+            commits,
+            missing_commits,
+        })
+    }
+
+    pub fn export_entry_json(&self, details: &EntryDetails) -> Result<String> {
+        serde_json::to_string_pretty(details).map_err(|e| {
+            TimeSpanError::InvalidDuration(format!("Failed to serialize time entry: {}", e))
+        })
+    }
+
+    /// Save each gap of untracked activity as a time entry tagged
+    /// `git-activity`
+    pub async fn add_activity_entries(&self, gaps: &[ActivityGap]) -> Result<Vec<TimeEntry>> {
+        let mut entries = Vec::with_capacity(gaps.len());
+        for gap in gaps {
+            let mut entry = TimeEntry::new(
+                gap.project_id,
+                gap.project_name.clone(),
+                Some("Untracked working-tree activity".to_string()),
+                gap.start,
+            );
+            entry.stop(gap.end)?;
+            entry.tags.push("git-activity".to_string());
+            self.repository.create_time_entry(&entry).await?;
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
     pub async fn add_tag_to_active_timer(&self, tag: String) -> Result<()> {
         let mut timer = self
             .repository
@@ -177,61 +701,298 @@ impl ReportingService {
     }
 
     pub async fn generate_daily_report(&self, date: DateTime<Utc>) -> Result<TimeReport> {
-        let start_of_day = date
-            .date_naive()
-            .and_hms_opt(0, 0, 0)
-            .unwrap()
-            .and_local_timezone(Utc)
-            .unwrap();
-        let end_of_day = date
-            .date_naive()
-            .and_hms_opt(23, 59, 59)
-            .unwrap()
-            .and_local_timezone(Utc)
-            .unwrap();
+        self.generate_period_report(ReportPeriod::Day, date).await
+    }
+
+    pub async fn generate_weekly_report(&self, date: DateTime<Utc>) -> Result<TimeReport> {
+        self.generate_period_report(ReportPeriod::Week, date).await
+    }
 
+    pub async fn generate_monthly_report(&self, date: DateTime<Utc>) -> Result<TimeReport> {
+        self.generate_period_report(ReportPeriod::Month, date).await
+    }
+
+    /// Report for the day, week or month containing `date`
+    pub async fn generate_period_report(
+        &self,
+        period: ReportPeriod,
+        date: DateTime<Utc>,
+    ) -> Result<TimeReport> {
+        let range = period.range_containing(date);
         let entries = self
             .repository
-            .list_time_entries_by_date_range(start_of_day, end_of_day)
+            .list_time_entries_by_date_range(range.start, range.end)
             .await?;
 
-        Ok(TimeReport::new(entries, start_of_day, end_of_day))
+        Ok(TimeReport::new(entries, range.start, range.end))
     }
 
-    pub async fn generate_weekly_report(&self, date: DateTime<Utc>) -> Result<TimeReport> {
-        use chrono::{Datelike, Weekday};
-
-        let days_from_monday = match date.weekday() {
-            Weekday::Mon => 0,
-            Weekday::Tue => 1,
-            Weekday::Wed => 2,
-            Weekday::Thu => 3,
-            Weekday::Fri => 4,
-            Weekday::Sat => 5,
-            Weekday::Sun => 6,
+    /// Time per ticket or issue reference for entries that started within
+    /// `date_range`, most time first
+    pub async fn generate_ticket_report(
+        &self,
+        date_range: &DateRange,
+    ) -> Result<Vec<TicketSummary>> {
+        let entries = self
+            .repository
+            .list_time_entries_by_date_range(date_range.start, date_range.end)
+            .await?;
+        let references = self
+            .repository
+            .list_entry_references(date_range.start, date_range.end)
+            .await?;
+
+        let mut summaries: Vec<TicketSummary> = Vec::new();
+        for reference in &references {
+            let Some(entry) = entries
+                .iter()
+                .find(|entry| entry.id == reference.time_entry_id && entry.end_time.is_some())
+            else {
+                continue;
+            };
+            let seconds = entry.duration.map(|d| d.num_seconds()).unwrap_or(0);
+            let end_time = entry.end_time.unwrap_or(entry.start_time);
+
+            let summary = match summaries
+                .iter()
+                .position(|summary| summary.reference == reference.reference)
+            {
+                Some(index) => &mut summaries[index],
+                None => {
+                    summaries.push(TicketSummary {
+                        reference: reference.reference.clone(),
+                        total_seconds: 0,
+                        entry_count: 0,
+                        commit_count: 0,
+                        projects: Vec::new(),
+                        first_tracked: entry.start_time,
+                        last_tracked: end_time,
+                    });
+                    summaries.last_mut().unwrap()
+                }
+            };
+            summary.total_seconds += seconds;
+            summary.entry_count += 1;
+            if reference.source == ReferenceSource::Commit {
+                summary.commit_count += 1;
+            }
+            if !summary.projects.contains(&entry.project_name) {
+                summary.projects.push(entry.project_name.clone());
+            }
+            summary.first_tracked = summary.first_tracked.min(entry.start_time);
+            summary.last_tracked = summary.last_tracked.max(end_time);
+        }
+
+        summaries.sort_by(|a, b| {
+            b.total_seconds
+                .cmp(&a.total_seconds)
+                .then_with(|| a.reference.cmp(&b.reference))
+        });
+        Ok(summaries)
+    }
+
+    /// One row per reference with decimal hours, for pasting into an issue
+    /// tracker's time log
+    pub fn export_ticket_report_csv(&self, summaries: &[TicketSummary]) -> String {
+        let mut out = String::from("reference,hours,seconds,entries,commits,projects,first,last\n");
+        for summary in summaries {
+            out.push_str(&format!(
+                "{},{:.2},{},{},{},{},{},{}\n",
+                csv_field(&summary.reference),
+                summary.total_seconds as f64 / 3600.0,
+                summary.total_seconds,
+                summary.entry_count,
+                summary.commit_count,
+                csv_field(&summary.projects.join("; ")),
+                summary.first_tracked.format("%Y-%m-%d"),
+                summary.last_tracked.format("%Y-%m-%d")
+            ));
+        }
+        out
+    }
+
+    pub fn export_ticket_report_json(&self, summaries: &[TicketSummary]) -> Result<String> {
+        serde_json::to_string_pretty(summaries).map_err(|e| {
+            TimeSpanError::InvalidDuration(format!("Failed to serialize ticket report: {}", e))
+        })
+    }
+
+    /// Time per ticket within `date_range`. Entries are grouped by the ticket
+    /// id in their branch name, falling back to the branch itself.
+    pub async fn generate_branch_report(
+        &self,
+        date_range: &DateRange,
+        branches: &BranchConfig,
+    ) -> Result<Vec<BranchTimeSummary>> {
+        let entries = self
+            .repository
+            .list_time_entries_by_date_range(date_range.start, date_range.end)
+            .await?;
+
+        let mut summaries: Vec<BranchTimeSummary> = Vec::new();
+        for entry in entries.iter().filter(|entry| entry.end_time.is_some()) {
+            let ticket = entry
+                .branch
+                .as_deref()
+                .and_then(|branch| branches.ticket(branch));
+            let key = ticket
+                .clone()
+                .or_else(|| entry.branch.clone())
+                .unwrap_or_else(|| "(no branch)".to_string());
+            let seconds = entry.duration.map(|d| d.num_seconds()).unwrap_or(0);
+
+            let summary = match summaries.iter().position(|summary| summary.key == key) {
+                Some(index) => &mut summaries[index],
+                None => {
+                    summaries.push(BranchTimeSummary {
+                        key,
+                        ticket,
+                        branches: Vec::new(),
+                        projects: Vec::new(),
+                        entry_count: 0,
+                        total_seconds: 0,
+                    });
+                    summaries.last_mut().unwrap()
+                }
+            };
+            summary.entry_count += 1;
+            summary.total_seconds += seconds;
+            if let Some(branch) = &entry.branch {
+                if !summary.branches.contains(branch) {
+                    summary.branches.push(branch.clone());
+                }
+            }
+            if !summary.projects.contains(&entry.project_name) {
+                summary.projects.push(entry.project_name.clone());
+            }
+        }
+
+        summaries.sort_by(|a, b| {
+            b.total_seconds
+                .cmp(&a.total_seconds)
+                .then_with(|| a.key.cmp(&b.key))
+        });
+        Ok(summaries)
+    }
+
+    /// Working-tree activity recorded by `git watch` within `date_range`
+    /// that no time entry (or the running timer) covers
+    pub async fn find_activity_gaps(&self, date_range: &DateRange) -> Result<Vec<ActivityGap>> {
+        let pulses = self
+            .repository
+            .list_activity_pulses(date_range.start, date_range.end)
+            .await?;
+        if pulses.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Entries that started the day before can still overlap the range
+        let mut entries = self
+            .repository
+            .list_time_entries_by_date_range(
+                date_range.start - chrono::Duration::days(1),
+                date_range.end,
+            )
+            .await?;
+        if let Some(timer) = self.repository.get_active_timer().await? {
+            entries.push(TimeEntry::new(
+                timer.project_id,
+                timer.project_name,
+                None,
+                timer.start_time,
+            ));
+        }
+
+        Ok(ActivityGap::find(
+            &pulses,
+            &entries,
+            chrono::Duration::minutes(ACTIVITY_MAX_GAP_MINUTES),
+            chrono::Duration::minutes(ACTIVITY_MIN_GAP_MINUTES),
+        ))
+    }
+
+    pub fn export_gap_report_json(&self, gaps: &[ActivityGap]) -> Result<String> {
+        serde_json::to_string_pretty(gaps).map_err(|e| {
+            TimeSpanError::InvalidDuration(format!("Failed to serialize gap report: {}", e))
+        })
+    }
+
+    pub fn export_branch_report_json(&self, summaries: &[BranchTimeSummary]) -> Result<String> {
+        serde_json::to_string_pretty(summaries).map_err(|e| {
+            TimeSpanError::InvalidDuration(format!("Failed to serialize branch report: {}", e))
+        })
+    }
+
+    /// Compare the period containing `date` with the period `periods_back`
+    /// periods earlier (1 = the immediately preceding period)
+    pub async fn compare_periods(
+        &self,
+        period: ReportPeriod,
+        date: DateTime<Utc>,
+        periods_back: u32,
+    ) -> Result<PeriodComparison> {
+        if periods_back == 0 {
+            return Err(TimeSpanError::InvalidInput(
+                "Must compare against at least one period back".to_string(),
+            ));
+        }
+
+        let previous_date = match period {
+            ReportPeriod::Day => date - chrono::Duration::days(periods_back as i64),
+            ReportPeriod::Week => date - chrono::Duration::weeks(periods_back as i64),
+            ReportPeriod::Month => date
+                .checked_sub_months(chrono::Months::new(periods_back))
+                .ok_or_else(|| {
+                    TimeSpanError::InvalidInput(format!(
+                        "Cannot go back {} months from {}",
+                        periods_back,
+                        date.date_naive()
+                    ))
+                })?,
         };
 
-        let monday = date - chrono::Duration::days(days_from_monday as i64);
-        let start_of_week = monday
-            .date_naive()
-            .and_hms_opt(0, 0, 0)
-            .unwrap()
-            .and_local_timezone(Utc)
-            .unwrap();
-        let end_of_week = start_of_week + chrono::Duration::days(6);
-        let end_of_week = end_of_week
-            .date_naive()
-            .and_hms_opt(23, 59, 59)
-            .unwrap()
-            .and_local_timezone(Utc)
-            .unwrap();
+        let current = self.generate_period_report(period, date).await?;
+        let previous = self.generate_period_report(period, previous_date).await?;
+
+        Ok(PeriodComparison::new(period, &current, &previous))
+    }
+
+    /// Projects × days grid for the week (Monday to Sunday) containing `date`
+    pub async fn generate_timesheet(&self, date: DateTime<Utc>) -> Result<Timesheet> {
+        let report = self.generate_weekly_report(date).await?;
+        Ok(Timesheet::from_report(&report))
+    }
+
+    /// Total tracked time per day for every day from `start` to `end`
+    /// inclusive, with zero for days without entries
+    pub async fn generate_daily_totals(
+        &self,
+        start: chrono::NaiveDate,
+        end: chrono::NaiveDate,
+    ) -> Result<std::collections::BTreeMap<chrono::NaiveDate, chrono::Duration>> {
+        let range_start = start.and_hms_opt(0, 0, 0).unwrap().and_utc();
+        let range_end = end.and_hms_opt(23, 59, 59).unwrap().and_utc();
+
+        let mut totals = std::collections::BTreeMap::new();
+        let mut day = start;
+        while day <= end {
+            totals.insert(day, chrono::Duration::zero());
+            day += chrono::Duration::days(1);
+        }
 
         let entries = self
             .repository
-            .list_time_entries_by_date_range(start_of_week, end_of_week)
+            .list_time_entries_by_date_range(range_start, range_end)
             .await?;
+        for entry in entries {
+            if let Some(duration) = entry.duration {
+                *totals
+                    .entry(entry.start_time.date_naive())
+                    .or_insert_with(chrono::Duration::zero) += duration;
+            }
+        }
 
-        Ok(TimeReport::new(entries, start_of_week, end_of_week))
+        Ok(totals)
     }
 
     pub async fn generate_project_report(&self, project_name: &str) -> Result<TimeReport> {
@@ -261,17 +1022,90 @@ impl ReportingService {
         Ok(TimeReport::new(entries, start, end))
     }
 
+    /// Apply `default_rule` and any per-project rounding overrides to a report.
+    /// Stored entry durations are never modified.
+    pub async fn apply_rounding(
+        &self,
+        report: &mut TimeReport,
+        default_rule: Option<RoundingRule>,
+    ) -> Result<()> {
+        let overrides = self
+            .repository
+            .list_projects()
+            .await?
+            .into_iter()
+            .filter_map(|p| p.rounding_rule.map(|rule| (p.name, rule)))
+            .collect::<std::collections::HashMap<_, _>>();
+
+        if default_rule.is_none() && overrides.is_empty() {
+            return Ok(());
+        }
+
+        report.apply_rounding(default_rule, &overrides);
+        Ok(())
+    }
+
     pub fn export_report_json(&self, report: &TimeReport) -> Result<String> {
         serde_json::to_string_pretty(report).map_err(|e| {
             TimeSpanError::InvalidDuration(format!("Failed to serialize report: {}", e))
         })
     }
+
+    pub fn export_timesheet_json(&self, timesheet: &Timesheet) -> Result<String> {
+        serde_json::to_string_pretty(timesheet).map_err(|e| {
+            TimeSpanError::InvalidDuration(format!("Failed to serialize timesheet: {}", e))
+        })
+    }
+
+    /// CSV with a header row of dates, one row per project and a final
+    /// totals row
+    pub fn export_timesheet_csv(&self, timesheet: &Timesheet, decimal: bool) -> String {
+        let mut out = String::from("Project");
+        for day in &timesheet.days {
+            out.push_str(&format!(",{}", day.format("%Y-%m-%d")));
+        }
+        out.push_str(",Total\n");
+
+        let mut push_row = |name: &str, cells: &[i64], total: i64| {
+            out.push_str(&csv_field(name));
+            for seconds in cells {
+                out.push(',');
+                out.push_str(&Timesheet::format_seconds(*seconds, decimal));
+            }
+            out.push(',');
+            out.push_str(&Timesheet::format_seconds(total, decimal));
+            out.push('\n');
+        };
+
+        for row in &timesheet.rows {
+            push_row(&row.project_name, &row.daily_seconds, row.total_seconds);
+        }
+        push_row("Total", &timesheet.daily_totals, timesheet.total_seconds);
+
+        out
+    }
+
+    pub fn export_comparison_json(&self, comparison: &PeriodComparison) -> Result<String> {
+        serde_json::to_string_pretty(comparison).map_err(|e| {
+            TimeSpanError::InvalidDuration(format!("Failed to serialize comparison: {}", e))
+        })
+    }
+}
+
+/// Quote a CSV field when it contains a delimiter, quote or newline
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::repository::SqliteRepository;
+    use crate::services::test_git::commit_file;
     use chrono::TimeZone;
 
     async fn setup_services() -> (ProjectService, TimeTrackingService, ReportingService) {
@@ -296,6 +1130,153 @@ mod tests {
         assert_eq!(project.description, Some("Test description".to_string()));
     }
 
+    #[tokio::test]
+    async fn test_find_project_for_directory() {
+        let repo = Arc::new(SqliteRepository::in_memory().unwrap());
+        let project_service = ProjectService::new(repo.clone());
+        let dir = tempfile::TempDir::new().unwrap();
+        let nested = dir.path().join("acme").join("web");
+        std::fs::create_dir_all(nested.join("src")).unwrap();
+
+        for (name, path) in [
+            ("Acme", dir.path().join("acme")),
+            ("Acme Web", nested.clone()),
+        ] {
+            let mut project = Project::new(name.to_string(), None);
+            project.directory_path = Some(path.to_string_lossy().to_string());
+            repo.create_project(&project).await.unwrap();
+        }
+
+        let found = |path: PathBuf| {
+            let project_service = &project_service;
+            async move {
+                project_service
+                    .find_project_for_directory(&path)
+                    .await
+                    .unwrap()
+                    .map(|project| project.name)
+            }
+        };
+
+        assert_eq!(found(nested.join("src")).await.as_deref(), Some("Acme Web"));
+        assert_eq!(
+            found(dir.path().join("acme").join("api")).await.as_deref(),
+            Some("Acme")
+        );
+        assert_eq!(found(dir.path().join("other")).await, None);
+
+        // Archived projects are never picked by directory
+        let mut web = repo.get_project_by_name("Acme Web").await.unwrap().unwrap();
+        web.archived_at = Some(Utc::now());
+        repo.update_project(&web).await.unwrap();
+        assert_eq!(found(nested.join("src")).await.as_deref(), Some("Acme"));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_directory_project() {
+        let repo = Arc::new(SqliteRepository::in_memory().unwrap());
+        let project_service = ProjectService::new(repo.clone());
+        let git_service = GitService::new(repo.clone());
+        let dir = tempfile::TempDir::new().unwrap();
+
+        let acme = dir.path().join("acme");
+        let shop = dir.path().join("shop-checkout");
+        let notes = dir.path().join("notes");
+        for path in [acme.join("src"), shop.join("src"), notes.join("2024")] {
+            std::fs::create_dir_all(path).unwrap();
+        }
+        git2::Repository::init(&shop)
+            .unwrap()
+            .remote("origin", "git@example.com:team/shop.git")
+            .unwrap();
+        std::fs::write(
+            notes.join(crate::manifest::MANIFEST_FILE),
+            "[project]\nname = \"Notes\"\n",
+        )
+        .unwrap();
+
+        let mut project = Project::new("Acme".to_string(), None);
+        project.directory_path = Some(acme.to_string_lossy().to_string());
+        repo.create_project(&project).await.unwrap();
+        repo.create_project(&Project::new("shop".to_string(), None))
+            .await
+            .unwrap();
+
+        let resolve = |path: PathBuf| {
+            let (project_service, git_service) = (&project_service, &git_service);
+            async move {
+                project_service
+                    .resolve_directory_project(&path, git_service)
+                    .await
+                    .unwrap()
+                    .map(|(project, rule)| (project.name, rule))
+            }
+        };
+
+        let (name, rule) = resolve(acme.join("src")).await.unwrap();
+        assert_eq!(name, "Acme");
+        assert!(matches!(rule, ProjectMatch::Directory(_)));
+
+        // Matched by the repository name in the origin URL
+        let (name, rule) = resolve(shop.join("src")).await.unwrap();
+        assert_eq!(name, "shop");
+        assert!(matches!(rule, ProjectMatch::Repository(root) if root.ends_with("shop-checkout")));
+
+        // The manifest project is created, after which its directory matches
+        let (name, rule) = resolve(notes.join("2024")).await.unwrap();
+        assert_eq!(name, "Notes");
+        assert!(matches!(rule, ProjectMatch::Manifest(_)));
+        let (_, rule) = resolve(notes.join("2024")).await.unwrap();
+        assert!(matches!(rule, ProjectMatch::Directory(_)));
+
+        assert_eq!(resolve(dir.path().to_path_buf()).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_ensure_manifest_project() {
+        let (project_service, _, _) = setup_services().await;
+        let dir = tempfile::TempDir::new().unwrap();
+        let manifest_path = dir.path().join(crate::manifest::MANIFEST_FILE);
+        std::fs::write(
+            &manifest_path,
+            "[project]\nname = \"Website\"\nclient = \"Acme\"\nhourly_rate = 95\n",
+        )
+        .unwrap();
+
+        let manifest = ProjectManifest::load(&manifest_path).unwrap();
+        let project = project_service
+            .ensure_manifest_project(&manifest)
+            .await
+            .unwrap();
+        assert_eq!(project.name, "[CLIENT] Acme/Website");
+        assert!(project.is_client_project);
+        assert!(project.billable);
+        assert_eq!(project.hourly_rate_cents, Some(9_500));
+
+        // Later edits to the manifest are copied onto the stored project
+        std::fs::write(
+            &manifest_path,
+            "[project]\nname = \"Website\"\nclient = \"Acme\"\nbillable = false\n",
+        )
+        .unwrap();
+        let manifest = ProjectManifest::load(&manifest_path).unwrap();
+        project_service
+            .ensure_manifest_project(&manifest)
+            .await
+            .unwrap();
+
+        let stored = project_service
+            .get_project("[CLIENT] Acme/Website")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.id, project.id);
+        assert!(!stored.billable);
+        assert_eq!(stored.hourly_rate_cents, Some(9_500));
+        assert_eq!(stored.directory_path.as_deref(), dir.path().to_str());
+        assert_eq!(project_service.list_projects().await.unwrap().len(), 1);
+    }
+
     #[tokio::test]
     async fn test_create_duplicate_project() {
         let (project_service, _, _) = setup_services().await;
@@ -342,6 +1323,385 @@ mod tests {
         assert!(!entry.is_running());
     }
 
+    #[tokio::test]
+    async fn test_stop_links_commits_made_during_timer() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let git_repo = git2::Repository::init(dir.path()).unwrap();
+        git_repo
+            .config()
+            .unwrap()
+            .set_str("user.email", "me@example.com")
+            .unwrap();
+        let me = ("Me", "me@example.com");
+
+        let repo = Arc::new(SqliteRepository::in_memory().unwrap());
+        let project_service = ProjectService::new(repo.clone());
+        let tracking_service = TimeTrackingService::new(repo.clone());
+        let git_service = GitService::new(repo.clone());
+        let mut project = project_service.create_project("App", None).await.unwrap();
+        project.directory_path = Some(dir.path().to_string_lossy().to_string());
+        repo.update_project(&project).await.unwrap();
+
+        // A directory that isn't a repository reports why nothing was linked
+        let plain = tempfile::TempDir::new().unwrap();
+        project.directory_path = Some(plain.path().to_string_lossy().to_string());
+        repo.update_project(&project).await.unwrap();
+        tracking_service.start_timer("App", None).await.unwrap();
+        let stopped = tracking_service
+            .stop_timer_with_commits(&git_service)
+            .await
+            .unwrap();
+        assert!(stopped.entry.commit_hashes.is_empty());
+        assert!(stopped
+            .link_error
+            .unwrap()
+            .to_string()
+            .contains("Failed to open git repository"));
+        project.directory_path = Some(dir.path().to_string_lossy().to_string());
+        repo.update_project(&project).await.unwrap();
+
+        // Before the first commit the history can't be read, but the timer
+        // still stops
+        tracking_service.start_timer("App", None).await.unwrap();
+        let stopped = tracking_service
+            .stop_timer_with_commits(&git_service)
+            .await
+            .unwrap();
+        assert!(stopped.entry.commit_hashes.is_empty());
+        assert!(stopped.link_error.is_some());
+        assert!(repo.get_active_timer().await.unwrap().is_none());
+
+        commit_file(
+            &git_repo,
+            me,
+            Utc::now() - chrono::Duration::hours(1),
+            "before the timer",
+        );
+        tracking_service.start_timer("App", None).await.unwrap();
+        let linked = commit_file(&git_repo, me, Utc::now(), "during the timer");
+        let stopped = tracking_service
+            .stop_timer_with_commits(&git_service)
+            .await
+            .unwrap();
+        assert!(stopped.link_error.is_none());
+        let entry = stopped.entry;
+        assert_eq!(entry.commit_hashes, vec![linked.to_string()]);
+
+        let prefix = &entry.id.to_string()[..8];
+        let found = tracking_service.find_entry(prefix).await.unwrap();
+        assert_eq!(found.commit_hashes, entry.commit_hashes);
+        assert!(tracking_service.find_entry("abc").await.is_err());
+
+        let details = tracking_service
+            .entry_details(found, &git_service)
+            .await
+            .unwrap();
+        assert_eq!(details.commits.len(), 1);
+        assert_eq!(details.commits[0].message, "during the timer");
+        assert!(details.missing_commits.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ticket_report_from_tasks_and_commits() {
+        let repo = Arc::new(SqliteRepository::in_memory().unwrap());
+        let project_service = ProjectService::new(repo.clone());
+        let tracking_service = TimeTrackingService::new(repo.clone());
+        let reporting_service = ReportingService::new(repo.clone());
+        let git_service = GitService::new(repo.clone());
+        let project = project_service.create_project("App", None).await.unwrap();
+
+        tracking_service
+            .start_timer("App", Some("Fix #12 and ACME-4"))
+            .await
+            .unwrap();
+        tracking_service.stop_timer().await.unwrap();
+
+        let mut commit = crate::models::GitCommit::new(
+            "abc123".to_string(),
+            "feat: login form\n\nCloses ACME-4".to_string(),
+            "Author".to_string(),
+            "author@example.com".to_string(),
+            Utc::now() - chrono::Duration::minutes(5),
+            PathBuf::from("/app"),
+        );
+        commit.files_changed = vec!["src/login.rs".to_string()];
+        git_service
+            .import_commits(&[commit], &project, &GitImportOptions::default())
+            .await
+            .unwrap();
+
+        let today = ReportPeriod::Day.range_containing(Utc::now());
+        let report = reporting_service
+            .generate_ticket_report(&today)
+            .await
+            .unwrap();
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].reference, "ACME-4");
+        assert_eq!(report[0].entry_count, 2);
+        assert_eq!(report[0].commit_count, 1);
+        assert_eq!(report[1].reference, "#12");
+        assert_eq!(report[1].commit_count, 0);
+
+        let csv = reporting_service.export_ticket_report_csv(&report);
+        assert!(csv.starts_with("reference,hours,seconds,"));
+        assert!(csv.lines().nth(1).unwrap().starts_with("ACME-4,"));
+    }
+
+    #[tokio::test]
+    async fn test_references_from_linked_commits_updates_and_reindex() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let git_repo = git2::Repository::init(dir.path()).unwrap();
+        git_repo
+            .config()
+            .unwrap()
+            .set_str("user.email", "me@example.com")
+            .unwrap();
+        let me = ("Me", "me@example.com");
+
+        let repo = Arc::new(SqliteRepository::in_memory().unwrap());
+        let project_service = ProjectService::new(repo.clone());
+        let tracking_service = TimeTrackingService::new(repo.clone());
+        let reporting_service = ReportingService::new(repo.clone());
+        let git_service = GitService::new(repo.clone());
+        let mut project = project_service.create_project("App", None).await.unwrap();
+        project.directory_path = Some(dir.path().to_string_lossy().to_string());
+        repo.update_project(&project).await.unwrap();
+        let today = ReportPeriod::Day.range_containing(Utc::now());
+
+        commit_file(
+            &git_repo,
+            me,
+            Utc::now() - chrono::Duration::hours(1),
+            "initial",
+        );
+        tracking_service
+            .start_timer("App", Some("Fix #12"))
+            .await
+            .unwrap();
+        let linked = commit_file(&git_repo, me, Utc::now(), "feat: login\n\nCloses ACME-4");
+        let mut entry = tracking_service
+            .stop_timer_with_commits(&git_service)
+            .await
+            .unwrap()
+            .entry;
+
+        let report = reporting_service
+            .generate_ticket_report(&today)
+            .await
+            .unwrap();
+        let references: Vec<&str> = report.iter().map(|s| s.reference.as_str()).collect();
+        assert_eq!(references, vec!["#12", "ACME-4"]);
+        assert_eq!(report[1].commit_count, 1);
+
+        // Changing the task replaces its references but keeps the commit's
+        entry.task_description = Some("Fix #13".to_string());
+        tracking_service.update_time_entry(&entry).await.unwrap();
+        let report = reporting_service
+            .generate_ticket_report(&today)
+            .await
+            .unwrap();
+        let references: Vec<&str> = report.iter().map(|s| s.reference.as_str()).collect();
+        assert_eq!(references, vec!["#13", "ACME-4"]);
+
+        // An entry saved without references is picked up by a reindex
+        let mut earlier = TimeEntry::new(
+            project.id,
+            project.name.clone(),
+            Some("Review GH-7".to_string()),
+            Utc::now() - chrono::Duration::minutes(30),
+        );
+        earlier.commit_hashes = vec![linked.to_string(), "0000000".to_string()];
+        earlier
+            .stop(Utc::now() - chrono::Duration::minutes(20))
+            .unwrap();
+        repo.create_time_entry(&earlier).await.unwrap();
+
+        let result = tracking_service
+            .reindex_references(&git_service)
+            .await
+            .unwrap();
+        assert_eq!(result.entries, 2);
+        assert_eq!(result.missing_commits, vec!["0000000".to_string()]);
+
+        let report = reporting_service
+            .generate_ticket_report(&today)
+            .await
+            .unwrap();
+        let acme = report.iter().find(|s| s.reference == "ACME-4").unwrap();
+        assert_eq!(acme.entry_count, 2);
+        assert_eq!(acme.commit_count, 2);
+        assert!(report.iter().any(|s| s.reference == "GH-7"));
+        assert!(report.iter().any(|s| s.reference == "#13"));
+        assert!(!report.iter().any(|s| s.reference == "#12"));
+    }
+
+    #[tokio::test]
+    async fn test_switch_branch_splits_timer() {
+        let (project_service, tracking_service, reporting_service) = setup_services().await;
+        project_service
+            .create_project("Internal", None)
+            .await
+            .unwrap();
+        project_service
+            .create_project("[CLIENT] Acme", None)
+            .await
+            .unwrap();
+        let branches = crate::config::GitConfig::parse(
+            "[branches]\nenabled = true\n[branches.projects]\n\"feature/ACME-*\" = \"[CLIENT] Acme\"",
+        )
+        .unwrap()
+        .branches;
+        let app = Some(Path::new("/work/app"));
+
+        let timer = tracking_service
+            .start_timer_on_branch("Internal", None, app, Some("main"), &branches)
+            .await
+            .unwrap();
+        assert_eq!(timer.branch.as_deref(), Some("main"));
+        assert_eq!(timer.task_description, None);
+
+        // Same branch or a detached HEAD leaves the timer alone
+        for branch in [Some("main"), None] {
+            assert!(tracking_service
+                .switch_branch(app, branch, &branches)
+                .await
+                .unwrap()
+                .is_none());
+        }
+
+        let switch = tracking_service
+            .switch_branch(app, Some("feature/ACME-123-login"), &branches)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(switch.stopped.branch.as_deref(), Some("main"));
+        assert_eq!(switch.stopped.project_name, "Internal");
+        assert_eq!(switch.timer.project_name, "[CLIENT] Acme");
+        assert_eq!(switch.timer.task_description.as_deref(), Some("ACME-123"));
+        assert_eq!(switch.ticket.as_deref(), Some("ACME-123"));
+
+        // Back to a branch without a ticket: the ticket is cleared again
+        let back = tracking_service
+            .switch_branch(app, Some("feature/search"), &branches)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(back.timer.task_description, None);
+        assert_eq!(back.timer.project_name, "[CLIENT] Acme");
+
+        tracking_service.stop_timer().await.unwrap();
+        let today = ReportPeriod::Day.range_containing(Utc::now());
+        let report = reporting_service
+            .generate_branch_report(&today, &branches)
+            .await
+            .unwrap();
+        let keys: Vec<&str> = report.iter().map(|s| s.key.as_str()).collect();
+        assert_eq!(report.len(), 3);
+        assert!(keys.contains(&"ACME-123"));
+        assert!(keys.contains(&"main"));
+        assert!(keys.contains(&"feature/search"));
+    }
+
+    #[tokio::test]
+    async fn test_ticket_tags_follow_branch() {
+        let (project_service, tracking_service, _) = setup_services().await;
+        project_service
+            .create_project("Internal", None)
+            .await
+            .unwrap();
+        let branches =
+            crate::config::GitConfig::parse("[branches]\nenabled = true\nticket_field = \"tag\"")
+                .unwrap()
+                .branches;
+        let app = Some(Path::new("/work/app"));
+
+        let timer = tracking_service
+            .start_timer_on_branch(
+                "Internal",
+                Some("Login"),
+                app,
+                Some("ACME-1-login"),
+                &branches,
+            )
+            .await
+            .unwrap();
+        assert_eq!(timer.tags, vec!["ACME-1"]);
+
+        let switch = tracking_service
+            .switch_branch(app, Some("ACME-2-signup"), &branches)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(switch.stopped.tags, vec!["ACME-1"]);
+        assert_eq!(switch.timer.tags, vec!["ACME-2"]);
+        assert_eq!(switch.timer.task_description.as_deref(), Some("Login"));
+    }
+
+    #[tokio::test]
+    async fn test_switch_branch_ignores_other_repositories() {
+        let first = tempfile::TempDir::new().unwrap();
+        let second = tempfile::TempDir::new().unwrap();
+        let me = ("Me", "me@example.com");
+        let first_repo = git2::Repository::init(first.path()).unwrap();
+        let second_repo = git2::Repository::init(second.path()).unwrap();
+        let first_commit = commit_file(&first_repo, me, Utc::now(), "first");
+        let second_commit = commit_file(&second_repo, me, Utc::now(), "second");
+        let first_commit = first_repo.find_commit(first_commit).unwrap();
+        first_repo
+            .branch("feature/a", &first_commit, false)
+            .unwrap();
+        let second_commit = second_repo.find_commit(second_commit).unwrap();
+        second_repo
+            .branch("feature/b", &second_commit, false)
+            .unwrap();
+        second_repo.set_head("refs/heads/feature/b").unwrap();
+
+        let repo = Arc::new(SqliteRepository::in_memory().unwrap());
+        let project_service = ProjectService::new(repo.clone());
+        let tracking_service = TimeTrackingService::new(repo.clone());
+        let git_service = GitService::new(repo.clone());
+        project_service.create_project("App", None).await.unwrap();
+        let branches = crate::config::GitConfig::parse("[branches]\nenabled = true")
+            .unwrap()
+            .branches;
+        let switch_in = |dir: &Path| {
+            let repository = git_service.repository_root(dir);
+            let branch = git_service.current_branch(dir);
+            let tracking_service = &tracking_service;
+            let branches = &branches;
+            async move {
+                tracking_service
+                    .switch_branch(repository.as_deref(), branch.as_deref(), branches)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let start_dir = first.path().to_path_buf();
+        let timer = tracking_service
+            .start_timer_on_branch(
+                "App",
+                None,
+                git_service.repository_root(&start_dir).as_deref(),
+                git_service.current_branch(&start_dir).as_deref(),
+                &branches,
+            )
+            .await
+            .unwrap();
+        assert!(timer.repository.is_some());
+
+        // Status from another repository on another branch keeps the timer
+        assert!(switch_in(second.path()).await.is_none());
+        let active = repo.get_active_timer().await.unwrap().unwrap();
+        assert_eq!(active.id, timer.id);
+
+        // Checking out a branch in the timer's own repository splits it
+        first_repo.set_head("refs/heads/feature/a").unwrap();
+        let switch = switch_in(first.path()).await.unwrap();
+        assert_eq!(switch.timer.branch.as_deref(), Some("feature/a"));
+        assert_eq!(switch.timer.repository, timer.repository);
+    }
+
     #[tokio::test]
     async fn test_start_timer_when_already_running() {
         let (project_service, tracking_service, _) = setup_services().await;
@@ -427,6 +1787,153 @@ mod tests {
         assert_eq!(entry.tags, vec!["development"]);
     }
 
+    #[tokio::test]
+    async fn test_apply_rounding_uses_project_override() {
+        use crate::models::{RoundingMode, RoundingScope};
+
+        let (project_service, _, reporting_service) = setup_services().await;
+        project_service
+            .create_project("Billed", None)
+            .await
+            .unwrap();
+        project_service
+            .set_rounding_rule(
+                "Billed",
+                Some(RoundingRule::new(
+                    RoundingMode::Up,
+                    RoundingScope::Entry,
+                    15,
+                )),
+            )
+            .await
+            .unwrap();
+        let project = project_service
+            .get_project("Billed")
+            .await
+            .unwrap()
+            .unwrap();
+
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap();
+        let mut entry = TimeEntry::new(project.id, project.name.clone(), None, start);
+        entry.stop(start + chrono::Duration::minutes(20)).unwrap();
+        let mut report = TimeReport::new(vec![entry], start, start);
+
+        reporting_service
+            .apply_rounding(&mut report, None)
+            .await
+            .unwrap();
+
+        assert_eq!(report.total_duration, chrono::Duration::minutes(20));
+        assert_eq!(
+            report.rounded_total_duration,
+            Some(chrono::Duration::minutes(30))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generate_daily_totals() {
+        let (project_service, _, reporting_service) = setup_services().await;
+        let project = project_service.create_project("Test", None).await.unwrap();
+
+        let start = Utc.with_ymd_and_hms(2024, 1, 2, 9, 0, 0).unwrap();
+        let mut entry = TimeEntry::new(project.id, project.name.clone(), None, start);
+        entry.stop(start + chrono::Duration::minutes(45)).unwrap();
+        reporting_service
+            .repository
+            .create_time_entry(&entry)
+            .await
+            .unwrap();
+
+        let totals = reporting_service
+            .generate_daily_totals(
+                chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                chrono::NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(totals.len(), 3);
+        assert_eq!(
+            totals[&chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()],
+            chrono::Duration::minutes(45)
+        );
+        assert_eq!(
+            totals[&chrono::NaiveDate::from_ymd_opt(2024, 1, 3).unwrap()],
+            chrono::Duration::zero()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compare_periods_month() {
+        let (project_service, _, reporting_service) = setup_services().await;
+        let project = project_service.create_project("Test", None).await.unwrap();
+
+        for (start, minutes) in [
+            (Utc.with_ymd_and_hms(2024, 1, 31, 9, 0, 0).unwrap(), 60),
+            (Utc.with_ymd_and_hms(2024, 2, 1, 9, 0, 0).unwrap(), 30),
+            (Utc.with_ymd_and_hms(2024, 3, 10, 9, 0, 0).unwrap(), 45),
+        ] {
+            let mut entry = TimeEntry::new(project.id, project.name.clone(), None, start);
+            entry
+                .stop(start + chrono::Duration::minutes(minutes))
+                .unwrap();
+            reporting_service
+                .repository
+                .create_time_entry(&entry)
+                .await
+                .unwrap();
+        }
+
+        let date = Utc.with_ymd_and_hms(2024, 3, 15, 12, 0, 0).unwrap();
+        let comparison = reporting_service
+            .compare_periods(ReportPeriod::Month, date, 2)
+            .await
+            .unwrap();
+
+        assert_eq!(comparison.current_seconds, 45 * 60);
+        assert_eq!(comparison.previous_seconds, 60 * 60);
+        assert_eq!(comparison.percent_change, Some(-25.0));
+        assert_eq!(
+            comparison.previous_range.start.date_naive(),
+            chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+        );
+
+        assert!(reporting_service
+            .compare_periods(ReportPeriod::Week, date, 0)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_timesheet_csv_export() {
+        let (project_service, _, reporting_service) = setup_services().await;
+        let project = project_service
+            .create_project("Acme, Inc", None)
+            .await
+            .unwrap();
+
+        let start = Utc.with_ymd_and_hms(2024, 1, 3, 9, 0, 0).unwrap();
+        let mut entry = TimeEntry::new(project.id, project.name.clone(), None, start);
+        entry.stop(start + chrono::Duration::minutes(90)).unwrap();
+        reporting_service
+            .repository
+            .create_time_entry(&entry)
+            .await
+            .unwrap();
+
+        let timesheet = reporting_service.generate_timesheet(start).await.unwrap();
+        let csv = reporting_service.export_timesheet_csv(&timesheet, true);
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("Project,2024-01-01,"));
+        assert_eq!(
+            lines[1],
+            "\"Acme, Inc\",0.00,0.00,1.50,0.00,0.00,0.00,0.00,1.50"
+        );
+        assert!(lines[2].starts_with("Total,"));
+    }
+
     #[tokio::test]
     async fn test_export_report_json() {
         let (_, _, reporting_service) = setup_services().await;
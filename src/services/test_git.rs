@@ -0,0 +1,65 @@
+//! Helpers for tests that build small git histories
+
+use chrono::{DateTime, Utc};
+use std::path::Path;
+
+/// Commit a change to `lib.rs` on HEAD as the given author
+pub(crate) fn commit_file(
+    git_repo: &git2::Repository,
+    author: (&str, &str),
+    at: DateTime<Utc>,
+    message: &str,
+) -> git2::Oid {
+    let content = format!("// {}\n", message);
+    commit_changes(
+        git_repo,
+        author,
+        at,
+        message,
+        &[("lib.rs", Some(content.as_bytes()))],
+    )
+}
+
+/// Commit files on HEAD; `None` content removes the file
+pub(crate) fn commit_changes(
+    git_repo: &git2::Repository,
+    (name, email): (&str, &str),
+    at: DateTime<Utc>,
+    message: &str,
+    changes: &[(&str, Option<&[u8]>)],
+) -> git2::Oid {
+    let workdir = git_repo.workdir().unwrap();
+    let mut index = git_repo.index().unwrap();
+    for (file, content) in changes {
+        match content {
+            Some(content) => {
+                std::fs::write(workdir.join(file), content).unwrap();
+                index.add_path(Path::new(file)).unwrap();
+            }
+            None => {
+                std::fs::remove_file(workdir.join(file)).unwrap();
+                index.remove_path(Path::new(file)).unwrap();
+            }
+        }
+    }
+    index.write().unwrap();
+    let tree = git_repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = git2::Signature::new(name, email, &git2::Time::new(at.timestamp(), 0)).unwrap();
+    let parents: Vec<git2::Commit> = git_repo
+        .head()
+        .ok()
+        .and_then(|head| head.peel_to_commit().ok())
+        .into_iter()
+        .collect();
+    let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+    git_repo
+        .commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parent_refs,
+        )
+        .unwrap()
+}
@@ -99,6 +99,7 @@ mod sensitive_data_tests {
 
     /// Test to ensure no sensitive data in source code
     #[test]
+    #[allow(clippy::unnecessary_map_or)]
     fn test_no_sensitive_data_in_source_code() {
         let repo_root = find_repo_root().expect("Could not find repository root");
         let src_dir = repo_root.join("src");